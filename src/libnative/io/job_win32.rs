@@ -0,0 +1,188 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Job Objects, which group a child with all of its descendants
+//!
+//! A process is assigned to a job right after it is created suspended, before
+//! it had a chance to start any children of its own, so that the limits of
+//! the job apply to the whole tree of processes.
+
+use std::io::process::JobLimits;
+use std::libc::{HANDLE, DWORD};
+use std::libc;
+use std::mem;
+use std::ptr;
+use std::unstable::intrinsics;
+
+use super::IoResult;
+
+pub struct Job {
+    priv handle: HANDLE,
+    priv kill_on_close: bool,
+}
+
+impl Job {
+    /// Creates a new anonymous job enforcing `limits`.
+    pub fn new(limits: &JobLimits) -> IoResult<Job> {
+        let handle = unsafe { imp::CreateJobObjectA(ptr::mut_null(), ptr::null()) };
+        if handle.is_null() {
+            return Err(super::last_error());
+        }
+        let job = Job { handle: handle, kill_on_close: limits.kill_on_close };
+
+        let mut info: imp::JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe {
+            intrinsics::init()
+        };
+        let mut flags = 0;
+        for &n in limits.active_processes.iter() {
+            flags |= imp::JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+            info.BasicLimitInformation.ActiveProcessLimit = n as DWORD;
+        }
+        for &n in limits.process_memory.iter() {
+            flags |= imp::JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+            info.ProcessMemoryLimit = n as libc::SIZE_T;
+        }
+        for &n in limits.job_memory.iter() {
+            flags |= imp::JOB_OBJECT_LIMIT_JOB_MEMORY;
+            info.JobMemoryLimit = n as libc::SIZE_T;
+        }
+        if limits.allow_breakaway {
+            flags |= imp::JOB_OBJECT_LIMIT_BREAKAWAY_OK;
+        }
+        if limits.kill_on_close {
+            flags |= imp::JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        }
+        if flags != 0 {
+            info.BasicLimitInformation.LimitFlags = flags;
+            match job.set(imp::JobObjectExtendedLimitInformation, &info) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        for &percent in limits.cpu_rate.iter() {
+            let info = imp::JOBOBJECT_CPU_RATE_CONTROL_INFORMATION {
+                ControlFlags: imp::JOB_OBJECT_CPU_RATE_CONTROL_ENABLE |
+                              imp::JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+                // in hundredths of a percent
+                CpuRate: (percent * 100) as DWORD,
+            };
+            match job.set(imp::JobObjectCpuRateControlInformation, &info) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(job)
+    }
+
+    /// Assigns the process `process` to this job.
+    pub fn assign(&self, process: HANDLE) -> IoResult<()> {
+        match unsafe { imp::AssignProcessToJobObject(self.handle, process) } {
+            0 => Err(super::last_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether all processes of this job are terminated when it is closed,
+    /// in which case they are also terminated together when the process is
+    /// killed.
+    pub fn kills_on_close(&self) -> bool { self.kill_on_close }
+
+    /// Terminates all processes of this job with the exit code `code`.
+    pub fn terminate(&self, code: libc::c_uint) -> IoResult<()> {
+        match unsafe { imp::TerminateJobObject(self.handle, code) } {
+            0 => Err(super::last_error()),
+            _ => Ok(()),
+        }
+    }
+
+    fn set<T>(&self, class: libc::c_int, info: &T) -> IoResult<()> {
+        let ret = unsafe {
+            imp::SetInformationJobObject(self.handle, class,
+                                         info as *T as libc::LPVOID,
+                                         mem::size_of::<T>() as DWORD)
+        };
+        match ret {
+            0 => Err(super::last_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe { libc::CloseHandle(self.handle); }
+    }
+}
+
+#[allow(non_camel_case_types)]
+mod imp {
+    use std::libc::{LPSECURITY_ATTRIBUTES, BOOL, LPCSTR, HANDLE, LARGE_INTEGER,
+                    LPVOID, DWORD, SIZE_T, c_int, c_uint};
+
+    pub static JobObjectExtendedLimitInformation: c_int = 9;
+    pub static JobObjectCpuRateControlInformation: c_int = 15;
+
+    pub static JOB_OBJECT_LIMIT_ACTIVE_PROCESS: DWORD = 0x00000008;
+    pub static JOB_OBJECT_LIMIT_PROCESS_MEMORY: DWORD = 0x00000100;
+    pub static JOB_OBJECT_LIMIT_JOB_MEMORY: DWORD = 0x00000200;
+    pub static JOB_OBJECT_LIMIT_BREAKAWAY_OK: DWORD = 0x00000800;
+    pub static JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE: DWORD = 0x00002000;
+
+    pub static JOB_OBJECT_CPU_RATE_CONTROL_ENABLE: DWORD = 0x1;
+    pub static JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP: DWORD = 0x4;
+
+    pub struct JOBOBJECT_BASIC_LIMIT_INFORMATION {
+        PerProcessUserTimeLimit: LARGE_INTEGER,
+        PerJobUserTimeLimit: LARGE_INTEGER,
+        LimitFlags: DWORD,
+        MinimumWorkingSetSize: SIZE_T,
+        MaximumWorkingSetSize: SIZE_T,
+        ActiveProcessLimit: DWORD,
+        Affinity: SIZE_T,
+        PriorityClass: DWORD,
+        SchedulingClass: DWORD,
+    }
+
+    pub struct IO_COUNTERS {
+        ReadOperationCount: u64,
+        WriteOperationCount: u64,
+        OtherOperationCount: u64,
+        ReadTransferCount: u64,
+        WriteTransferCount: u64,
+        OtherTransferCount: u64,
+    }
+
+    pub struct JOBOBJECT_EXTENDED_LIMIT_INFORMATION {
+        BasicLimitInformation: JOBOBJECT_BASIC_LIMIT_INFORMATION,
+        IoInfo: IO_COUNTERS,
+        ProcessMemoryLimit: SIZE_T,
+        JobMemoryLimit: SIZE_T,
+        PeakProcessMemoryUsed: SIZE_T,
+        PeakJobMemoryUsed: SIZE_T,
+    }
+
+    pub struct JOBOBJECT_CPU_RATE_CONTROL_INFORMATION {
+        ControlFlags: DWORD,
+        CpuRate: DWORD,
+    }
+
+    extern "system" {
+        pub fn CreateJobObjectA(lpJobAttributes: LPSECURITY_ATTRIBUTES,
+                                lpName: LPCSTR) -> HANDLE;
+        pub fn SetInformationJobObject(hJob: HANDLE,
+                                       JobObjectInfoClass: c_int,
+                                       lpJobObjectInfo: LPVOID,
+                                       cbJobObjectInfoLength: DWORD) -> BOOL;
+        pub fn AssignProcessToJobObject(hJob: HANDLE, hProcess: HANDLE) -> BOOL;
+        pub fn TerminateJobObject(hJob: HANDLE, uExitCode: c_uint) -> BOOL;
+    }
+}
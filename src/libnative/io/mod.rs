@@ -63,6 +63,34 @@ pub mod timer;
 
 mod timer_helper;
 
+#[cfg(target_os = "win32")]
+#[path = "job_win32.rs"]
+mod job;
+
+#[cfg(target_os = "win32")]
+#[path = "startup_win32.rs"]
+mod startup;
+
+#[cfg(target_os = "freebsd")]
+#[path = "procctl_freebsd.rs"]
+mod procctl;
+
+#[cfg(target_os = "linux")]
+#[path = "prctl_linux.rs"]
+mod prctl;
+
+#[cfg(target_os = "linux")]
+#[path = "namespace_linux.rs"]
+mod namespace;
+
+#[cfg(target_os = "linux")]
+#[path = "pidfd_linux.rs"]
+mod pidfd;
+
+#[cfg(unix)]
+#[path = "pty_unix.rs"]
+mod pty;
+
 pub type IoResult<T> = Result<T, IoError>;
 
 fn unimpl() -> IoError {
@@ -76,6 +104,7 @@ fn unimpl() -> IoError {
 fn translate_error(errno: i32, detail: bool) -> IoError {
     #[cfg(windows)]
     fn get_err(errno: i32) -> (io::IoErrorKind, &'static str) {
+        static ERROR_BAD_EXE_FORMAT: i32 = 193;
         match errno {
             libc::EOF => (io::EndOfFile, "end of file"),
             libc::WSAECONNREFUSED => (io::ConnectionRefused, "connection refused"),
@@ -87,6 +116,7 @@ fn translate_error(errno: i32, detail: bool) -> IoError {
             libc::WSAECONNABORTED => (io::ConnectionAborted, "connection aborted"),
             libc::WSAEADDRNOTAVAIL => (io::ConnectionRefused, "address not available"),
             libc::WSAEADDRINUSE => (io::ConnectionRefused, "address in use"),
+            ERROR_BAD_EXE_FORMAT => (io::NotExecutable, "exec format error"),
 
             x => {
                 debug!("ignoring {}: {}", x, os::last_os_error());
@@ -109,6 +139,7 @@ fn translate_error(errno: i32, detail: bool) -> IoError {
             libc::ECONNABORTED => (io::ConnectionAborted, "connection aborted"),
             libc::EADDRNOTAVAIL => (io::ConnectionRefused, "address not available"),
             libc::EADDRINUSE => (io::ConnectionRefused, "address in use"),
+            libc::ENOEXEC => (io::NotExecutable, "exec format error"),
 
             // These two constants can have the same value on some systems, but
             // different values on others, so we can't use a match clause
@@ -271,10 +302,12 @@ impl rtio::IoFactory for IoFactory {
     fn spawn(&mut self, config: ProcessConfig)
             -> IoResult<(~RtioProcess, ~[Option<~RtioPipe>])> {
         process::Process::spawn(config).map(|(p, io)| {
-            (~p as ~RtioProcess,
-             io.move_iter().map(|p| p.map(|p| ~p as ~RtioPipe)).collect())
+            (~p as ~RtioProcess, io)
         })
     }
+    fn adopt_process(&mut self, pid: libc::pid_t) -> IoResult<~RtioProcess> {
+        process::Process::adopt(pid).map(|p| ~p as ~RtioProcess)
+    }
     fn pipe_open(&mut self, fd: c_int) -> IoResult<~RtioPipe> {
         Ok(~file::FileDesc::new(fd, true) as ~RtioPipe)
     }
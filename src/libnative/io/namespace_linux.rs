@@ -0,0 +1,252 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Isolation of a child in namespaces of its own on Linux
+//!
+//! The parent prepares an `Isolation` before it forks, and the child enters
+//! the new namespaces and sets them up before it executes the program. When
+//! the current process is not root, the child enters a new user namespace
+//! first, in which it is root with its own uid and gid mapped, so that
+//! isolation works without privileges.
+//!
+//! Only the children of a process are created in a new pid namespace, so a
+//! child entering one forks once more. The grandchild executes the program as
+//! the first process of the namespace, and the child stays in between. The
+//! child hands the id of the grandchild to the parent, which sends its
+//! signals to the program directly.
+
+use std::c_str::CString;
+use std::io::process::ProcessConfig;
+use std::libc::{c_char, c_int, c_ulong, c_void, size_t, ssize_t};
+use std::libc::funcs::posix01::wait::waitpid;
+use std::libc::funcs::posix88::signal::kill;
+use std::libc::funcs::posix88::unistd::fork;
+use std::libc;
+use std::os;
+use std::ptr;
+
+/// The namespaces a child enters, and how it sets them up.
+pub struct Isolation {
+    priv flags: c_int,
+    priv uid_map: ~str,
+    priv gid_map: ~str,
+    priv private_tmp: bool,
+    priv read_only: ~[CString],
+}
+
+static MOUNT_PROC_FLAGS: c_ulong = imp::MS_NOSUID | imp::MS_NODEV | imp::MS_NOEXEC;
+
+/// Describes the isolation asked for by `config`, if any.
+pub fn prepare(config: &ProcessConfig) -> Option<Isolation> {
+    let mut flags = 0;
+    let mut private_tmp = false;
+    let mut read_only = ~[];
+    for fs in config.fs_isolation.iter() {
+        flags |= imp::CLONE_NEWNS;
+        private_tmp = fs.private_tmp;
+        read_only = fs.read_only.map(|p| p.to_c_str());
+    }
+    if config.no_network {
+        flags |= imp::CLONE_NEWNET;
+    }
+    let ns = &config.namespaces;
+    let asked = [(ns.mount, imp::CLONE_NEWNS), (ns.pid, imp::CLONE_NEWPID),
+                 (ns.net, imp::CLONE_NEWNET), (ns.ipc, imp::CLONE_NEWIPC),
+                 (ns.uts, imp::CLONE_NEWUTS)];
+    for &(wanted, flag) in asked.iter() {
+        if wanted {
+            flags |= flag;
+        }
+    }
+    if flags == 0 {
+        return None
+    }
+    let (uid, gid) = unsafe {
+        (libc::funcs::posix88::unistd::getuid(), libc::funcs::posix88::unistd::getgid())
+    };
+    if unsafe { libc::funcs::posix88::unistd::geteuid() } != 0 {
+        flags |= imp::CLONE_NEWUSER;
+    }
+    Some(Isolation {
+        flags: flags,
+        uid_map: format!("{} {} 1", uid, uid),
+        gid_map: format!("{} {} 1", gid, gid),
+        private_tmp: private_tmp,
+        read_only: read_only,
+    })
+}
+
+/// Makes the current process enter the namespaces of `isolation` and sets
+/// them up, returning -1 and leaving `errno` set if it could not.
+pub unsafe fn apply(isolation: &Isolation) -> c_int {
+    if imp::unshare(isolation.flags) == -1 {
+        return -1
+    }
+    if isolation.flags & imp::CLONE_NEWUSER != 0 {
+        // The groups can only be mapped once setgroups is denied, which
+        // kernels older than 3.19 have no knob for.
+        write_file("/proc/self/setgroups", "deny");
+        if !write_file("/proc/self/uid_map", isolation.uid_map) ||
+           !write_file("/proc/self/gid_map", isolation.gid_map) {
+            return -1
+        }
+    }
+    if isolation.flags & imp::CLONE_NEWNS != 0 {
+        // Keep the mounts of the child from propagating back to the parent.
+        if mount(None, "/", None, imp::MS_REC | imp::MS_PRIVATE) == -1 {
+            return -1
+        }
+        if isolation.private_tmp &&
+           mount(Some("tmpfs"), "/tmp", Some("tmpfs"), imp::MS_NOSUID | imp::MS_NODEV) == -1 {
+            return -1
+        }
+        for path in isolation.read_only.iter() {
+            if make_read_only(path) == -1 {
+                return -1
+            }
+        }
+    }
+    0
+}
+
+/// Returns whether `isolation` has the child enter a new pid namespace, in
+/// which it forks again.
+pub fn enters_pid_namespace(isolation: &Isolation) -> bool {
+    isolation.flags & imp::CLONE_NEWPID != 0
+}
+
+/// Forks the current process if `isolation` has it enter a new pid
+/// namespace, returning in the child, which is the first process of the
+/// namespace. The current process writes the id of the child to `pid_fd`,
+/// closes `report_fd`, through which only the child reports failures to
+/// execute the program, waits for the child and exits like it. The child is
+/// killed if it dies first.
+///
+/// This returns -1 and leaves `errno` set if the fork or the setup of the
+/// child failed, in whichever process is left to report it.
+pub unsafe fn fork_into_pid_namespace(isolation: &Isolation, report_fd: c_int,
+                                      pid_fd: c_int) -> c_int {
+    if !enters_pid_namespace(isolation) {
+        return 0
+    }
+    let pid = fork();
+    if pid < 0 {
+        return -1
+    } else if pid == 0 {
+        libc::close(pid_fd);
+        if imp::prctl(imp::PR_SET_PDEATHSIG, libc::SIGKILL as c_ulong, 0, 0, 0) == -1 {
+            return -1
+        }
+        // The /proc of the parent namespace shows the processes outside.
+        if isolation.flags & imp::CLONE_NEWNS != 0 &&
+           mount(Some("proc"), "/proc", Some("proc"), MOUNT_PROC_FLAGS) == -1 {
+            return -1
+        }
+        return 0
+    }
+
+    // The id is handed over before the parent can see the end of the
+    // reports, so it is there once the program is known to run.
+    let bytes = [(pid >> 24) as u8, (pid >> 16) as u8, (pid >> 8) as u8, pid as u8];
+    libc::write(pid_fd, bytes.as_ptr() as *c_void, 4);
+    libc::close(pid_fd);
+    libc::close(report_fd);
+    let mut status = 0 as c_int;
+    while waitpid(pid, &mut status, 0) == -1 {
+        if os::errno() as c_int != libc::EINTR {
+            imp::_exit(127);
+        }
+    }
+    if status & 0x7f == 0 {
+        imp::_exit((status >> 8) & 0xff);
+    }
+    let signal = status & 0x7f;
+    imp::rust_reset_signal(signal);
+    kill(libc::getpid(), signal);
+    imp::_exit(128 + signal);
+}
+
+/// Remounts `path` and everything below it read-only.
+unsafe fn make_read_only(path: &CString) -> c_int {
+    let target = path.with_ref(|p| p);
+    if imp::mount(target, target, ptr::null(), imp::MS_BIND | imp::MS_REC, ptr::null()) == -1 {
+        return -1
+    }
+    // In a user namespace, the flags locked by the parent namespace must be
+    // kept when remounting.
+    let flags = imp::MS_BIND | imp::MS_REMOUNT | imp::MS_RDONLY;
+    if imp::mount(ptr::null(), target, ptr::null(), flags, ptr::null()) == -1 &&
+       imp::mount(ptr::null(), target, ptr::null(), flags | imp::MS_NOSUID | imp::MS_NODEV,
+                  ptr::null()) == -1 {
+        return -1
+    }
+    0
+}
+
+unsafe fn mount(source: Option<&str>, target: &str, fstype: Option<&str>,
+                flags: c_ulong) -> c_int {
+    with_opt_c_str(source, |source| {
+        with_opt_c_str(fstype, |fstype| {
+            target.with_c_str(|target| {
+                imp::mount(source, target, fstype, flags, ptr::null())
+            })
+        })
+    })
+}
+
+fn with_opt_c_str<T>(s: Option<&str>, f: |*c_char| -> T) -> T {
+    match s {
+        Some(s) => s.with_c_str(f),
+        None => f(ptr::null()),
+    }
+}
+
+/// Writes `data` to the file at `path`, returning whether it all got written.
+unsafe fn write_file(path: &str, data: &str) -> bool {
+    let fd = path.with_c_str(|p| libc::open(p, libc::O_WRONLY, 0));
+    if fd == -1 {
+        return false
+    }
+    let ret = libc::write(fd, data.as_ptr() as *c_void, data.len() as size_t);
+    libc::close(fd);
+    ret == data.len() as ssize_t
+}
+
+mod imp {
+    use std::libc::{c_char, c_int, c_ulong, c_void};
+
+    pub static CLONE_NEWNS: c_int = 0x00020000;
+    pub static CLONE_NEWNET: c_int = 0x40000000;
+    pub static CLONE_NEWUSER: c_int = 0x10000000;
+    pub static CLONE_NEWPID: c_int = 0x20000000;
+    pub static CLONE_NEWIPC: c_int = 0x08000000;
+    pub static CLONE_NEWUTS: c_int = 0x04000000;
+
+    pub static PR_SET_PDEATHSIG: c_int = 1;
+
+    pub static MS_RDONLY: c_ulong = 1;
+    pub static MS_NOSUID: c_ulong = 2;
+    pub static MS_NODEV: c_ulong = 4;
+    pub static MS_NOEXEC: c_ulong = 8;
+    pub static MS_REMOUNT: c_ulong = 32;
+    pub static MS_BIND: c_ulong = 4096;
+    pub static MS_REC: c_ulong = 16384;
+    pub static MS_PRIVATE: c_ulong = 1 << 18;
+
+    extern {
+        pub fn unshare(flags: c_int) -> c_int;
+        pub fn prctl(option: c_int, arg2: c_ulong, arg3: c_ulong, arg4: c_ulong,
+                     arg5: c_ulong) -> c_int;
+        pub fn _exit(status: c_int) -> !;
+        pub fn rust_reset_signal(signum: c_int);
+        pub fn mount(source: *c_char, target: *c_char, fstype: *c_char,
+                     flags: c_ulong, data: *c_void) -> c_int;
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Process file descriptors on Linux
+//!
+//! A pidfd refers to one process for as long as the descriptor is open, even
+//! after the process exited and its id was reused, so signals sent through it
+//! can never reach another process. It becomes readable once the process
+//! exits, which lets an event loop wait for the exit with `poll`. Kernels
+//! older than 5.3 have no `pidfd_open`, in which case children are signaled
+//! by their id as before.
+
+use std::cmp;
+use std::i32;
+use std::libc::{c_int, c_long, pid_t};
+use std::ptr;
+
+use super::IoResult;
+use super::retry;
+
+// The numbers of these system calls are the same on every architecture.
+static SYS_PIDFD_SEND_SIGNAL: c_long = 424;
+static SYS_PIDFD_OPEN: c_long = 434;
+
+/// Opens a pidfd for the process `pid`, which is closed on exec. Returns None
+/// if the kernel does not support pidfds.
+pub fn open(pid: pid_t) -> Option<c_int> {
+    match unsafe { imp::syscall(SYS_PIDFD_OPEN, pid as c_long, 0, 0, 0) } {
+        -1 => None,
+        fd => Some(fd as c_int),
+    }
+}
+
+/// Sends `signal` to the process referred to by `pidfd`.
+pub fn send_signal(pidfd: c_int, signal: int) -> IoResult<()> {
+    let null = ptr::null::<()>() as c_long;
+    match unsafe {
+        imp::syscall(SYS_PIDFD_SEND_SIGNAL, pidfd as c_long, signal as c_long, null, 0)
+    } {
+        -1 => Err(super::last_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Waits up to `msecs` milliseconds for the process referred to by `pidfd` to
+/// exit, returning false if it could not be waited for.
+pub fn wait(pidfd: c_int, msecs: u64) -> bool {
+    static POLLIN: i16 = 1;
+
+    let mut fds = imp::pollfd { fd: pidfd, events: POLLIN, revents: 0 };
+    let msecs = cmp::min(msecs, i32::MAX as u64) as c_int;
+    retry(|| unsafe { imp::poll(&mut fds, 1, msecs) }) != -1
+}
+
+mod imp {
+    use std::libc::{c_int, c_long, c_ulong};
+
+    pub struct pollfd {
+        fd: c_int,
+        events: i16,
+        revents: i16,
+    }
+
+    extern {
+        pub fn syscall(number: c_long, arg1: c_long, arg2: c_long, arg3: c_long,
+                       arg4: c_long) -> c_long;
+        pub fn poll(fds: *mut pollfd, nfds: c_ulong, timeout: c_int) -> c_int;
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Restrictions a child applies to itself on Linux with `prctl` and friends
+//!
+//! These are all applied by the child after it forked and before it executes
+//! the program, when only the child is affected by them. They return -1 and
+//! leave `errno` set on failure, for the child to report to its parent.
+
+use std::io::process::Capabilities;
+use std::libc::{c_int, c_ulong, pid_t};
+use std::libc;
+
+/// Drops the capabilities which `capabilities` does not keep from the
+/// bounding set of the current process, if asked. This needs CAP_SETPCAP, and
+/// leaves the capabilities the process has until it executes the program.
+pub unsafe fn drop_bounding_set(capabilities: &Capabilities) -> c_int {
+    if !capabilities.bound {
+        return 0
+    }
+    let keep = kept(capabilities);
+    for cap in range(0u, 64) {
+        if keep[cap / 32] & (1 << (cap % 32)) != 0 ||
+           prctl(imp::PR_CAPBSET_READ, cap as c_ulong) != 1 {
+            continue
+        }
+        if prctl(imp::PR_CAPBSET_DROP, cap as c_ulong) == -1 {
+            return -1
+        }
+    }
+    0
+}
+
+/// Reduces the capability sets of the current process to the capabilities
+/// kept by `capabilities`.
+pub unsafe fn reduce_capabilities(capabilities: &Capabilities) -> c_int {
+    let keep = kept(capabilities);
+    let mut header = imp::cap_header { version: imp::LINUX_CAPABILITY_VERSION_3, pid: 0 };
+    let mut data = [imp::cap_data { effective: 0, permitted: 0, inheritable: 0 }, ..2];
+    if imp::capget(&mut header, data.as_mut_ptr()) == -1 {
+        return -1
+    }
+    for (d, &keep) in data.mut_iter().zip(keep.iter()) {
+        d.effective &= keep;
+        d.permitted &= keep;
+        d.inheritable &= keep;
+    }
+    imp::capset(&mut header, data.as_ptr())
+}
+
+/// Returns the capabilities kept by `capabilities`, as the two words of a
+/// capability set.
+fn kept(capabilities: &Capabilities) -> [u32, ..2] {
+    let mut keep = [0u32, ..2];
+    for &cap in capabilities.keep.iter() {
+        if cap < 64 {
+            keep[cap / 32] |= 1 << (cap % 32);
+        }
+    }
+    keep
+}
+
+/// Bars the current process and everything it executes from gaining
+/// privileges.
+pub unsafe fn set_no_new_privs() -> c_int {
+    prctl(imp::PR_SET_NO_NEW_PRIVS, 1)
+}
+
+/// Asks for `signum` to be delivered to the current process when the thread
+/// which forked it exits. `parent` is the pid of the process which forked it.
+pub unsafe fn set_parent_death_signal(signum: int, parent: pid_t) -> c_int {
+    if prctl(imp::PR_SET_PDEATHSIG, signum as c_ulong) == -1 {
+        return -1
+    }
+    // The parent may have exited before the signal was asked for, in which
+    // case it would never be delivered.
+    if libc::funcs::posix88::unistd::getppid() != parent {
+        libc::funcs::posix88::signal::kill(libc::getpid(), signum as c_int);
+    }
+    0
+}
+
+unsafe fn prctl(option: c_int, arg: c_ulong) -> c_int {
+    imp::prctl(option, arg, 0, 0, 0)
+}
+
+#[allow(non_camel_case_types)]
+mod imp {
+    use std::libc::{c_int, c_ulong};
+
+    pub static PR_SET_PDEATHSIG: c_int = 1;
+    pub static PR_CAPBSET_READ: c_int = 23;
+    pub static PR_CAPBSET_DROP: c_int = 24;
+    pub static PR_SET_NO_NEW_PRIVS: c_int = 38;
+
+    pub static LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+
+    pub struct cap_header {
+        version: u32,
+        pid: c_int,
+    }
+
+    pub struct cap_data {
+        effective: u32,
+        permitted: u32,
+        inheritable: u32,
+    }
+
+    extern {
+        pub fn prctl(option: c_int, arg2: c_ulong, arg3: c_ulong, arg4: c_ulong,
+                     arg5: c_ulong) -> c_int;
+        pub fn capget(header: *mut cap_header, data: *mut cap_data) -> c_int;
+        pub fn capset(header: *mut cap_header, data: *cap_data) -> c_int;
+    }
+}
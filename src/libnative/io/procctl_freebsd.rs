@@ -0,0 +1,89 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Process controls applied with `procctl` on FreeBSD
+//!
+//! Reaper status is acquired by the parent before it forks, while the other
+//! controls are applied by the child to itself before it executes the
+//! program.
+
+use std::io::process::ProcessControls;
+use std::libc::{c_int, c_void, pid_t};
+use std::libc;
+use std::os;
+use std::ptr;
+
+use super::IoResult;
+
+/// Makes the current process the reaper of its descendants, which it may
+/// already be.
+pub fn acquire_reaper() -> IoResult<()> {
+    match unsafe { procctl(libc::getpid(), imp::PROC_REAP_ACQUIRE, ptr::mut_null()) } {
+        -1 if os::errno() as c_int != libc::EBUSY => Err(super::last_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Applies the controls of `controls` which concern the child, from the child
+/// itself. `parent` is the pid of the process which forked it. This returns -1
+/// and leaves `errno` set if a control could not be applied.
+pub unsafe fn apply_to_child(controls: &ProcessControls, parent: pid_t) -> c_int {
+    let me = libc::getpid();
+    if controls.protect {
+        let mut flags = imp::PPROT_SET | imp::PPROT_INHERIT;
+        if procctl(me, imp::PROC_SPROTECT, &mut flags as *mut c_int as *mut c_void) == -1 {
+            return -1
+        }
+    }
+    for &signum in controls.parent_death_signal.iter() {
+        if set_parent_death_signal(signum, parent) == -1 {
+            return -1
+        }
+    }
+    0
+}
+
+/// Asks for `signum` to be delivered to the current process when the process
+/// which forked it, whose pid is `parent`, exits. This returns -1 and leaves
+/// `errno` set if it could not.
+pub unsafe fn set_parent_death_signal(signum: int, parent: pid_t) -> c_int {
+    let me = libc::getpid();
+    let mut signum = signum as c_int;
+    if procctl(me, imp::PROC_PDEATHSIG_CTL, &mut signum as *mut c_int as *mut c_void) == -1 {
+        return -1
+    }
+    // The parent may have exited before the signal was asked for, in which
+    // case it would never be delivered.
+    if libc::funcs::posix88::unistd::getppid() != parent {
+        libc::funcs::posix88::signal::kill(me, signum);
+    }
+    0
+}
+
+unsafe fn procctl(pid: pid_t, cmd: c_int, data: *mut c_void) -> c_int {
+    imp::procctl(imp::P_PID, pid as i64, cmd, data)
+}
+
+mod imp {
+    use std::libc::{c_int, c_void};
+
+    pub static P_PID: c_int = 0;
+
+    pub static PROC_SPROTECT: c_int = 1;
+    pub static PROC_REAP_ACQUIRE: c_int = 2;
+    pub static PROC_PDEATHSIG_CTL: c_int = 11;
+
+    pub static PPROT_SET: c_int = 1;
+    pub static PPROT_INHERIT: c_int = 0x10;
+
+    extern {
+        pub fn procctl(idtype: c_int, id: i64, cmd: c_int, data: *mut c_void) -> c_int;
+    }
+}
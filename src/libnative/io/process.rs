@@ -8,6 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cmp;
 use std::io;
 use std::libc::{pid_t, c_void, c_int};
 use std::libc;
@@ -21,6 +22,7 @@ use super::file;
 
 #[cfg(windows)] use std::cast;
 #[cfg(not(windows))] use super::retry;
+#[cfg(not(windows))] use std::c_str::CString;
 
 /**
  * A value representing a child process.
@@ -40,8 +42,33 @@ pub struct Process {
 
     /// None until finish() is called.
     priv exit_code: Option<p::ProcessExit>,
+
+    /// The job object the process was placed in, if any. This is always None
+    /// on unix.
+    priv job: Option<Job>,
+
+    /// A pidfd referring to the process, if any. This is only ever opened on
+    /// Linux, by kernels which support it.
+    priv pidfd: Option<c_int>,
+
+    /// The resources the process used, collected along with its exit status.
+    /// This is always None on windows, where they are queried from the
+    /// handle instead.
+    priv rusage: Option<p::ResourceUsage>,
+
+    /// Whether the process dumped core when it was killed, which is only
+    /// known once it has been waited for. This is always false on windows.
+    priv core_dumped: bool,
+
+    /// The id of the process running the program, if it is not the process
+    /// itself, as when the process runs it in a new pid namespace. Signals
+    /// are sent to it rather than to the process.
+    priv program_pid: Option<pid_t>,
 }
 
+#[cfg(windows)] type Job = super::job::Job;
+#[cfg(unix)] type Job = ();
+
 impl Process {
     /// Creates a new process using native process-spawning abilities provided
     /// by the OS. Operations on this process will be blocking instead of using
@@ -57,21 +84,111 @@ impl Process {
     ///         environment
     /// * cwd - an optionally specified current working directory of the child,
     ///         defaulting to the parent's current working directory
-    /// * stdin, stdout, stderr - These optionally specified file descriptors
-    ///     dictate where the stdin/out/err of the child process will go. If
-    ///     these are `None`, then this module will bind the input/output to an
-    ///     os pipe instead. This process takes ownership of these file
-    ///     descriptors, closing them upon destruction of the process.
+    /// * io - where each file descriptor of the child, starting with
+    ///     stdin/out/err, will go. Descriptors beyond the standard streams are
+    ///     only supported on unix.
     pub fn spawn(config: p::ProcessConfig)
-        -> Result<(Process, ~[Option<file::FileDesc>]), io::IoError>
+        -> Result<(Process, ~[Option<~rtio::RtioPipe>]), io::IoError>
     {
-        // right now we only handle stdin/stdout/stderr.
-        if config.io.len() > 3 {
-            return Err(super::unimpl());
+        // windows only has handles for stdin/stdout/stderr.
+        if cfg!(windows) && config.io.len() > 3 {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "descriptors beyond the standard streams are not supported on windows",
+                detail: None,
+            });
+        }
+        if cfg!(windows) && (config.uid.is_some() || config.gid.is_some()) {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "setting the user of a process is not supported on windows",
+                detail: None,
+            });
+        }
+        if cfg!(windows) && config.rlimits.len() > 0 {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "resource limits are not supported on windows",
+                detail: None,
+            });
+        }
+        if config.cpu_affinity.is_some() && !cfg!(target_os = "linux") && !cfg!(windows) {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "CPU affinity is not supported on this platform",
+                detail: None,
+            });
+        }
+        if config.namespaces != p::Namespaces::new() && !cfg!(target_os = "linux") {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "namespaces are not supported on this platform",
+                detail: None,
+            });
+        }
+        if cfg!(windows) && config.chroot.is_some() {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "changing the root directory is not supported on windows",
+                detail: None,
+            });
+        }
+        if cfg!(windows) && config.before_exec.is_some() {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "before_exec hooks are not supported on windows",
+                detail: None,
+            });
+        }
+        if config.parent_death_signal.is_some() && !cfg!(target_os = "linux") &&
+           !cfg!(target_os = "freebsd") {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "a signal on the death of the parent is not supported on this platform",
+                detail: None,
+            });
+        }
+        if config.no_network && !cfg!(target_os = "linux") && !cfg!(target_os = "macos") {
+            return Err(io::IoError {
+                kind: io::IoUnavailable,
+                desc: "network isolation is not supported on this platform",
+                detail: None,
+            });
+        }
+        if config.capabilities.map_or(false, |c| !c.bound) && regains_capabilities(&config) {
+            return Err(io::IoError {
+                kind: io::InvalidInput,
+                desc: "the capabilities of a child running as root are only dropped \
+                       along with its bounding set",
+                detail: None,
+            });
+        }
+        if cfg!(unix) && config.vfork && needs_child_setup(&config) {
+            return Err(io::IoError {
+                kind: io::InvalidInput,
+                desc: "only the standard streams are set up with vfork",
+                detail: None,
+            });
         }
 
+        // All of the streams given CreatePty share a single terminal.
+        let uses_pty = config.io.iter().any(|io| {
+            match *io { p::CreatePty => true, _ => false }
+        });
+        let (mut master, slave) = if uses_pty {
+            match open_pty() {
+                Ok((master, slave)) => (Some(master), Some(slave)),
+                Err(e) => return Err(e),
+            }
+        } else {
+            (None, None)
+        };
+        let slave_fd = slave.as_ref().map_or(-1, |s| s.fd());
+
         fn get_io(io: &[p::StdioContainer],
-                  ret: &mut ~[Option<file::FileDesc>],
+                  ret: &mut ~[Option<~rtio::RtioPipe>],
+                  master: &mut Option<file::FileDesc>,
+                  slave_fd: c_int,
                   idx: uint) -> (Option<os::Pipe>, c_int) {
             if idx >= io.len() { return (None, -1); }
             ret.push(None);
@@ -85,52 +202,312 @@ impl Process {
                     } else {
                         (pipe.out, pipe.input)
                     };
-                    ret[idx] = Some(file::FileDesc::new(ours, true));
+                    ret[idx] = Some(~file::FileDesc::new(ours, true) as ~rtio::RtioPipe);
                     (Some(pipe), theirs)
                 }
+                // The master side is handed out for the first of the streams
+                // only.
+                p::CreatePty => {
+                    ret[idx] = master.take().map(pty_master);
+                    (None, slave_fd)
+                }
             }
         }
 
+        // The ends of the pipes which the child uses are closed once it has
+        // been spawned.
         let mut ret_io = ~[];
-        let (in_pipe, in_fd) = get_io(config.io, &mut ret_io, 0);
-        let (out_pipe, out_fd) = get_io(config.io, &mut ret_io, 1);
-        let (err_pipe, err_fd) = get_io(config.io, &mut ret_io, 2);
+        let mut fds = ~[];
+        let mut child_ends = ~[];
+        for idx in range(0, cmp::max(config.io.len(), 3)) {
+            let (pipe, fd) = get_io(config.io, &mut ret_io, &mut master, slave_fd, idx);
+            if pipe.is_some() {
+                child_ends.push(fd);
+            }
+            fds.push(fd);
+        }
+        // A child kept from the terminal does not read from it either.
+        if cfg!(unix) && config.no_ctty && fds[0] != -1 && unsafe { libc::isatty(fds[0]) } == 1 {
+            match open_null_input() {
+                Ok(fd) => {
+                    child_ends.push(fd);
+                    fds[0] = fd;
+                }
+                Err(e) => {
+                    unsafe {
+                        for &fd in child_ends.iter() { libc::close(fd); }
+                    }
+                    return Err(e);
+                }
+            }
+        }
 
-        let env = config.env.map(|a| a.to_owned());
-        let cwd = config.cwd.map(|a| Path::new(a));
-        let res = spawn_process_os(config.program, config.args, env,
-                                   cwd.as_ref(), in_fd, out_fd, err_fd);
+        let res = spawn_process_os(&config, fds);
+
+        // The master only reports the end of the output once the child is the
+        // last to hold the slave side.
+        drop(slave);
 
         unsafe {
-            for pipe in in_pipe.iter() { libc::close(pipe.input); }
-            for pipe in out_pipe.iter() { libc::close(pipe.out); }
-            for pipe in err_pipe.iter() { libc::close(pipe.out); }
+            for &fd in child_ends.iter() { libc::close(fd); }
         }
 
         match res {
             Ok(res) => {
-                Ok((Process { pid: res.pid, handle: res.handle, exit_code: None },
-                    ret_io))
+                Ok((Process {
+                    pid: res.pid,
+                    handle: res.handle,
+                    exit_code: None,
+                    job: res.job,
+                    pidfd: open_pidfd(res.pid),
+                    rusage: None,
+                    core_dumped: false,
+                    program_pid: res.program_pid,
+                }, ret_io))
             }
             Err(e) => Err(e)
         }
     }
+
+    /// Takes over the management of the child `pid` of this process, which
+    /// was spawned by a previous image of this process before it exec'd, or
+    /// by a process which has since handed it over.
+    ///
+    /// On unix an error is returned if `pid` is not a child of this process
+    /// which still has to be waited for. If it has exited already, its exit
+    /// status is collected right away.
+    #[cfg(unix)]
+    pub fn adopt(pid: pid_t) -> IoResult<Process> {
+        let (exit_code, core_dumped, rusage) = match try_waitpid(pid) {
+            Ok(Some((exit_code, core_dumped, rusage))) => {
+                (Some(exit_code), core_dumped, Some(rusage))
+            }
+            Ok(None) => (None, false, None),
+            Err(e) => return Err(e),
+        };
+        let pidfd = if exit_code.is_none() { open_pidfd(pid) } else { None };
+        Ok(Process {
+            pid: pid,
+            handle: ptr::null(),
+            exit_code: exit_code,
+            job: None,
+            pidfd: pidfd,
+            rusage: rusage,
+            core_dumped: core_dumped,
+            program_pid: None,
+        })
+    }
+
+    /// Takes over the management of the child `pid` of this process, which
+    /// was spawned by a previous image of this process before it exec'd, or
+    /// by a process which has since handed it over.
+    ///
+    /// On windows any process which can be opened can be adopted.
+    #[cfg(windows)]
+    pub fn adopt(pid: pid_t) -> IoResult<Process> {
+        use std::libc::types::os::arch::extra::DWORD;
+        use std::libc::consts::os::extra::{FALSE, SYNCHRONIZE, PROCESS_QUERY_INFORMATION,
+                                           PROCESS_TERMINATE};
+        use std::libc::funcs::extra::kernel32::OpenProcess;
+        static PROCESS_SUSPEND_RESUME: DWORD = 0x0800;
+        static PROCESS_VM_READ: DWORD = 0x0010;
+
+        let access = SYNCHRONIZE | PROCESS_QUERY_INFORMATION | PROCESS_TERMINATE |
+                     PROCESS_SUSPEND_RESUME | PROCESS_VM_READ;
+        let handle = unsafe { OpenProcess(access, FALSE, pid as DWORD) };
+        if handle.is_null() {
+            return Err(super::last_error());
+        }
+        Ok(Process {
+            pid: pid,
+            handle: unsafe { cast::transmute(handle) },
+            exit_code: None,
+            job: None,
+            pidfd: None,
+            rusage: None,
+            core_dumped: false,
+            program_pid: None,
+        })
+    }
+
+    /// Returns the resources the exited child used, which were collected by
+    /// `wait4` when it was reaped.
+    #[cfg(unix)]
+    fn usage(&self) -> Option<p::ResourceUsage> { self.rusage }
+
+    /// Returns the resources the exited child used, which windows keeps for
+    /// as long as a handle to the process is open.
+    #[cfg(windows)]
+    fn usage(&self) -> Option<p::ResourceUsage> {
+        use std::libc::types::os::arch::extra::{BOOL, DWORD, HANDLE, SIZE_T, FILETIME};
+        use std::libc::consts::os::extra::FALSE;
+        use std::mem;
+        use std::unstable::intrinsics;
+
+        struct PROCESS_MEMORY_COUNTERS {
+            cb: DWORD,
+            PageFaultCount: DWORD,
+            PeakWorkingSetSize: SIZE_T,
+            WorkingSetSize: SIZE_T,
+            QuotaPeakPagedPoolUsage: SIZE_T,
+            QuotaPagedPoolUsage: SIZE_T,
+            QuotaPeakNonPagedPoolUsage: SIZE_T,
+            QuotaNonPagedPoolUsage: SIZE_T,
+            PagefileUsage: SIZE_T,
+            PeakPagefileUsage: SIZE_T,
+        }
+
+        extern "system" {
+            fn GetProcessTimes(hProcess: HANDLE, lpCreationTime: *mut FILETIME,
+                               lpExitTime: *mut FILETIME, lpKernelTime: *mut FILETIME,
+                               lpUserTime: *mut FILETIME) -> BOOL;
+        }
+        #[link(name = "psapi")]
+        extern "system" {
+            fn GetProcessMemoryInfo(hProcess: HANDLE, ppsmemCounters: *mut PROCESS_MEMORY_COUNTERS,
+                                    cb: DWORD) -> BOOL;
+        }
+
+        // FILETIMEs count in units of 100 nanoseconds.
+        fn micros(t: &FILETIME) -> u64 {
+            ((t.dwHighDateTime as u64 << 32) | t.dwLowDateTime as u64) / 10
+        }
+
+        unsafe {
+            let handle: HANDLE = cast::transmute(self.handle);
+            let mut creation: FILETIME = intrinsics::init();
+            let mut exit: FILETIME = intrinsics::init();
+            let mut kernel: FILETIME = intrinsics::init();
+            let mut user: FILETIME = intrinsics::init();
+            if GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel,
+                               &mut user) == FALSE {
+                return None;
+            }
+            let mut counters: PROCESS_MEMORY_COUNTERS = intrinsics::init();
+            let size = mem::size_of::<PROCESS_MEMORY_COUNTERS>() as DWORD;
+            counters.cb = size;
+            if GetProcessMemoryInfo(handle, &mut counters, size) == FALSE {
+                counters = intrinsics::init();
+            }
+            Some(p::ResourceUsage {
+                user_time: micros(&user),
+                system_time: micros(&kernel),
+                max_rss: counters.PeakWorkingSetSize as u64,
+                minor_faults: counters.PageFaultCount as u64,
+                major_faults: 0,
+            })
+        }
+    }
+
+    /// Stops the child with SIGSTOP, or continues it with SIGCONT.
+    #[cfg(unix)]
+    fn set_paused(&mut self, paused: bool) -> IoResult<()> {
+        use std::rt::rtio::RtioProcess;
+
+        let signum = if paused { SIGSTOP } else { SIGCONT };
+        self.kill(signum as int)
+    }
+
+    /// Suspends or resumes every thread of the child, with the functions of
+    /// ntdll which debuggers have long been using for this.
+    #[cfg(windows)]
+    fn set_paused(&mut self, paused: bool) -> IoResult<()> {
+        use std::libc::types::os::arch::extra::HANDLE;
+
+        #[link(name = "ntdll")]
+        extern "system" {
+            fn NtSuspendProcess(process: HANDLE) -> i32;
+            fn NtResumeProcess(process: HANDLE) -> i32;
+        }
+
+        if self.exit_code.is_some() {
+            return Err(io::IoError {
+                kind: io::OtherIoError,
+                desc: "can't pause or resume an exited process",
+                detail: None,
+            });
+        }
+        let status = unsafe {
+            let handle = cast::transmute(self.handle);
+            if paused { NtSuspendProcess(handle) } else { NtResumeProcess(handle) }
+        };
+        // Failures are the negative NTSTATUS values.
+        if status < 0 {
+            Err(io::IoError {
+                kind: io::OtherIoError,
+                desc: "couldn't pause or resume the process",
+                detail: Some(format!("NTSTATUS {:#x}", status as u32)),
+            })
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl rtio::RtioProcess for Process {
     fn id(&self) -> pid_t { self.pid }
 
+    fn pidfd(&self) -> Option<c_int> { self.pidfd }
+
     fn wait(&mut self) -> p::ProcessExit {
         match self.exit_code {
             Some(code) => code,
             None => {
-                let code = waitpid(self.pid);
+                let (code, core_dumped, rusage) = waitpid(self.pid);
                 self.exit_code = Some(code);
+                self.core_dumped = core_dumped;
+                self.rusage = rusage;
                 code
             }
         }
     }
 
+    fn try_wait(&mut self) -> Option<p::ProcessExit> {
+        if self.exit_code.is_none() {
+            match try_wait_os(self.pid, self.handle) {
+                Some((code, core_dumped, rusage)) => {
+                    self.exit_code = Some(code);
+                    self.core_dumped = core_dumped;
+                    self.rusage = rusage;
+                }
+                None => {}
+            }
+        }
+        self.exit_code
+    }
+
+    fn rusage(&self) -> Option<p::ResourceUsage> {
+        match self.exit_code {
+            Some(..) => self.usage(),
+            None => None,
+        }
+    }
+
+    fn core_dumped(&self) -> bool { self.core_dumped }
+
+    fn pause(&mut self) -> Result<(), io::IoError> { self.set_paused(true) }
+
+    fn resume(&mut self) -> Result<(), io::IoError> { self.set_paused(false) }
+
+    fn wait_timeout(&mut self, msecs: u64) -> Option<p::ProcessExit> {
+        if self.try_wait().is_some() || msecs == 0 {
+            return self.exit_code;
+        }
+        if !wait_exit(self.pid, self.handle, self.pidfd, msecs) {
+            // Without a way to be notified of the exit, the child is polled
+            // at growing intervals instead.
+            let mut waited = 0;
+            let mut delay = 1;
+            while waited < msecs && self.try_wait().is_none() {
+                let delay_now = cmp::min(delay, msecs - waited);
+                super::timer::Timer::sleep(delay_now);
+                waited += delay_now;
+                delay = cmp::min(delay * 2, 100);
+            }
+        }
+        self.try_wait()
+    }
+
     fn kill(&mut self, signum: int) -> Result<(), io::IoError> {
         // if the process has finished, and therefore had waitpid called,
         // and we kill it, then on unix we might ending up killing a
@@ -143,16 +520,51 @@ impl rtio::RtioProcess for Process {
             }),
             None => {}
         }
+        // a job which dies with the process is also killed with it
+        match kill_job(&self.job, signum) {
+            Some(ret) => return ret,
+            None => {}
+        }
+        // the program may run in a child of the process, which does not
+        // forward signals
+        match self.program_pid {
+            Some(pid) => return unsafe { killpid(pid, signum) },
+            None => {}
+        }
+        // a pidfd keeps referring to our child even if its id was reused
+        match self.pidfd {
+            Some(fd) => return send_pidfd_signal(fd, signum),
+            None => {}
+        }
         return unsafe { killpid(self.pid, signum) };
 
         #[cfg(windows)]
         unsafe fn killpid(pid: pid_t, signal: int) -> Result<(), io::IoError> {
+            use std::libc::types::os::arch::extra::{BOOL, DWORD};
+
+            extern "system" {
+                fn GenerateConsoleCtrlEvent(event: DWORD, group: DWORD) -> BOOL;
+            }
+            static SIGINT: int = 2;
+            static SIGBREAK: int = 21;
+            static CTRL_BREAK_EVENT: DWORD = 1;
+
             match signal {
                 io::process::PleaseExitSignal | io::process::MustDieSignal => {
                     libc::funcs::extra::kernel32::TerminateProcess(
                         cast::transmute(pid), 1);
                     Ok(())
                 }
+                // Only Ctrl+Break can be sent to a single process group, and
+                // only to one which shares the console of the current
+                // process.
+                SIGINT | SIGBREAK => {
+                    if GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid as DWORD) == 0 {
+                        Err(super::last_error())
+                    } else {
+                        Ok(())
+                    }
+                }
                 _ => Err(io::IoError {
                     kind: io::OtherIoError,
                     desc: "unsupported signal on windows",
@@ -172,45 +584,123 @@ impl rtio::RtioProcess for Process {
 impl Drop for Process {
     fn drop(&mut self) {
         free_handle(self.handle);
+        for &fd in self.pidfd.iter() {
+            unsafe { libc::close(fd); }
+        }
     }
 }
 
 struct SpawnProcessResult {
     pid: pid_t,
     handle: *(),
+    job: Option<Job>,
+    program_pid: Option<pid_t>,
 }
 
 #[cfg(windows)]
-fn spawn_process_os(prog: &str, args: &[~str],
-                    env: Option<~[(~str, ~str)]>,
-                    dir: Option<&Path>,
-                    in_fd: c_int, out_fd: c_int,
-                    err_fd: c_int) -> IoResult<SpawnProcessResult> {
+fn spawn_process_os(config: &p::ProcessConfig,
+                    fds: &[c_int]) -> IoResult<SpawnProcessResult> {
     use std::libc::types::os::arch::extra::{DWORD, HANDLE, STARTUPINFO};
     use std::libc::consts::os::extra::{
         TRUE, FALSE,
         STARTF_USESTDHANDLES,
         INVALID_HANDLE_VALUE,
-        DUPLICATE_SAME_ACCESS
+        DUPLICATE_SAME_ACCESS,
+        CREATE_BREAKAWAY_FROM_JOB,
+        CREATE_NEW_CONSOLE,
+        CREATE_NEW_PROCESS_GROUP,
+        CREATE_NO_WINDOW,
+        CREATE_SUSPENDED,
+        DETACHED_PROCESS,
+        IDLE_PRIORITY_CLASS,
+        BELOW_NORMAL_PRIORITY_CLASS,
+        ABOVE_NORMAL_PRIORITY_CLASS,
+        HIGH_PRIORITY_CLASS
     };
     use std::libc::funcs::extra::kernel32::{
         GetCurrentProcess,
         DuplicateHandle,
         CloseHandle,
-        CreateProcessA
+        CreateProcessA,
+        ResumeThread,
+        SetProcessAffinityMask,
+        TerminateProcess
     };
     use std::libc::funcs::extra::msvcrt::get_osfhandle;
 
     use std::mem;
+    use std::uint;
+
+    use super::startup::{SecurityAttributes, StartupInfoEx};
+    use super::startup::EXTENDED_STARTUPINFO_PRESENT;
 
     unsafe {
 
+        let mut flags = 0;
+        match config.console {
+            p::InheritConsole => {}
+            p::NewConsole => flags |= CREATE_NEW_CONSOLE,
+            p::NoConsole => flags |= DETACHED_PROCESS,
+            p::HiddenConsole => flags |= CREATE_NO_WINDOW,
+        }
+        flags |= config.creation_flags as DWORD;
+        if config.breakaway_from_job {
+            flags |= CREATE_BREAKAWAY_FROM_JOB;
+        }
+        if config.new_process_group || config.new_session {
+            flags |= CREATE_NEW_PROCESS_GROUP;
+        }
+        match config.priority {
+            Some(nice) if nice >= 15 => flags |= IDLE_PRIORITY_CLASS,
+            Some(nice) if nice >= 5 => flags |= BELOW_NORMAL_PRIORITY_CLASS,
+            Some(nice) if nice <= -15 => flags |= HIGH_PRIORITY_CLASS,
+            Some(nice) if nice <= -5 => flags |= ABOVE_NORMAL_PRIORITY_CLASS,
+            _ => {}
+        }
+
+        // The job has to be set up before the process gets a chance to start
+        // any children of its own, so it is created suspended until then.
+        let job = match config.job_limits {
+            Some(ref limits) => match super::job::Job::new(limits) {
+                Ok(job) => Some(job),
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+        // So does its affinity, for it not to run anywhere else meanwhile.
+        let suspended = job.is_some() || config.cpu_affinity.is_some();
+        if suspended {
+            flags |= CREATE_SUSPENDED;
+        }
+
+        let process_attrs = match config.process_security {
+            Some(sddl) => match SecurityAttributes::from_sddl(sddl) {
+                Ok(attrs) => Some(attrs),
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+        let thread_attrs = match config.thread_security {
+            Some(sddl) => match SecurityAttributes::from_sddl(sddl) {
+                Ok(attrs) => Some(attrs),
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+
         let mut si = zeroed_startupinfo();
         si.cb = mem::size_of::<STARTUPINFO>() as DWORD;
         si.dwFlags = STARTF_USESTDHANDLES;
 
+        // This has to outlive the call to CreateProcessA.
+        let desktop = config.desktop.map(|d| d.to_c_str());
+        for desktop in desktop.iter() {
+            si.lpDesktop = cast::transmute(desktop.with_ref(|p| p));
+        }
+
         let cur_proc = GetCurrentProcess();
 
+        let (in_fd, out_fd, err_fd) = (fds[0], fds[1], fds[2]);
         let orig_std_in = get_osfhandle(in_fd) as HANDLE;
         if orig_std_in == INVALID_HANDLE_VALUE as HANDLE {
             fail!("failure in get_osfhandle: {}", os::last_os_error());
@@ -238,16 +728,52 @@ fn spawn_process_os(prog: &str, args: &[~str],
             fail!("failure in DuplicateHandle: {}", os::last_os_error());
         }
 
-        let cmd = make_command_line(prog, args);
+        let mut si_ex = None;
+        if config.mitigations != p::Mitigations::new() {
+            match StartupInfoEx::new(si, &config.mitigations) {
+                Ok(ex) => si_ex = Some(ex),
+                Err(e) => {
+                    CloseHandle(si.hStdInput);
+                    CloseHandle(si.hStdOutput);
+                    CloseHandle(si.hStdError);
+                    return Err(e);
+                }
+            }
+            flags |= EXTENDED_STARTUPINFO_PRESENT;
+        }
+        let sip = match si_ex {
+            Some(ref mut ex) => ex.as_mut_ptr(),
+            None => &mut si as *mut STARTUPINFO,
+        };
+        let process_attrs_ptr = process_attrs.as_ref().map_or(ptr::mut_null(), |a| a.as_ptr());
+        let thread_attrs_ptr = thread_attrs.as_ref().map_or(ptr::mut_null(), |a| a.as_ptr());
+
+        let cmd = match config.raw_command_line {
+            Some(line) => line.to_owned(),
+            None => {
+                let batch = match config.quoting {
+                    p::AutoQuoting => is_batch_file(config.program),
+                    p::CrtQuoting => false,
+                    p::BatchQuoting => true,
+                };
+                make_command_line(config.argv0.unwrap_or(config.program), config.args, batch)
+            }
+        };
+        // The program is only looked up by its first argument if it is not
+        // given on its own.
+        let program = config.argv0.map(|_| config.program.to_c_str());
+        let programp = program.as_ref().map_or(ptr::null(), |p| p.with_ref(|p| p));
+        let env = config.env.map(|a| a.to_owned());
+        let dir = config.cwd.map(|a| Path::new(a));
         let mut pi = zeroed_process_information();
         let mut create_err = None;
 
         with_envp(env, |envp| {
-            with_dirp(dir, |dirp| {
+            with_dirp(dir.as_ref(), |dirp| {
                 cmd.with_c_str(|cmdp| {
-                    let created = CreateProcessA(ptr::null(), cast::transmute(cmdp),
-                                                 ptr::mut_null(), ptr::mut_null(), TRUE,
-                                                 0, envp, dirp, &mut si, &mut pi);
+                    let created = CreateProcessA(programp, cast::transmute(cmdp),
+                                                 process_attrs_ptr, thread_attrs_ptr, TRUE,
+                                                 flags, envp, dirp, sip, &mut pi);
                     if created == FALSE {
                         create_err = Some(super::last_error());
                     }
@@ -264,6 +790,32 @@ fn spawn_process_os(prog: &str, args: &[~str],
             None => {}
         }
 
+        let mut setup = match job {
+            Some(ref job) => job.assign(pi.hProcess),
+            None => Ok(()),
+        };
+        for cpus in config.cpu_affinity.iter() {
+            if setup.is_ok() {
+                let mask = cpus.iter().filter(|&&cpu| cpu < uint::bits)
+                               .fold(0u, |mask, &cpu| mask | (1 << cpu));
+                if SetProcessAffinityMask(pi.hProcess, mask as libc::SIZE_T) == FALSE {
+                    setup = Err(super::last_error());
+                }
+            }
+        }
+        match setup {
+            Ok(()) => {}
+            Err(e) => {
+                TerminateProcess(pi.hProcess, 1);
+                CloseHandle(pi.hThread);
+                CloseHandle(pi.hProcess);
+                return Err(e);
+            }
+        }
+        if suspended {
+            ResumeThread(pi.hThread);
+        }
+
         // We close the thread handle because we don't care about keeping the
         // thread id valid, and we aren't keeping the thread handle around to be
         // able to close it later. We don't close the process handle however
@@ -273,7 +825,9 @@ fn spawn_process_os(prog: &str, args: &[~str],
 
         Ok(SpawnProcessResult {
             pid: pi.dwProcessId as pid_t,
-            handle: pi.hProcess as *()
+            handle: pi.hProcess as *(),
+            job: job,
+            program_pid: None,
         })
     }
 }
@@ -313,15 +867,46 @@ fn zeroed_process_information() -> libc::types::os::arch::extra::PROCESS_INFORMA
 }
 
 #[cfg(windows)]
-fn make_command_line(prog: &str, args: &[~str]) -> ~str {
+fn is_batch_file(prog: &str) -> bool {
+    use std::ascii::StrAsciiExt;
+
+    let prog = prog.to_ascii_lower();
+    prog.ends_with(".bat") || prog.ends_with(".cmd")
+}
+
+#[cfg(windows)]
+fn make_command_line(prog: &str, args: &[~str], batch: bool) -> ~str {
+    let append = if batch { append_batch_arg } else { append_arg };
     let mut cmd = ~"";
-    append_arg(&mut cmd, prog);
+    append(&mut cmd, prog);
     for arg in args.iter() {
         cmd.push_char(' ');
-        append_arg(&mut cmd, *arg);
+        append(&mut cmd, *arg);
     }
     return cmd;
 
+    // cmd.exe does not give any meaning to backslashes, but expands
+    // variables even inside of quotes. Expanding a substring of the working
+    // directory which is empty yields the `%` instead.
+    fn append_batch_arg(cmd: &mut ~str, arg: &str) {
+        let quote = arg.is_empty() || arg.chars().any(|c| {
+            c == ' ' || c == '\t' || "&|<>^()!,;=\"".contains_char(c)
+        });
+        if quote {
+            cmd.push_char('"');
+        }
+        for c in arg.chars() {
+            match c {
+                '"' => cmd.push_str("\"\""),
+                '%' => cmd.push_str("%%cd:~,%"),
+                c => cmd.push_char(c),
+            }
+        }
+        if quote {
+            cmd.push_char('"');
+        }
+    }
+
     fn append_arg(cmd: &mut ~str, arg: &str) {
         let quote = arg.chars().any(|c| c == ' ' || c == '\t');
         if quote {
@@ -365,21 +950,79 @@ fn make_command_line(prog: &str, args: &[~str]) -> ~str {
 }
 
 #[cfg(unix)]
-fn spawn_process_os(prog: &str, args: &[~str],
-                    env: Option<~[(~str, ~str)]>,
-                    dir: Option<&Path>,
-                    in_fd: c_int, out_fd: c_int,
-                    err_fd: c_int) -> IoResult<SpawnProcessResult> {
-    use std::libc::funcs::posix88::unistd::{fork, dup2, close, chdir, execvp};
+mod rustrt {
+    use std::libc::{c_int, mode_t, size_t};
+
+    extern {
+        pub fn rust_unset_sigprocmask();
+        pub fn rust_set_sigprocmask(signals: *c_int, count: size_t);
+        pub fn rust_reset_signal(signum: c_int);
+        pub fn rust_reset_all_signals();
+        pub fn rust_reset_caught_signals();
+        pub fn umask(mask: mode_t) -> mode_t;
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+static F_DUPFD_CLOEXEC: c_int = 1030;
+#[cfg(target_os = "macos")]
+static F_DUPFD_CLOEXEC: c_int = 67;
+#[cfg(target_os = "freebsd")]
+static F_DUPFD_CLOEXEC: c_int = 17;
+
+/// Copies the descriptors handed to a child above the ones they are handed
+/// as, so that none is overwritten before it is used. The copies are closed
+/// on exec, and are to be closed by the parent once the child is spawned.
+#[cfg(unix)]
+fn move_fds(fds: &[c_int]) -> IoResult<~[c_int]> {
+    extern { fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int; }
+
+    let nfds = fds.len() as c_int;
+    let mut srcs = ~[];
+    for &fd in fds.iter() {
+        if fd == -1 {
+            srcs.push(-1);
+            continue;
+        }
+        let moved = unsafe { fcntl(fd, F_DUPFD_CLOEXEC, nfds) };
+        if moved == -1 {
+            let err = super::last_error();
+            close_fds(srcs);
+            return Err(err);
+        }
+        srcs.push(moved);
+    }
+    Ok(srcs)
+}
+
+/// Closes the copies made by `move_fds`.
+#[cfg(unix)]
+fn close_fds(srcs: &[c_int]) {
+    use std::libc::funcs::posix88::unistd::close;
+
+    for &src in srcs.iter() {
+        if src != -1 { unsafe { close(src); } }
+    }
+}
+
+#[cfg(unix)]
+fn spawn_process_os(config: &p::ProcessConfig,
+                    fds: &[c_int]) -> IoResult<SpawnProcessResult> {
+    use std::libc::funcs::posix88::unistd::{fork, dup2, close, chdir, execvp, setpgid};
+    use std::libc::funcs::posix88::unistd::{setuid, setgid, setsid};
     use std::libc::funcs::bsd44::getdtablesize;
     use std::libc::c_ulong;
     use std::unstable::intrinsics;
 
-    mod rustrt {
-        extern {
-            pub fn rust_unset_sigprocmask();
-        }
+    extern {
+        fn setgroups(ngroups: libc::size_t, gidset: *libc::gid_t) -> c_int;
+        fn setpriority(which: c_int, who: libc::c_uint, prio: c_int) -> c_int;
+        fn chroot(path: *libc::c_char) -> c_int;
+        fn fcntl(fd: c_int, cmd: c_int, arg: c_int) -> c_int;
     }
+    static PRIO_PROCESS: c_int = 0;
+    static F_DUPFD: c_int = 0;
 
     #[cfg(target_os = "macos")]
     unsafe fn set_environ(envp: *c_void) {
@@ -407,21 +1050,147 @@ fn spawn_process_os(prog: &str, args: &[~str],
         assert_eq!(ret, 0);
     }
 
+    unsafe fn set_controlling_terminal(fd: c_int) -> c_int {
+        extern { fn ioctl(fd: c_int, req: c_ulong, arg: c_int) -> c_int; }
+
+        #[cfg(target_os = "macos")]
+        #[cfg(target_os = "freebsd")]
+        static TIOCSCTTY: c_ulong = 0x20007461;
+        #[cfg(target_os = "linux")]
+        #[cfg(target_os = "android")]
+        static TIOCSCTTY: c_ulong = 0x540E;
+
+        ioctl(fd, TIOCSCTTY, 0)
+    }
+
+    // Reports the errno of the last call of the child to the parent, which
+    // fails to spawn it with the corresponding error.
+    unsafe fn fail(output: &mut file::FileDesc) -> ! {
+        let errno = os::errno();
+        let bytes = [
+            (errno << 24) as u8,
+            (errno << 16) as u8,
+            (errno <<  8) as u8,
+            (errno <<  0) as u8,
+        ];
+        output.inner_write(bytes);
+        intrinsics::abort()
+    }
+
+    if config.vfork {
+        return spawn_process_vfork(config, fds);
+    }
+
+    let env = config.env.map(|a| a.to_owned());
+    let dir = config.cwd.map(|a| Path::new(a));
+    let attrs = config.spawn_attributes;
+    let suspended = attrs.map_or(false, |a| a.start_suspended);
+    let signal_mask = attrs.and_then(|a| a.signal_mask.as_ref())
+                           .map(|m| m.map(|&s| s as c_int));
+    let sandbox_profile = sandbox_profile(config).map(|s| s.to_c_str());
+    let controls = config.process_controls;
+    let parent_death_signal = config.parent_death_signal;
+    let capabilities = config.capabilities;
+    let no_new_privs = config.no_new_privs;
+    let isolation = prepare_isolation(config);
+    let umask = config.umask;
+    let inherit_fds = config.inherit_fds;
+    let reset_signals = config.reset_signals;
+    let uid = config.uid;
+    let gid = config.gid;
+    let new_process_group = config.new_process_group;
+    let new_session = config.new_session || config.no_ctty;
+    let affinity = config.cpu_affinity.map(|cpus| cpu_set(cpus));
+    let root = config.chroot.map(|r| r.to_c_str());
+    let program = config.program.to_c_str();
+    let argv0 = config.argv0.unwrap_or(config.program);
+    // A process which is not told where to run inside of its new root would
+    // otherwise be left outside of it.
+    let dir = match dir {
+        None if root.is_some() => Some(Path::new("/")),
+        dir => dir,
+    };
+    // The first standard stream connected to a pseudo-terminal makes it the
+    // controlling terminal of the child.
+    let pty_fd = config.io.iter().take(3).position(|io| {
+        match *io { p::CreatePty => true, _ => false }
+    }).map(|i| i as c_int);
+    if controls.map_or(false, |c| c.reaper) {
+        match acquire_reaper() {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let parent = unsafe { libc::getpid() };
+    let kept_fds = attrs.map_or(~[], |a| {
+        a.file_actions.iter().filter_map(|action| {
+            match *action {
+                p::DupFd(_, dst) => Some(dst),
+                p::CloseFd(..) => None,
+            }
+        }).collect()
+    });
+
+    // The child must not allocate, so the copies it moves into place are
+    // made beforehand.
+    let srcs = match move_fds(fds) {
+        Ok(srcs) => srcs,
+        Err(e) => return Err(e),
+    };
+
     let pipe = os::pipe();
     let mut input = file::FileDesc::new(pipe.input, true);
     let mut output = file::FileDesc::new(pipe.out, true);
 
     unsafe { set_cloexec(output.fd()) };
 
+    // A child entering a new pid namespace runs the program in a child of its
+    // own, whose id it writes to this pipe.
+    let (pid_in, mut pid_out) = if isolation.as_ref().map_or(false, |i| enters_pid_namespace(i)) {
+        let pipe = os::pipe();
+        unsafe {
+            set_cloexec(pipe.input);
+            set_cloexec(pipe.out);
+        }
+        (pipe.input, pipe.out)
+    } else {
+        (-1, -1)
+    };
+
     unsafe {
         let pid = fork();
         if pid < 0 {
             fail!("failure in fork: {}", os::last_os_error());
         } else if pid > 0 {
             drop(output);
+            close_fds(srcs);
+            if pid_out != -1 {
+                close(pid_out);
+            }
+            // A child which starts suspended stops before it executes the
+            // program, so there is nothing to read on the pipe until it is
+            // continued.
+            if suspended {
+                return match wait_stopped(pid) {
+                    true => Ok(SpawnProcessResult {
+                        pid: pid,
+                        handle: ptr::null(),
+                        job: None,
+                        program_pid: read_program_pid(pid_in),
+                    }),
+                    false => Err(io::IoError {
+                        kind: io::OtherIoError,
+                        desc: "child exited before it was suspended",
+                        detail: None,
+                    }),
+                };
+            }
             let mut bytes = [0, ..4];
             return match input.inner_read(bytes) {
                 Ok(4) => {
+                    if pid_in != -1 {
+                        close(pid_in);
+                    }
                     let errno = (bytes[0] << 24) as i32 |
                                 (bytes[1] << 16) as i32 |
                                 (bytes[2] <<  8) as i32 |
@@ -434,39 +1203,158 @@ fn spawn_process_os(prog: &str, args: &[~str],
                             "unexpected error: {:?}", e);
                     Ok(SpawnProcessResult {
                         pid: pid,
-                        handle: ptr::null()
+                        handle: ptr::null(),
+                        job: None,
+                        program_pid: read_program_pid(pid_in),
                     })
                 }
                 Ok(..) => fail!("short read on the cloexec pipe"),
             };
         }
         drop(input);
+        if pid_in != -1 {
+            close(pid_in);
+        }
 
-        rustrt::rust_unset_sigprocmask();
+        match signal_mask {
+            Some(ref mask) => {
+                rustrt::rust_set_sigprocmask(mask.as_ptr(), mask.len() as libc::size_t)
+            }
+            None if reset_signals => rustrt::rust_unset_sigprocmask(),
+            None => {}
+        }
+        if reset_signals {
+            rustrt::rust_reset_all_signals();
+        }
 
-        if in_fd == -1 {
-            libc::close(libc::STDIN_FILENO);
-        } else if retry(|| dup2(in_fd, 0)) == -1 {
-            fail!("failure in dup2(in_fd, 0): {}", os::last_os_error());
+        // The pipe reporting the errors of the child is moved above the
+        // descriptors it is handed, like their copies, so that it is not
+        // overwritten before it is used.
+        let nfds = fds.len() as c_int;
+        if output.fd() < nfds {
+            let moved = fcntl(output.fd(), F_DUPFD, nfds);
+            if moved == -1 {
+                fail(&mut output);
+            }
+            set_cloexec(moved);
+            output = file::FileDesc::new(moved, true);
         }
-        if out_fd == -1 {
-            libc::close(libc::STDOUT_FILENO);
-        } else if retry(|| dup2(out_fd, 1)) == -1 {
-            fail!("failure in dup2(out_fd, 1): {}", os::last_os_error());
+        if pid_out != -1 && pid_out < nfds {
+            let moved = fcntl(pid_out, F_DUPFD, nfds);
+            if moved == -1 {
+                fail(&mut output);
+            }
+            set_cloexec(moved);
+            pid_out = moved;
         }
-        if err_fd == -1 {
-            libc::close(libc::STDERR_FILENO);
-        } else if retry(|| dup2(err_fd, 2)) == -1 {
-            fail!("failure in dup3(err_fd, 2): {}", os::last_os_error());
+        for (dst, &src) in srcs.iter().enumerate() {
+            let dst = dst as c_int;
+            if src == -1 {
+                close(dst);
+            } else if retry(|| dup2(src, dst)) == -1 {
+                fail(&mut output);
+            } else {
+                close(src);
+            }
+        }
+        for attrs in attrs.iter() {
+            for action in attrs.file_actions.iter() {
+                match *action {
+                    p::DupFd(src, dst) => {
+                        if retry(|| dup2(src, dst)) == -1 {
+                            fail(&mut output);
+                        }
+                    }
+                    p::CloseFd(fd) => { close(fd); }
+                }
+            }
         }
         // close all other fds
-        for fd in range(3, getdtablesize()).rev() {
-            if fd != output.fd() {
-                close(fd as c_int);
+        if !inherit_fds {
+            for fd in range(nfds, getdtablesize()).rev() {
+                if fd != output.fd() && fd != pid_out && !kept_fds.contains(&(fd as c_int)) {
+                    close(fd as c_int);
+                }
+            }
+        }
+
+        for attrs in attrs.iter() {
+            for &signum in attrs.default_signals.iter() {
+                rustrt::rust_reset_signal(signum as c_int);
+            }
+            for &pgid in attrs.process_group.iter() {
+                if setpgid(0, pgid) == -1 {
+                    fail(&mut output);
+                }
+            }
+        }
+        if new_session || pty_fd.is_some() {
+            if setsid() == -1 {
+                fail(&mut output);
+            }
+        } else if new_process_group {
+            if setpgid(0, 0) == -1 {
+                fail(&mut output);
+            }
+        }
+        for &fd in pty_fd.iter() {
+            if set_controlling_terminal(fd) == -1 {
+                fail(&mut output);
+            }
+        }
+
+        for profile in sandbox_profile.iter() {
+            if apply_sandbox_profile(profile) == -1 {
+                fail(&mut output);
+            }
+        }
+        for controls in controls.iter() {
+            if apply_process_controls(*controls, parent) == -1 {
+                fail(&mut output);
+            }
+        }
+        for &signum in parent_death_signal.iter() {
+            if set_parent_death_signal(signum, parent) == -1 {
+                fail(&mut output);
+            }
+        }
+        for isolation in isolation.iter() {
+            if apply_isolation(isolation, output.fd(), pid_out) == -1 {
+                fail(&mut output);
+            }
+        }
+        for root in root.iter() {
+            if root.with_ref(|p| chroot(p)) == -1 {
+                fail(&mut output);
+            }
+        }
+        if no_new_privs && set_no_new_privs() == -1 {
+            fail(&mut output);
+        }
+
+        for &mask in umask.iter() {
+            rustrt::umask(mask);
+        }
+        // Raising a hard limit needs privileges which may be dropped below,
+        // as does raising the priority, and so do changing the user and
+        // reducing the bounding set.
+        for &(resource, soft, hard) in config.rlimits.iter() {
+            if set_rlimit(resource, soft, hard) == -1 {
+                fail(&mut output);
+            }
+        }
+        for &nice in config.priority.iter() {
+            if setpriority(PRIO_PROCESS, 0, nice as c_int) == -1 {
+                fail(&mut output);
+            }
+        }
+        for set in affinity.iter() {
+            if set_affinity(set.as_slice()) == -1 {
+                fail(&mut output);
             }
         }
 
-        with_dirp(dir, |dirp| {
+        with_dirp(dir.as_ref(), |dirp| {
             if !dirp.is_null() && chdir(dirp) == -1 {
                 fail!("failure in chdir: {}", os::last_os_error());
             }
@@ -477,21 +1365,534 @@ fn spawn_process_os(prog: &str, args: &[~str],
                 set_environ(envp);
             }
         });
-        with_argv(prog, args, |argv| {
-            execvp(*argv, argv);
-            let errno = os::errno();
-            let bytes = [
-                (errno << 24) as u8,
-                (errno << 16) as u8,
-                (errno <<  8) as u8,
-                (errno <<  0) as u8,
-            ];
-            output.inner_write(bytes);
-            intrinsics::abort();
+        for capabilities in capabilities.iter() {
+            if drop_bounding_set(*capabilities) == -1 {
+                fail(&mut output);
+            }
+        }
+        for &gid in gid.iter() {
+            if setgid(gid as libc::gid_t) != 0 {
+                fail(&mut output);
+            }
+        }
+        for &uid in uid.iter() {
+            // Dropping root privileges also drops the groups of root, which
+            // only succeeds as root, so this is done on a best-effort basis.
+            setgroups(0, ptr::null());
+            if setuid(uid as libc::uid_t) != 0 {
+                fail(&mut output);
+            }
+        }
+        // The capabilities are reduced last, as everything above may need
+        // some of those which are dropped.
+        for capabilities in capabilities.iter() {
+            if reduce_capabilities(*capabilities) == -1 {
+                fail(&mut output);
+            }
+        }
+        match config.before_exec {
+            Some(ref hook) => if !(*hook)() { fail(&mut output) },
+            None => {}
+        }
+        if suspended {
+            libc::funcs::posix88::signal::kill(libc::getpid(), SIGSTOP);
+        }
+        with_argv(argv0, config.args, |argv| {
+            program.with_ref(|p| execvp(p, argv));
+            fail(&mut output);
         })
     }
 }
 
+/// Spawns the child with `vfork`, which lends it the memory of the current
+/// process until it executes the program or exits. Everything it needs is
+/// therefore prepared here, and it only makes async-signal-safe system calls
+/// on values it does not change: it moves its standard streams into place,
+/// resets its signals, and then either executes the program or leaves its
+/// `errno` for the parent and exits.
+///
+/// All signals are blocked around `vfork`, as a handler running in the child
+/// would run on the stack of the parent. The child resets the handlers which
+/// are set before unblocking the signals, so that none of them runs between
+/// the unblocking and the exec.
+#[cfg(unix)]
+fn spawn_process_vfork(config: &p::ProcessConfig,
+                       fds: &[c_int]) -> IoResult<SpawnProcessResult> {
+    use std::libc::funcs::posix88::unistd::{dup2, close, execve};
+    use std::libc::funcs::bsd44::getdtablesize;
+    use std::unstable::intrinsics;
+
+    // Large enough to hold a `sigset_t` on every supported platform.
+    struct sigset_t {
+        bits: [u64, ..16],
+    }
+
+    extern {
+        fn vfork() -> pid_t;
+        fn _exit(status: c_int) -> !;
+        fn sigfillset(set: *mut sigset_t) -> c_int;
+        fn pthread_sigmask(how: c_int, set: *sigset_t, old: *mut sigset_t) -> c_int;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    static SIG_SETMASK: c_int = 2;
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    static SIG_SETMASK: c_int = 3;
+
+    unsafe fn fail(errno: *mut c_int) -> ! {
+        intrinsics::volatile_store(errno, os::errno() as c_int);
+        _exit(127)
+    }
+
+    let env = match config.env {
+        Some(env) => env.to_owned(),
+        None => os::env(),
+    };
+    let program = match search_path(config.program, env) {
+        Some(program) => program,
+        None => return Err(io::standard_error(io::FileNotFound)),
+    };
+    let argv0 = config.argv0.unwrap_or(config.program);
+    let inherit_fds = config.inherit_fds;
+    let reset_signals = config.reset_signals;
+    let nfds = fds.len() as c_int;
+    let maxfd = unsafe { getdtablesize() } as c_int;
+
+    let srcs = match move_fds(fds) {
+        Ok(srcs) => srcs,
+        Err(e) => return Err(e),
+    };
+
+    let mut errno = 0 as c_int;
+    let errno_ptr = &mut errno as *mut c_int;
+    let mut all: sigset_t = unsafe { intrinsics::init() };
+    let mut old: sigset_t = unsafe { intrinsics::init() };
+    unsafe {
+        sigfillset(&mut all);
+        pthread_sigmask(SIG_SETMASK, &all, &mut old);
+    }
+
+    let pid = with_envp(Some(env), |envp| {
+        with_argv(argv0, config.args, |argv| unsafe {
+            let pid = vfork();
+            if pid != 0 {
+                return pid;
+            }
+
+            // With all signals blocked no call is interrupted, so none is
+            // retried.
+            for (dst, &src) in srcs.iter().enumerate() {
+                let dst = dst as c_int;
+                if src == -1 {
+                    close(dst);
+                } else if dup2(src, dst) == -1 {
+                    fail(errno_ptr);
+                }
+            }
+            if !inherit_fds {
+                for fd in range(nfds, maxfd) {
+                    close(fd);
+                }
+            }
+            if reset_signals {
+                rustrt::rust_reset_all_signals();
+                rustrt::rust_unset_sigprocmask();
+            } else {
+                rustrt::rust_reset_caught_signals();
+                pthread_sigmask(SIG_SETMASK, &old, ptr::mut_null());
+            }
+            program.with_ref(|p| execve(p, argv, envp as **libc::c_char));
+            fail(errno_ptr)
+        })
+    });
+
+    let vfork_error = if pid < 0 { Some(super::last_error()) } else { None };
+    unsafe { pthread_sigmask(SIG_SETMASK, &old, ptr::mut_null()); }
+    close_fds(srcs);
+    match vfork_error {
+        Some(e) => return Err(e),
+        None => {}
+    }
+    let errno = unsafe { intrinsics::volatile_load(errno_ptr as *c_int) };
+    if errno != 0 {
+        waitpid(pid);
+        return Err(super::translate_error(errno, false));
+    }
+    Ok(SpawnProcessResult {
+        pid: pid,
+        handle: ptr::null(),
+        job: None,
+        program_pid: None,
+    })
+}
+
+/// Returns the path `execvp` would execute `program` from in a process with
+/// the environment `env`, for a child which cannot look it up itself.
+#[cfg(unix)]
+fn search_path(program: &str, env: &[(~str, ~str)]) -> Option<CString> {
+    if program.contains_char('/') {
+        return Some(program.to_c_str());
+    }
+    let mut dirs = "/bin:/usr/bin";
+    for &(ref key, ref value) in env.iter() {
+        if key.as_slice() == "PATH" {
+            dirs = value.as_slice();
+        }
+    }
+    for dir in dirs.split(':') {
+        let dir = if dir.is_empty() { "." } else { dir };
+        let path = Path::new(dir).join(program).to_c_str();
+        if path.with_ref(|p| unsafe { libc::access(p, libc::X_OK) }) == 0 {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Returns whether the child needs any setup besides its standard streams,
+/// which a child spawned with `vfork` cannot do.
+fn needs_child_setup(config: &p::ProcessConfig) -> bool {
+    let uses_pty = config.io.iter().any(|io| {
+        match *io { p::CreatePty => true, _ => false }
+    });
+    uses_pty || config.spawn_attributes.is_some() || config.sandbox_profile.is_some() ||
+        config.process_controls.is_some() || config.parent_death_signal.is_some() ||
+        config.capabilities.is_some() || config.no_new_privs || config.fs_isolation.is_some() ||
+        config.no_network ||
+        config.namespaces != p::Namespaces::new() || config.umask.is_some() ||
+        config.uid.is_some() || config.gid.is_some() || config.new_process_group ||
+        config.new_session || config.no_ctty || config.before_exec.is_some() ||
+        config.rlimits.len() > 0 || config.priority.is_some() || config.cpu_affinity.is_some() ||
+        config.chroot.is_some() || config.cwd.is_some() || config.io.len() > 3
+}
+
+/// Returns the sandbox profile applied to the child, which denies it network
+/// access after the profile of `config` if it is not to have any.
+#[cfg(target_os = "macos")]
+fn sandbox_profile(config: &p::ProcessConfig) -> Option<~str> {
+    match (config.sandbox_profile, config.no_network) {
+        (Some(profile), true) => Some(format!("{} (deny network*)", profile)),
+        (None, true) => Some(~"(version 1) (allow default) (deny network*)"),
+        (profile, false) => profile.map(|p| p.to_owned()),
+    }
+}
+
+#[cfg(unix, not(target_os = "macos"))]
+fn sandbox_profile(config: &p::ProcessConfig) -> Option<~str> {
+    config.sandbox_profile.map(|p| p.to_owned())
+}
+
+/// Applies the sandbox profile `profile` to the current process, which can
+/// never leave the sandbox again.
+#[cfg(target_os = "macos")]
+unsafe fn apply_sandbox_profile(profile: &CString) -> c_int {
+    extern {
+        fn sandbox_init(profile: *libc::c_char, flags: u64,
+                        errorbuf: *mut *libc::c_char) -> c_int;
+        fn sandbox_free_error(errorbuf: *libc::c_char);
+        fn __error() -> *mut c_int;
+    }
+
+    let mut err = ptr::null();
+    if profile.with_ref(|p| sandbox_init(p, 0, &mut err)) == -1 {
+        // The reason is only given as a message, which cannot be sent
+        // through the errno pipe, and sandbox_init need not set errno.
+        sandbox_free_error(err);
+        *__error() = libc::EINVAL;
+        return -1
+    }
+    0
+}
+
+#[cfg(unix, not(target_os = "macos"))]
+unsafe fn apply_sandbox_profile(_profile: &CString) -> c_int { 0 }
+
+#[cfg(target_os = "freebsd")]
+fn acquire_reaper() -> IoResult<()> { super::procctl::acquire_reaper() }
+#[cfg(target_os = "freebsd")]
+unsafe fn apply_process_controls(controls: &p::ProcessControls, parent: pid_t) -> c_int {
+    super::procctl::apply_to_child(controls, parent)
+}
+
+#[cfg(unix, not(target_os = "freebsd"))]
+fn acquire_reaper() -> IoResult<()> { Ok(()) }
+#[cfg(unix, not(target_os = "freebsd"))]
+unsafe fn apply_process_controls(_controls: &p::ProcessControls, _parent: pid_t) -> c_int {
+    0
+}
+
+/// Returns whether the child regains every capability of its bounding set
+/// when it executes the program, as a child running as root does.
+#[cfg(target_os = "linux")]
+fn regains_capabilities(config: &p::ProcessConfig) -> bool {
+    match config.uid {
+        Some(uid) => uid == 0,
+        None => unsafe { libc::funcs::posix88::unistd::geteuid() == 0 },
+    }
+}
+#[cfg(not(target_os = "linux"))]
+fn regains_capabilities(_config: &p::ProcessConfig) -> bool { false }
+
+#[cfg(target_os = "linux")]
+unsafe fn drop_bounding_set(capabilities: &p::Capabilities) -> c_int {
+    super::prctl::drop_bounding_set(capabilities)
+}
+#[cfg(target_os = "linux")]
+unsafe fn reduce_capabilities(capabilities: &p::Capabilities) -> c_int {
+    super::prctl::reduce_capabilities(capabilities)
+}
+#[cfg(unix, not(target_os = "linux"))]
+unsafe fn drop_bounding_set(_capabilities: &p::Capabilities) -> c_int { 0 }
+#[cfg(unix, not(target_os = "linux"))]
+unsafe fn reduce_capabilities(_capabilities: &p::Capabilities) -> c_int { 0 }
+
+/// Returns the `cpu_set_t` holding `cpus`, as the words of its 1024 bits.
+/// CPUs beyond those are ignored.
+#[cfg(target_os = "linux")]
+fn cpu_set(cpus: &[uint]) -> ~[libc::c_ulong] {
+    use std::mem;
+    use std::vec;
+
+    let bits = 8 * mem::size_of::<libc::c_ulong>();
+    let mut set = vec::from_elem(1024 / bits, 0 as libc::c_ulong);
+    for &cpu in cpus.iter().filter(|&&cpu| cpu < 1024) {
+        set[cpu / bits] |= 1 << (cpu % bits);
+    }
+    set
+}
+/// Restricts the current process to the CPUs of `set`.
+#[cfg(target_os = "linux")]
+unsafe fn set_affinity(set: &[libc::c_ulong]) -> c_int {
+    use std::mem;
+
+    extern {
+        fn sched_setaffinity(pid: pid_t, size: libc::size_t, mask: *libc::c_ulong) -> c_int;
+    }
+    let size = set.len() * mem::size_of::<libc::c_ulong>();
+    sched_setaffinity(0, size as libc::size_t, set.as_ptr())
+}
+#[cfg(unix, not(target_os = "linux"))]
+fn cpu_set(_cpus: &[uint]) -> ~[libc::c_ulong] { ~[] }
+#[cfg(unix, not(target_os = "linux"))]
+unsafe fn set_affinity(_set: &[libc::c_ulong]) -> c_int { 0 }
+
+#[cfg(target_os = "linux")]
+fn open_pidfd(pid: pid_t) -> Option<c_int> { super::pidfd::open(pid) }
+#[cfg(not(target_os = "linux"))]
+fn open_pidfd(_pid: pid_t) -> Option<c_int> { None }
+
+#[cfg(target_os = "linux")]
+fn send_pidfd_signal(fd: c_int, signum: int) -> IoResult<()> {
+    super::pidfd::send_signal(fd, signum)
+}
+#[cfg(not(target_os = "linux"))]
+fn send_pidfd_signal(_fd: c_int, _signum: int) -> IoResult<()> { Ok(()) }
+
+/// Sets the limits of `resource` of the current process, as `setrlimit`.
+#[cfg(unix)]
+unsafe fn set_rlimit(resource: p::Resource, soft: u64, hard: u64) -> c_int {
+    use std::u64;
+
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    mod consts {
+        use std::libc::{c_int, c_ulong};
+        use std::io::process as p;
+
+        pub type rlim_t = c_ulong;
+        pub static RLIM_INFINITY: rlim_t = !0;
+
+        pub fn resource(r: p::Resource) -> c_int {
+            match r {
+                p::CpuTime => 0,
+                p::FileSize => 1,
+                p::DataSize => 2,
+                p::StackSize => 3,
+                p::CoreSize => 4,
+                p::Processes => 6,
+                p::OpenFiles => 7,
+                p::LockedMemory => 8,
+                p::AddressSpace => 9,
+            }
+        }
+    }
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    mod consts {
+        use std::libc::c_int;
+        use std::io::process as p;
+
+        pub type rlim_t = u64;
+        pub static RLIM_INFINITY: rlim_t = (1 << 63) - 1;
+
+        pub fn resource(r: p::Resource) -> c_int {
+            match r {
+                p::CpuTime => 0,
+                p::FileSize => 1,
+                p::DataSize => 2,
+                p::StackSize => 3,
+                p::CoreSize => 4,
+                p::LockedMemory => 6,
+                p::Processes => 7,
+                p::OpenFiles => 8,
+                p::AddressSpace => if cfg!(target_os = "macos") { 5 } else { 10 },
+            }
+        }
+    }
+
+    struct rlimit {
+        rlim_cur: consts::rlim_t,
+        rlim_max: consts::rlim_t,
+    }
+    extern { fn setrlimit(resource: c_int, rlim: *rlimit) -> c_int; }
+
+    fn limit(l: u64) -> consts::rlim_t {
+        if l == u64::MAX {
+            consts::RLIM_INFINITY
+        } else {
+            l as consts::rlim_t
+        }
+    }
+    let rlim = rlimit { rlim_cur: limit(soft), rlim_max: limit(hard) };
+    setrlimit(consts::resource(resource), &rlim)
+}
+
+/// Terminates all processes of `job` if `signum` kills the process and the
+/// job is set to die with it, returning None if this is not the case.
+#[cfg(windows)]
+fn kill_job(job: &Option<Job>, signum: int) -> Option<IoResult<()>> {
+    match *job {
+        Some(ref job) if job.kills_on_close() &&
+                         (signum == p::PleaseExitSignal || signum == p::MustDieSignal) => {
+            Some(job.terminate(1))
+        }
+        _ => None,
+    }
+}
+#[cfg(unix)]
+fn kill_job(_job: &Option<Job>, _signum: int) -> Option<IoResult<()>> { None }
+
+#[cfg(unix)]
+fn open_pty() -> IoResult<(file::FileDesc, file::FileDesc)> { super::pty::open() }
+#[cfg(windows)]
+fn open_pty() -> IoResult<(file::FileDesc, file::FileDesc)> {
+    Err(io::IoError {
+        kind: io::IoUnavailable,
+        desc: "pseudo-terminals are not supported on windows",
+        detail: None,
+    })
+}
+
+/// Opens the null device for reading.
+fn open_null_input() -> IoResult<c_int> {
+    let path = if cfg!(windows) { "NUL" } else { "/dev/null" };
+    match path.with_c_str(|p| unsafe { libc::open(p, libc::O_RDONLY, 0) }) {
+        -1 => Err(super::last_error()),
+        fd => Ok(fd),
+    }
+}
+
+#[cfg(unix)]
+fn pty_master(fd: file::FileDesc) -> ~rtio::RtioPipe {
+    ~super::pty::PtyMaster::new(fd) as ~rtio::RtioPipe
+}
+#[cfg(windows)]
+fn pty_master(fd: file::FileDesc) -> ~rtio::RtioPipe { ~fd as ~rtio::RtioPipe }
+
+#[cfg(target_os = "linux")]
+unsafe fn set_no_new_privs() -> c_int { super::prctl::set_no_new_privs() }
+#[cfg(unix, not(target_os = "linux"))]
+unsafe fn set_no_new_privs() -> c_int { 0 }
+
+#[cfg(target_os = "linux")]
+unsafe fn set_parent_death_signal(signum: int, parent: pid_t) -> c_int {
+    super::prctl::set_parent_death_signal(signum, parent)
+}
+#[cfg(target_os = "freebsd")]
+unsafe fn set_parent_death_signal(signum: int, parent: pid_t) -> c_int {
+    super::procctl::set_parent_death_signal(signum, parent)
+}
+#[cfg(unix, not(target_os = "linux"), not(target_os = "freebsd"))]
+unsafe fn set_parent_death_signal(_signum: int, _parent: pid_t) -> c_int { 0 }
+
+#[cfg(target_os = "linux")] type Isolation = super::namespace::Isolation;
+#[cfg(unix, not(target_os = "linux"))] type Isolation = ();
+
+#[cfg(target_os = "linux")]
+fn prepare_isolation(config: &p::ProcessConfig) -> Option<Isolation> {
+    super::namespace::prepare(config)
+}
+#[cfg(target_os = "linux")]
+unsafe fn apply_isolation(isolation: &Isolation, report_fd: c_int, pid_fd: c_int) -> c_int {
+    if super::namespace::apply(isolation) == -1 {
+        return -1
+    }
+    super::namespace::fork_into_pid_namespace(isolation, report_fd, pid_fd)
+}
+#[cfg(target_os = "linux")]
+fn enters_pid_namespace(isolation: &Isolation) -> bool {
+    super::namespace::enters_pid_namespace(isolation)
+}
+
+#[cfg(unix, not(target_os = "linux"))]
+fn prepare_isolation(_config: &p::ProcessConfig) -> Option<Isolation> { None }
+#[cfg(unix, not(target_os = "linux"))]
+unsafe fn apply_isolation(_isolation: &Isolation, _report_fd: c_int, _pid_fd: c_int) -> c_int {
+    0
+}
+#[cfg(unix, not(target_os = "linux"))]
+fn enters_pid_namespace(_isolation: &Isolation) -> bool { false }
+
+/// Reads the id of the process running the program from `fd`, which the
+/// child writes it to if it runs the program in a child of its own, and
+/// closes `fd`.
+#[cfg(unix)]
+fn read_program_pid(fd: c_int) -> Option<pid_t> {
+    if fd == -1 {
+        return None
+    }
+    let mut bytes = [0u8, ..4];
+    let n = retry(|| unsafe {
+        libc::read(fd, bytes.as_mut_ptr() as *mut c_void, 4) as c_int
+    });
+    unsafe { libc::close(fd); }
+    if n != 4 {
+        return None
+    }
+    Some((bytes[0] as pid_t << 24) | (bytes[1] as pid_t << 16) |
+         (bytes[2] as pid_t << 8) | bytes[3] as pid_t)
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+static SIGSTOP: c_int = 19;
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+static SIGCONT: c_int = 18;
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+static SIGSTOP: c_int = 17;
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+static SIGCONT: c_int = 19;
+
+/// Waits for the child `pid` to stop, returning false if it terminated
+/// instead (in which case it has been reaped).
+#[cfg(unix)]
+fn wait_stopped(pid: pid_t) -> bool {
+    use std::libc::funcs::posix01::wait;
+
+    static WUNTRACED: c_int = 2;
+
+    let mut status = 0 as c_int;
+    match retry(|| unsafe { wait::waitpid(pid, &mut status, WUNTRACED) }) {
+        -1 => fail!("unknown waitpid error: {:?}", super::last_error()),
+        _ => status & 0xff == 0x7f,
+    }
+}
+
 #[cfg(unix)]
 fn with_argv<T>(prog: &str, args: &[~str], cb: |**libc::c_char| -> T) -> T {
     use std::vec;
@@ -587,7 +1988,9 @@ fn free_handle(_handle: *()) {
 }
 
 /**
- * Waits for a process to exit and returns the exit code, failing
+ * Waits for a process to exit and returns the exit code and whether it dumped
+ * core, along with the resources it used where they are collected at the
+ * same time, failing
  * if there is no process with the specified id.
  *
  * Note that this is private to avoid race conditions on unix where if
@@ -596,11 +1999,11 @@ fn free_handle(_handle: *()) {
  * operate on a none-existent process or, even worse, on a newer process
  * with the same id.
  */
-fn waitpid(pid: pid_t) -> p::ProcessExit {
+fn waitpid(pid: pid_t) -> (p::ProcessExit, bool, Option<p::ResourceUsage>) {
     return waitpid_os(pid);
 
     #[cfg(windows)]
-    fn waitpid_os(pid: pid_t) -> p::ProcessExit {
+    fn waitpid_os(pid: pid_t) -> (p::ProcessExit, bool, Option<p::ResourceUsage>) {
         use std::libc::types::os::arch::extra::DWORD;
         use std::libc::consts::os::extra::{
             SYNCHRONIZE,
@@ -634,7 +2037,7 @@ fn waitpid(pid: pid_t) -> p::ProcessExit {
                 }
                 if status != STILL_ACTIVE {
                     CloseHandle(process);
-                    return p::ExitStatus(status as int);
+                    return (p::ExitStatus(status as int), false, None);
                 }
                 if WaitForSingleObject(process, INFINITE) == WAIT_FAILED {
                     CloseHandle(process);
@@ -645,37 +2048,226 @@ fn waitpid(pid: pid_t) -> p::ProcessExit {
     }
 
     #[cfg(unix)]
-    fn waitpid_os(pid: pid_t) -> p::ProcessExit {
-        use std::libc::funcs::posix01::wait;
+    fn waitpid_os(pid: pid_t) -> (p::ProcessExit, bool, Option<p::ResourceUsage>) {
+        match wait4(pid, 0) {
+            Ok(Some((exit, core_dumped, rusage))) => (exit, core_dumped, Some(rusage)),
+            Ok(None) => unreachable!(),
+            Err(e) => fail!("unknown waitpid error: {:?}", e),
+        }
+    }
+}
 
-        #[cfg(target_os = "linux")]
-        #[cfg(target_os = "android")]
-        mod imp {
-            pub fn WIFEXITED(status: i32) -> bool { (status & 0xff) == 0 }
-            pub fn WEXITSTATUS(status: i32) -> i32 { (status >> 8) & 0xff }
-            pub fn WTERMSIG(status: i32) -> i32 { status & 0x7f }
+/**
+ * Reaps the child `pid` with `wait4`, passing it `options`, and returns its
+ * exit status and whether it dumped core along with the resources it used,
+ * or None if WNOHANG was passed and it is still running.
+ */
+#[cfg(unix)]
+fn wait4(pid: pid_t,
+         options: c_int) -> IoResult<Option<(p::ProcessExit, bool, p::ResourceUsage)>> {
+    use std::libc::types::os::common::posix01::timeval;
+    use std::unstable::intrinsics;
+
+    struct rusage {
+        ru_utime: timeval,
+        ru_stime: timeval,
+        ru_maxrss: libc::c_long,
+        ru_ixrss: libc::c_long,
+        ru_idrss: libc::c_long,
+        ru_isrss: libc::c_long,
+        ru_minflt: libc::c_long,
+        ru_majflt: libc::c_long,
+        ru_nswap: libc::c_long,
+        ru_inblock: libc::c_long,
+        ru_oublock: libc::c_long,
+        ru_msgsnd: libc::c_long,
+        ru_msgrcv: libc::c_long,
+        ru_nsignals: libc::c_long,
+        ru_nvcsw: libc::c_long,
+        ru_nivcsw: libc::c_long,
+    }
+
+    extern {
+        fn wait4(pid: pid_t, status: *mut c_int, options: c_int, rusage: *mut rusage) -> pid_t;
+    }
+
+    // The largest resident set size is counted in kilobytes, except on OS X
+    // where it is counted in bytes.
+    #[cfg(target_os = "macos")]
+    static MAXRSS_UNIT: u64 = 1;
+    #[cfg(not(target_os = "macos"))]
+    static MAXRSS_UNIT: u64 = 1024;
+
+    fn micros(t: &timeval) -> u64 {
+        t.tv_sec as u64 * 1000000 + t.tv_usec as u64
+    }
+
+    let mut status = 0 as c_int;
+    let mut ru: rusage = unsafe { intrinsics::init() };
+    match retry(|| unsafe { wait4(pid, &mut status, options, &mut ru) }) {
+        -1 => Err(super::last_error()),
+        0 => Ok(None),
+        _ => Ok(Some((translate_status(status), core_dumped(status), p::ResourceUsage {
+            user_time: micros(&ru.ru_utime),
+            system_time: micros(&ru.ru_stime),
+            max_rss: ru.ru_maxrss as u64 * MAXRSS_UNIT,
+            minor_faults: ru.ru_minflt as u64,
+            major_faults: ru.ru_majflt as u64,
+        }))),
+    }
+}
+
+/**
+ * Checks whether the child `pid` has exited without blocking, collecting its
+ * exit status and the resources it used if it has.
+ *
+ * An error is returned if `pid` is not a child of this process, or if it has
+ * already been waited for.
+ */
+#[cfg(unix)]
+fn try_waitpid(pid: pid_t) -> IoResult<Option<(p::ProcessExit, bool, p::ResourceUsage)>> {
+    static WNOHANG: c_int = 1;
+    wait4(pid, WNOHANG)
+}
+
+/// Returns the exit status of the child `pid`, whether it dumped core and the
+/// resources it used if it has exited, without blocking.
+#[cfg(unix)]
+fn try_wait_os(pid: pid_t,
+               _handle: *()) -> Option<(p::ProcessExit, bool, Option<p::ResourceUsage>)> {
+    match try_waitpid(pid) {
+        Ok(exit) => exit.map(|(exit, core_dumped, rusage)| (exit, core_dumped, Some(rusage))),
+        Err(e) => fail!("unknown waitpid error: {:?}", e),
+    }
+}
+
+/// Returns the exit status of the child `handle` if it has exited, without
+/// blocking. The resources it used are queried from the handle later on.
+#[cfg(windows)]
+fn try_wait_os(_pid: pid_t,
+               handle: *()) -> Option<(p::ProcessExit, bool, Option<p::ResourceUsage>)> {
+    use std::libc::consts::os::extra::{FALSE, STILL_ACTIVE};
+    use std::libc::funcs::extra::kernel32::GetExitCodeProcess;
+
+    let mut status = 0;
+    if unsafe { GetExitCodeProcess(cast::transmute(handle), &mut status) } == FALSE {
+        fail!("failure in GetExitCodeProcess: {}", os::last_os_error());
+    }
+    if status == STILL_ACTIVE {
+        None
+    } else {
+        Some((p::ExitStatus(status as int), false, None))
+    }
+}
+
+/// Blocks for up to `msecs` milliseconds or until the child exits, whichever
+/// comes first, returning false if there is no way to wait for the exit of
+/// the child for a limited time.
+#[cfg(target_os = "linux")]
+fn wait_exit(_pid: pid_t, _handle: *(), pidfd: Option<c_int>, msecs: u64) -> bool {
+    match pidfd {
+        Some(fd) => super::pidfd::wait(fd, msecs),
+        None => false,
+    }
+}
+
+#[cfg(target_os = "android")]
+fn wait_exit(_pid: pid_t, _handle: *(), _pidfd: Option<c_int>, _msecs: u64) -> bool {
+    false
+}
+
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn wait_exit(pid: pid_t, _handle: *(), _pidfd: Option<c_int>, msecs: u64) -> bool {
+    mod imp {
+        use std::libc::{c_int, c_void, intptr_t, timespec, uintptr_t};
+
+        pub static EVFILT_PROC: i16 = -5;
+        pub static EV_ADD: u16 = 0x1;
+        pub static EV_ONESHOT: u16 = 0x10;
+        pub static NOTE_EXIT: u32 = 0x80000000;
+
+        pub struct kevent {
+            ident: uintptr_t,
+            filter: i16,
+            flags: u16,
+            fflags: u32,
+            data: intptr_t,
+            udata: *c_void,
         }
 
-        #[cfg(target_os = "macos")]
-        #[cfg(target_os = "freebsd")]
-        mod imp {
-            pub fn WIFEXITED(status: i32) -> bool { (status & 0x7f) == 0 }
-            pub fn WEXITSTATUS(status: i32) -> i32 { status >> 8 }
-            pub fn WTERMSIG(status: i32) -> i32 { status & 0o177 }
-        }
-
-        let mut status = 0 as c_int;
-        match retry(|| unsafe { wait::waitpid(pid, &mut status, 0) }) {
-            -1 => fail!("unknown waitpid error: {:?}", super::last_error()),
-            _ => {
-                if imp::WIFEXITED(status) {
-                    p::ExitStatus(imp::WEXITSTATUS(status) as int)
-                } else {
-                    p::ExitSignal(imp::WTERMSIG(status) as int)
-                }
-            }
+        extern {
+            pub fn kqueue() -> c_int;
+            pub fn kevent(kq: c_int, changes: *kevent, nchanges: c_int,
+                          events: *mut kevent, nevents: c_int,
+                          timeout: *timespec) -> c_int;
         }
     }
+
+    let kq = unsafe { imp::kqueue() };
+    if kq == -1 {
+        return false;
+    }
+    let change = imp::kevent {
+        ident: pid as libc::uintptr_t,
+        filter: imp::EVFILT_PROC,
+        flags: imp::EV_ADD | imp::EV_ONESHOT,
+        fflags: imp::NOTE_EXIT,
+        data: 0,
+        udata: ptr::null(),
+    };
+    let mut event = change;
+    let timeout = libc::timespec {
+        tv_sec: (msecs / 1000) as libc::time_t,
+        tv_nsec: ((msecs % 1000) * 1000000) as libc::c_long,
+    };
+    let ret = retry(|| unsafe { imp::kevent(kq, &change, 1, &mut event, 1, &timeout) });
+    // A child which has exited already cannot be registered for its exit.
+    let waited = ret != -1 || os::errno() as c_int == libc::ESRCH;
+    unsafe { libc::close(kq); }
+    waited
+}
+
+#[cfg(windows)]
+fn wait_exit(_pid: pid_t, handle: *(), _pidfd: Option<c_int>, msecs: u64) -> bool {
+    use std::libc::types::os::arch::extra::DWORD;
+    use std::libc::consts::os::extra::{INFINITE, WAIT_FAILED};
+    use std::libc::funcs::extra::kernel32::WaitForSingleObject;
+
+    let msecs = cmp::min(msecs, (INFINITE - 1) as u64) as DWORD;
+    unsafe { WaitForSingleObject(cast::transmute(handle), msecs) != WAIT_FAILED }
+}
+
+#[cfg(unix)]
+fn translate_status(status: c_int) -> p::ProcessExit {
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "android")]
+    mod imp {
+        pub fn WIFEXITED(status: i32) -> bool { (status & 0xff) == 0 }
+        pub fn WEXITSTATUS(status: i32) -> i32 { (status >> 8) & 0xff }
+        pub fn WTERMSIG(status: i32) -> i32 { status & 0x7f }
+    }
+
+    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "freebsd")]
+    mod imp {
+        pub fn WIFEXITED(status: i32) -> bool { (status & 0x7f) == 0 }
+        pub fn WEXITSTATUS(status: i32) -> i32 { status >> 8 }
+        pub fn WTERMSIG(status: i32) -> i32 { status & 0o177 }
+    }
+
+    if imp::WIFEXITED(status) {
+        p::ExitStatus(imp::WEXITSTATUS(status) as int)
+    } else {
+        p::ExitSignal(imp::WTERMSIG(status) as int)
+    }
+}
+
+/// Returns whether the wait status `status` says that the child dumped core
+/// when it was killed, which is the same bit on every unix.
+#[cfg(unix)]
+fn core_dumped(status: c_int) -> bool {
+    (status & 0x80) != 0
 }
 
 #[cfg(test)]
@@ -685,20 +2277,36 @@ mod tests {
     fn test_make_command_line() {
         use super::make_command_line;
         assert_eq!(
-            make_command_line("prog", [~"aaa", ~"bbb", ~"ccc"]),
+            make_command_line("prog", [~"aaa", ~"bbb", ~"ccc"], false),
             ~"prog aaa bbb ccc"
         );
         assert_eq!(
-            make_command_line("C:\\Program Files\\blah\\blah.exe", [~"aaa"]),
+            make_command_line("C:\\Program Files\\blah\\blah.exe", [~"aaa"], false),
             ~"\"C:\\Program Files\\blah\\blah.exe\" aaa"
         );
         assert_eq!(
-            make_command_line("C:\\Program Files\\test", [~"aa\"bb"]),
+            make_command_line("C:\\Program Files\\test", [~"aa\"bb"], false),
             ~"\"C:\\Program Files\\test\" aa\\\"bb"
         );
         assert_eq!(
-            make_command_line("echo", [~"a b c"]),
+            make_command_line("echo", [~"a b c"], false),
             ~"echo \"a b c\""
         );
     }
+
+    #[test] #[cfg(windows)]
+    fn test_make_batch_command_line() {
+        use super::{make_command_line, is_batch_file};
+        assert!(is_batch_file("C:\\build\\Make.CMD"));
+        assert!(!is_batch_file("cmd.exe"));
+        assert_eq!(
+            make_command_line("C:\\Program Files\\x.bat", [~"a b", ~"x^y", ~"\"q\"", ~""],
+                              true),
+            ~"\"C:\\Program Files\\x.bat\" \"a b\" \"x^y\" \"\"\"q\"\"\" \"\""
+        );
+        assert_eq!(
+            make_command_line("x.bat", [~"100%", ~"a\\"], true),
+            ~"x.bat 100%%cd:~,% a\\"
+        );
+    }
 }
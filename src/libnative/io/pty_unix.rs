@@ -0,0 +1,100 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Pseudo-terminals for the standard streams of children
+//!
+//! A child given the slave side of a pseudo-terminal as its controlling
+//! terminal believes it talks to a user, which programs such as ssh and sudo
+//! insist on before they prompt for a password, and which makes most others
+//! line-buffer their output and color it. The parent reads and writes the
+//! master side.
+
+use std::io;
+use std::libc::c_int;
+use std::libc;
+use std::os;
+use std::rt::rtio;
+
+use super::IoResult;
+use super::file::FileDesc;
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+static O_NOCTTY: c_int = 0o400;
+#[cfg(target_os = "macos")]
+static O_NOCTTY: c_int = 0x20000;
+#[cfg(target_os = "freebsd")]
+static O_NOCTTY: c_int = 0x8000;
+
+/// Opens a new pseudo-terminal, returning its master and slave sides. Neither
+/// becomes the controlling terminal of the current process.
+pub fn open() -> IoResult<(FileDesc, FileDesc)> {
+    unsafe {
+        let master = imp::posix_openpt(libc::O_RDWR | O_NOCTTY);
+        if master == -1 {
+            return Err(super::last_error());
+        }
+        let master = FileDesc::new(master, true);
+        if imp::grantpt(master.fd()) == -1 || imp::unlockpt(master.fd()) == -1 {
+            return Err(super::last_error());
+        }
+        // ptsname is not reentrant, but its result is only used right away
+        // and the name is the same for any caller asking about this master.
+        let name = imp::ptsname(master.fd());
+        if name.is_null() {
+            return Err(super::last_error());
+        }
+        let slave = libc::open(name, libc::O_RDWR | O_NOCTTY, 0);
+        if slave == -1 {
+            return Err(super::last_error());
+        }
+        Ok((master, FileDesc::new(slave, true)))
+    }
+}
+
+/// The master side of a pseudo-terminal.
+///
+/// Once every process has closed the slave side, reading the master fails
+/// with EIO on Linux rather than returning the end of file, which is what it
+/// is reported as here.
+pub struct PtyMaster {
+    priv fd: FileDesc,
+}
+
+impl PtyMaster {
+    pub fn new(fd: FileDesc) -> PtyMaster {
+        PtyMaster { fd: fd }
+    }
+}
+
+impl rtio::RtioPipe for PtyMaster {
+    fn read(&mut self, buf: &mut [u8]) -> Result<uint, io::IoError> {
+        match self.fd.inner_read(buf) {
+            Err(..) if os::errno() as c_int == libc::EIO => {
+                Err(io::standard_error(io::EndOfFile))
+            }
+            ret => ret,
+        }
+    }
+    fn write(&mut self, buf: &[u8]) -> Result<(), io::IoError> {
+        self.fd.inner_write(buf)
+    }
+}
+
+mod imp {
+    use std::libc::{c_char, c_int};
+
+    extern {
+        pub fn posix_openpt(flags: c_int) -> c_int;
+        pub fn grantpt(fd: c_int) -> c_int;
+        pub fn unlockpt(fd: c_int) -> c_int;
+        pub fn ptsname(fd: c_int) -> *c_char;
+    }
+}
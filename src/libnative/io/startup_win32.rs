@@ -0,0 +1,188 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Security attributes and extended startup information for CreateProcess
+
+use std::io::process::Mitigations;
+use std::libc::{DWORD, LPVOID, TRUE, STARTUPINFO};
+use std::libc;
+use std::mem;
+use std::ptr;
+use std::vec;
+
+use super::IoResult;
+
+/// A SECURITY_ATTRIBUTES structure holding a security descriptor which was
+/// given in the security descriptor definition language (SDDL).
+pub struct SecurityAttributes {
+    priv attrs: imp::SECURITY_ATTRIBUTES,
+}
+
+impl SecurityAttributes {
+    pub fn from_sddl(sddl: &str) -> IoResult<SecurityAttributes> {
+        let mut sd = ptr::mut_null();
+        let ret = sddl.with_c_str(|s| unsafe {
+            imp::ConvertStringSecurityDescriptorToSecurityDescriptorA(
+                s, imp::SDDL_REVISION_1, &mut sd, ptr::mut_null())
+        });
+        if ret == 0 {
+            return Err(super::last_error());
+        }
+        Ok(SecurityAttributes {
+            attrs: imp::SECURITY_ATTRIBUTES {
+                nLength: mem::size_of::<imp::SECURITY_ATTRIBUTES>() as DWORD,
+                lpSecurityDescriptor: sd,
+                bInheritHandle: TRUE,
+            }
+        })
+    }
+
+    /// Returns a pointer suitable for the security attributes arguments of
+    /// CreateProcess, which is valid as long as `self` is.
+    pub fn as_ptr(&self) -> LPVOID {
+        &self.attrs as *imp::SECURITY_ATTRIBUTES as LPVOID
+    }
+}
+
+impl Drop for SecurityAttributes {
+    fn drop(&mut self) {
+        unsafe { imp::LocalFree(self.attrs.lpSecurityDescriptor); }
+    }
+}
+
+/// A STARTUPINFOEX structure, which is a STARTUPINFO extended with a list of
+/// attributes for the new process.
+pub struct StartupInfoEx {
+    priv info: imp::STARTUPINFOEX,
+    priv mitigation_policy: u64,
+    priv child_policy: DWORD,
+    priv list: ~[u8],
+}
+
+impl StartupInfoEx {
+    /// Extends `si` with the process mitigation policies of `mitigations`.
+    pub fn new(si: STARTUPINFO, mitigations: &Mitigations) -> IoResult<~StartupInfoEx> {
+        let mut mitigation_policy = 0;
+        if mitigations.dep {
+            mitigation_policy |= imp::PROCESS_CREATION_MITIGATION_POLICY_DEP_ENABLE;
+        }
+        if mitigations.force_aslr {
+            mitigation_policy |=
+                imp::PROCESS_CREATION_MITIGATION_POLICY_FORCE_RELOCATE_IMAGES_ALWAYS_ON;
+        }
+        let child_policy = if mitigations.no_child_processes {
+            imp::PROCESS_CREATION_CHILD_PROCESS_RESTRICTED
+        } else {
+            0
+        };
+
+        let mut size = 0;
+        unsafe {
+            imp::InitializeProcThreadAttributeList(ptr::mut_null(), 2, 0, &mut size);
+        }
+        // The attributes refer to the policies by address, so they are boxed
+        // to stay at the same place until the process is created.
+        let mut ret = ~StartupInfoEx {
+            info: imp::STARTUPINFOEX { StartupInfo: si, lpAttributeList: ptr::mut_null() },
+            mitigation_policy: mitigation_policy,
+            child_policy: child_policy,
+            list: vec::from_elem(size as uint, 0u8),
+        };
+        ret.info.StartupInfo.cb = mem::size_of::<imp::STARTUPINFOEX>() as DWORD;
+        unsafe {
+            let list = ret.list.as_mut_ptr() as LPVOID;
+            if imp::InitializeProcThreadAttributeList(list, 2, 0, &mut size) == 0 {
+                return Err(super::last_error());
+            }
+            ret.info.lpAttributeList = list;
+            if ret.mitigation_policy != 0 &&
+               imp::UpdateProcThreadAttribute(list, 0,
+                                              imp::PROC_THREAD_ATTRIBUTE_MITIGATION_POLICY,
+                                              &ret.mitigation_policy as *u64 as LPVOID,
+                                              mem::size_of::<u64>() as libc::SIZE_T,
+                                              ptr::mut_null(), ptr::mut_null()) == 0 {
+                return Err(super::last_error());
+            }
+            if ret.child_policy != 0 &&
+               imp::UpdateProcThreadAttribute(list, 0,
+                                              imp::PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY,
+                                              &ret.child_policy as *DWORD as LPVOID,
+                                              mem::size_of::<DWORD>() as libc::SIZE_T,
+                                              ptr::mut_null(), ptr::mut_null()) == 0 {
+                return Err(super::last_error());
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the extended startup information, to be passed to
+    /// CreateProcess along with EXTENDED_STARTUPINFO_PRESENT.
+    pub fn as_mut_ptr(&mut self) -> *mut STARTUPINFO {
+        &mut self.info.StartupInfo as *mut STARTUPINFO
+    }
+}
+
+impl Drop for StartupInfoEx {
+    fn drop(&mut self) {
+        if !self.info.lpAttributeList.is_null() {
+            unsafe { imp::DeleteProcThreadAttributeList(self.info.lpAttributeList); }
+        }
+    }
+}
+
+pub static EXTENDED_STARTUPINFO_PRESENT: DWORD = 0x00080000;
+
+#[allow(non_camel_case_types)]
+mod imp {
+    use std::libc::{BOOL, DWORD, LPVOID, LPCSTR, SIZE_T, STARTUPINFO};
+
+    pub static SDDL_REVISION_1: DWORD = 1;
+
+    pub static PROC_THREAD_ATTRIBUTE_MITIGATION_POLICY: SIZE_T = 0x00020007;
+    pub static PROC_THREAD_ATTRIBUTE_CHILD_PROCESS_POLICY: SIZE_T = 0x0002000e;
+
+    pub static PROCESS_CREATION_MITIGATION_POLICY_DEP_ENABLE: u64 = 0x01;
+    pub static PROCESS_CREATION_MITIGATION_POLICY_FORCE_RELOCATE_IMAGES_ALWAYS_ON: u64 =
+        0x1 << 8;
+
+    pub static PROCESS_CREATION_CHILD_PROCESS_RESTRICTED: DWORD = 0x01;
+
+    pub struct SECURITY_ATTRIBUTES {
+        nLength: DWORD,
+        lpSecurityDescriptor: LPVOID,
+        bInheritHandle: BOOL,
+    }
+
+    pub struct STARTUPINFOEX {
+        StartupInfo: STARTUPINFO,
+        lpAttributeList: LPVOID,
+    }
+
+    extern "system" {
+        pub fn ConvertStringSecurityDescriptorToSecurityDescriptorA(
+            StringSecurityDescriptor: LPCSTR,
+            StringSDRevision: DWORD,
+            SecurityDescriptor: *mut LPVOID,
+            SecurityDescriptorSize: *mut DWORD) -> BOOL;
+        pub fn LocalFree(hMem: LPVOID) -> LPVOID;
+        pub fn InitializeProcThreadAttributeList(lpAttributeList: LPVOID,
+                                                 dwAttributeCount: DWORD,
+                                                 dwFlags: DWORD,
+                                                 lpSize: *mut SIZE_T) -> BOOL;
+        pub fn UpdateProcThreadAttribute(lpAttributeList: LPVOID,
+                                         dwFlags: DWORD,
+                                         Attribute: SIZE_T,
+                                         lpValue: LPVOID,
+                                         cbSize: SIZE_T,
+                                         lpPreviousValue: LPVOID,
+                                         lpReturnSize: *mut SIZE_T) -> BOOL;
+        pub fn DeleteProcThreadAttributeList(lpAttributeList: LPVOID);
+    }
+}
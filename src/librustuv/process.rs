@@ -8,8 +8,10 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::cast;
 use std::io::IoError;
 use std::io::process;
+use std::io;
 use std::libc::c_int;
 use std::libc;
 use std::ptr;
@@ -41,37 +43,67 @@ impl Process {
     /// Returns either the corresponding process object or an error which
     /// occurred.
     pub fn spawn(io_loop: &mut UvIoFactory, config: process::ProcessConfig)
-                -> Result<(~Process, ~[Option<PipeWatcher>]), UvError>
+                -> Result<(~Process, ~[Option<PipeWatcher>]), IoError>
     {
+        let flags = match process_flags(&config) {
+            Ok(flags) => flags,
+            Err(e) => return Err(e),
+        };
         let cwd = config.cwd.map(|s| s.to_c_str());
         let io = config.io;
+        // A child kept from the terminal does not read from it either.
+        let null_input = match io.head() {
+            Some(&process::InheritFd(fd)) if config.no_ctty && !cfg!(windows) &&
+                                              unsafe { libc::isatty(fd) } == 1 => {
+                match "/dev/null".with_c_str(|p| unsafe { libc::open(p, libc::O_RDONLY, 0) }) {
+                    -1 => return Err(io::IoError {
+                        kind: io::OtherIoError,
+                        desc: "couldn't open the null device",
+                        detail: None,
+                    }),
+                    fd => Some(fd),
+                }
+            }
+            _ => None,
+        };
         let mut stdio = vec::with_capacity::<uvll::uv_stdio_container_t>(io.len());
         let mut ret_io = vec::with_capacity(io.len());
         unsafe {
             stdio.set_len(io.len());
-            for (slot, other) in stdio.iter().zip(io.iter()) {
-                let io = set_stdio(slot as *uvll::uv_stdio_container_t, other,
+            for (i, (slot, other)) in stdio.iter().zip(io.iter()).enumerate() {
+                let other = match null_input {
+                    Some(fd) if i == 0 => process::InheritFd(fd),
+                    _ => *other,
+                };
+                let io = set_stdio(slot as *uvll::uv_stdio_container_t, &other,
                                    io_loop);
                 ret_io.push(io);
             }
         }
 
-        let ret = with_argv(config.program, config.args, |argv| {
+        let program = config.program.to_c_str();
+        let argv0 = config.argv0.unwrap_or(config.program);
+        // libuv joins the arguments without quoting them in verbatim mode.
+        let (argv0, args) = match config.raw_command_line {
+            Some(line) if cfg!(windows) => (line, &[]),
+            _ => (argv0, config.args),
+        };
+        let ret = with_argv(argv0, args, |argv| {
             with_env(config.env, |envp| {
                 let options = uvll::uv_process_options_t {
                     exit_cb: on_exit,
-                    file: unsafe { *argv },
+                    file: program.with_ref(|p| p),
                     args: argv,
                     env: envp,
                     cwd: match cwd {
                         Some(ref cwd) => cwd.with_ref(|p| p),
                         None => ptr::null(),
                     },
-                    flags: 0,
+                    flags: flags,
                     stdio_count: stdio.len() as libc::c_int,
                     stdio: stdio.as_ptr(),
-                    uid: 0,
-                    gid: 0,
+                    uid: config.uid.unwrap_or(0) as uvll::uv_uid_t,
+                    gid: config.gid.unwrap_or(0) as uvll::uv_gid_t,
                 };
 
                 let handle = UvHandle::alloc(None::<Process>, uvll::UV_PROCESS);
@@ -85,10 +117,13 @@ impl Process {
                     uvll::uv_spawn(io_loop.uv_loop(), handle, &options)
                 } {
                     0 => Ok(process.install()),
-                    err => Err(UvError(err)),
+                    err => Err(uv_error_to_io_error(UvError(err))),
                 }
             })
         });
+        for &fd in null_input.iter() {
+            unsafe { libc::close(fd); }
+        }
 
         match ret {
             Ok(p) => Ok((p, ret_io)),
@@ -97,6 +132,135 @@ impl Process {
     }
 }
 
+/// Translates the parts of `config` which libuv handles itself into the flags
+/// of `uv_process_options_t`, failing for those which it cannot honor rather
+/// than spawning a process configured differently than asked.
+///
+/// `inherit_fds` and `reset_signals` are the exceptions, as failing for their
+/// defaults would fail every spawn: libuv neither closes the descriptors of
+/// the child nor resets its signals, which their documentation says.
+fn process_flags(config: &process::ProcessConfig) -> Result<c_int, IoError> {
+    let mut flags = 0;
+    if config.no_network {
+        return Err(unsupported("network isolation"));
+    }
+    if config.io.iter().any(|io| match *io { process::CreatePty => true, _ => false }) {
+        return Err(unsupported("pseudo-terminals"));
+    }
+    if config.before_exec.is_some() {
+        return Err(unsupported("before_exec hooks"));
+    }
+    if config.rlimits.len() > 0 {
+        return Err(unsupported("resource limits"));
+    }
+    if config.priority.is_some() {
+        return Err(unsupported("scheduling priority"));
+    }
+    if config.cpu_affinity.is_some() {
+        return Err(unsupported("CPU affinity"));
+    }
+    if config.chroot.is_some() {
+        return Err(unsupported("chroot"));
+    }
+    if config.namespaces != process::Namespaces::new() {
+        return Err(unsupported("namespaces"));
+    }
+    if cfg!(windows) {
+        if config.uid.is_some() || config.gid.is_some() {
+            return Err(unsupported("uid/gid"));
+        }
+        if config.new_process_group || config.new_session {
+            return Err(unsupported("new process group"));
+        }
+        if config.raw_command_line.is_some() {
+            flags |= uvll::PROCESS_WINDOWS_VERBATIM_ARGUMENTS;
+        } else if config.quoting == process::BatchQuoting {
+            return Err(unsupported("batch quoting"));
+        }
+        match config.console {
+            process::InheritConsole => {}
+            process::NoConsole => flags |= uvll::PROCESS_DETACHED,
+            process::NewConsole => return Err(unsupported("new console")),
+            process::HiddenConsole => flags |= uvll::PROCESS_WINDOWS_HIDE,
+        }
+        if config.creation_flags != 0 {
+            return Err(unsupported("creation flags"));
+        }
+        if config.job_limits.is_some() {
+            return Err(unsupported("job limits"));
+        }
+        if config.breakaway_from_job {
+            return Err(unsupported("job breakaway"));
+        }
+        if config.desktop.is_some() {
+            return Err(unsupported("desktop"));
+        }
+        if config.process_security.is_some() || config.thread_security.is_some() {
+            return Err(unsupported("security descriptors"));
+        }
+        if config.mitigations != process::Mitigations::new() {
+            return Err(unsupported("mitigation policies"));
+        }
+    } else {
+        if config.spawn_attributes.is_some() {
+            return Err(unsupported("spawn attributes"));
+        }
+        if config.umask.is_some() {
+            return Err(unsupported("umask"));
+        }
+        if config.uid.is_some() {
+            flags |= uvll::PROCESS_SETUID;
+        }
+        if config.gid.is_some() {
+            flags |= uvll::PROCESS_SETGID;
+        }
+        // A detached child is the leader of a new session.
+        if config.new_session || config.no_ctty {
+            flags |= uvll::PROCESS_DETACHED;
+        } else if config.new_process_group {
+            return Err(unsupported("new process group"));
+        }
+        if cfg!(target_os = "macos") && config.sandbox_profile.is_some() {
+            return Err(unsupported("sandbox profile"));
+        }
+        if cfg!(target_os = "freebsd") && config.process_controls.is_some() {
+            return Err(unsupported("process controls"));
+        }
+        if config.parent_death_signal.is_some() {
+            return Err(unsupported("parent death signal"));
+        }
+        if cfg!(target_os = "linux") && config.capabilities.is_some() {
+            return Err(unsupported("capabilities"));
+        }
+        if cfg!(target_os = "linux") && config.no_new_privs {
+            return Err(unsupported("no_new_privs"));
+        }
+        if cfg!(target_os = "linux") && config.fs_isolation.is_some() {
+            return Err(unsupported("filesystem isolation"));
+        }
+    }
+    Ok(flags)
+}
+
+/// Returns the signals which stop and continue a child, SIGSTOP and SIGCONT,
+/// which libuv only has on unix.
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+fn stop_signals() -> Option<(int, int)> { Some((19, 18)) }
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn stop_signals() -> Option<(int, int)> { Some((17, 19)) }
+#[cfg(windows)]
+fn stop_signals() -> Option<(int, int)> { None }
+
+fn unsupported(what: &str) -> IoError {
+    IoError {
+        kind: io::IoUnavailable,
+        desc: "process option not supported by libuv",
+        detail: Some(what.to_owned()),
+    }
+}
+
 extern fn on_exit(handle: *uvll::uv_process_t,
                   exit_status: i64,
                   term_signal: libc::c_int) {
@@ -138,6 +302,8 @@ unsafe fn set_stdio(dst: *uvll::uv_stdio_container_t,
             uvll::set_stdio_container_stream(dst, pipe.handle());
             Some(pipe)
         }
+        // Rejected by process_flags before anything is set up.
+        process::CreatePty => unreachable!(),
     }
 }
 
@@ -218,6 +384,66 @@ impl RtioProcess for Process {
 
         self.exit_status.unwrap()
     }
+
+    fn pause(&mut self) -> Result<(), IoError> {
+        match stop_signals() {
+            Some((stop, _)) => self.kill(stop),
+            None => Err(unsupported("pausing")),
+        }
+    }
+
+    fn resume(&mut self) -> Result<(), IoError> {
+        match stop_signals() {
+            Some((_, cont)) => self.kill(cont),
+            None => Err(unsupported("resuming")),
+        }
+    }
+
+    fn try_wait(&mut self) -> Option<process::ProcessExit> {
+        // The exit callback is run by the home scheduler.
+        let _m = self.fire_homing_missile();
+        self.exit_status
+    }
+
+    fn wait_timeout(&mut self, msecs: u64) -> Option<process::ProcessExit> {
+        let _m = self.fire_homing_missile();
+        if self.exit_status.is_some() || msecs == 0 {
+            return self.exit_status;
+        }
+
+        // Whichever of the exit callback and the timer comes first wakes us
+        // up, and the timer is stopped before the other can.
+        let timer = unsafe { uvll::malloc_handle(uvll::UV_TIMER) as *uvll::uv_timer_t };
+        assert!(!timer.is_null());
+        unsafe {
+            let uv_loop = uvll::get_loop_for_uv_handle(self.handle);
+            assert_eq!(uvll::uv_timer_init(uv_loop as *uvll::uv_loop_t, timer), 0);
+        }
+        let me = self as *mut Process as *Process;
+        wait_until_woken_after(&mut self.to_wake, || {
+            unsafe {
+                uvll::set_data_for_uv_handle(timer, me);
+                assert_eq!(uvll::uv_timer_start(timer, on_timeout, msecs, 0), 0);
+            }
+        });
+        unsafe {
+            uvll::uv_timer_stop(timer);
+            uvll::uv_close(timer as *uvll::uv_handle_t, close_timer);
+        }
+        return self.exit_status;
+
+        extern fn on_timeout(timer: *uvll::uv_timer_t, _status: c_int) {
+            let p: &mut Process = unsafe {
+                cast::transmute(uvll::get_data_for_uv_handle(timer))
+            };
+            if p.to_wake.is_none() { return }
+            wakeup(&mut p.to_wake);
+        }
+
+        extern fn close_timer(handle: *uvll::uv_handle_t) {
+            unsafe { uvll::free_handle(handle) }
+        }
+    }
 }
 
 impl Drop for Process {
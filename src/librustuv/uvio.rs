@@ -270,10 +270,21 @@ impl IoFactory for UvIoFactory {
                 Ok((p as ~rtio::RtioProcess,
                     io.move_iter().map(|i| i.map(|p| ~p as ~rtio::RtioPipe)).collect()))
             }
-            Err(e) => Err(uv_error_to_io_error(e)),
+            Err(e) => Err(e),
         }
     }
 
+    fn adopt_process(&mut self, _pid: libc::pid_t)
+        -> Result<~rtio::RtioProcess, IoError>
+    {
+        // libuv can only watch the processes it spawned itself
+        Err(IoError {
+            kind: io::IoUnavailable,
+            desc: "adopting a process is not supported by libuv",
+            detail: None,
+        })
+    }
+
     fn unix_bind(&mut self, path: &CString) -> Result<~rtio::RtioUnixListener, IoError>
     {
         match PipeListener::bind(self, path) {
@@ -418,6 +418,8 @@ pub enum IoErrorKind {
     ResourceUnavailable,
     IoUnavailable,
     InvalidInput,
+    NotExecutable,
+    TimedOut,
 }
 
 // FIXME: #8242 implementing manually because deriving doesn't work for some reason
@@ -442,6 +444,8 @@ impl ToStr for IoErrorKind {
             ResourceUnavailable => ~"ResourceUnavailable",
             ConnectionAborted => ~"ConnectionAborted",
             InvalidInput => ~"InvalidInput",
+            NotExecutable => ~"NotExecutable",
+            TimedOut => ~"TimedOut",
         }
     }
 }
@@ -1284,6 +1288,7 @@ pub fn standard_error(kind: IoErrorKind) -> IoError {
         EndOfFile => "end of file",
         IoUnavailable => "I/O is unavailable",
         InvalidInput => "invalid input",
+        TimedOut => "operation timed out",
         _ => fail!()
     };
     IoError {
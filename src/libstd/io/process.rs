@@ -15,6 +15,7 @@ use prelude::*;
 use libc;
 use io;
 use io::io_error;
+use rt::reaper;
 use rt::rtio::{RtioProcess, IoFactory, LocalIo};
 
 use fmt;
@@ -27,8 +28,10 @@ use fmt;
 #[cfg(not(windows))] pub static MustDieSignal: int = libc::SIGKILL as int;
 
 pub struct Process {
-    priv handle: ~RtioProcess,
+    priv handle: Option<~RtioProcess>,
     io: ~[Option<io::PipeStream>],
+    priv drop_policy: DropPolicy,
+    priv forgotten: bool,
 }
 
 /// This configuration describes how a new process should be spawned. This is
@@ -40,6 +43,25 @@ pub struct ProcessConfig<'a> {
     /// Arguments to pass to the program (doesn't include the program itself)
     args: &'a [~str],
 
+    /// If this is Some(name) then the program is given `name` instead of
+    /// `program` as its first argument, which is how multi-call binaries and
+    /// login shells tell what they are to do. On Windows the program is then
+    /// not searched for in the `PATH`, and has to be given with its
+    /// extension.
+    argv0: Option<&'a str>,
+
+    /// If this is Some(line) then on Windows the process is given `line` as
+    /// its command line, instead of one joined from `argv0` or `program` and
+    /// `args` with the quoting of the C runtime, which programs parsing their
+    /// command line themselves like `cmd.exe` do not follow. The line has to
+    /// start with the program. This is ignored on other platforms.
+    raw_command_line: Option<&'a str>,
+
+    /// How the program and `args` are quoted when they are joined into the
+    /// command line of the process on Windows. This is ignored on other
+    /// platforms.
+    quoting: ArgQuoting,
+
     /// Optional environment to specify for the program. If this is None, then
     /// it will inherit the current process's environment.
     env: Option<&'a [(~str, ~str)]>,
@@ -58,7 +80,561 @@ pub struct ProcessConfig<'a> {
     ///     0 - stdin
     ///     1 - stdout
     ///     2 - stderr
-    io: &'a [StdioContainer]
+    ///
+    /// Any further entries set up the descriptors 3, 4, and so on, for
+    /// programs which take side channels on them like `gpg --status-fd`.
+    /// These are not supported on Windows.
+    io: &'a [StdioContainer],
+
+    /// Whether the process inherits the descriptors of the current process
+    /// which it is not given in `io`, other than those marked close-on-exec.
+    /// Otherwise they are closed in it, which libuv does not support: in
+    /// green tasks they are inherited either way. On Windows every
+    /// inheritable handle is inherited either way.
+    inherit_fds: bool,
+
+    /// Whether the disposition of every signal is reset to the default action
+    /// in the process on Unix, and its signal mask cleared unless
+    /// `spawn_attributes` give one, before it executes the program. Signals
+    /// which the current process ignores, like SIGPIPE, are otherwise ignored
+    /// by the program too. libuv leaves the signals of the process as it
+    /// does either way, and this is ignored on Windows.
+    reset_signals: bool,
+
+    /// Whether on Unix the process is created with `vfork` instead of `fork`,
+    /// which does not copy the page tables of the current process and is
+    /// therefore much faster for processes with large heaps. The process
+    /// borrows the memory of the current process until it executes the
+    /// program, so it only sets up its standard streams and its signals, and
+    /// spawning it fails with `InvalidInput` if any option needing more setup
+    /// in it is given, such as `cwd`, more than three entries in `io` or a
+    /// pseudo-terminal. libuv always forks, and this is ignored on Windows.
+    vfork: bool,
+
+    /// Which console the process is attached to on Windows. This is ignored on
+    /// other platforms.
+    console: ConsoleMode,
+
+    /// If this is Some(limits) then on Windows the process is placed in a new
+    /// Job Object enforcing the given limits on it and on all of its
+    /// descendants. This is ignored on other platforms.
+    job_limits: Option<JobLimits>,
+
+    /// Whether on Windows the process should break away from the job of the
+    /// current process, if it is in one which allows that. This is ignored on
+    /// other platforms.
+    breakaway_from_job: bool,
+
+    /// The window station and desktop (as in `winsta0\default`) the process
+    /// is started on, on Windows. If this is None, it is started on the same
+    /// desktop as the current process. This is ignored on other platforms.
+    desktop: Option<&'a str>,
+
+    /// Security descriptors for the handles of the process and of its main
+    /// thread on Windows, in the security descriptor definition language
+    /// (SDDL). If these are None, the handles get the default security
+    /// descriptor. These are ignored on other platforms.
+    process_security: Option<&'a str>,
+    thread_security: Option<&'a str>,
+
+    /// The mitigation policies the process is started with on Windows. This
+    /// is ignored on other platforms.
+    mitigations: Mitigations,
+
+    /// If this is Some(attributes) then on Unix the given attributes are
+    /// applied to the process before it executes the program. This is
+    /// ignored on other platforms.
+    spawn_attributes: Option<&'a SpawnAttributes>,
+
+    /// If this is Some(profile) then on OS X the sandbox profile (in the
+    /// sandbox profile language used by `sandbox-exec`) is applied to the
+    /// process before it executes the program. This is ignored on other
+    /// platforms.
+    sandbox_profile: Option<&'a str>,
+
+    /// If this is Some(controls) then on FreeBSD the given process controls
+    /// are applied with `procctl`. This is ignored on other platforms.
+    process_controls: Option<&'a ProcessControls>,
+
+    /// If this is Some(signal) then the signal is delivered to the process
+    /// when the current process exits, as with `PR_SET_PDEATHSIG` on Linux
+    /// and `PROC_PDEATHSIG_CTL` on FreeBSD. Spawning the process fails on
+    /// other platforms.
+    parent_death_signal: Option<int>,
+
+    /// If this is Some(capabilities) then on Linux the capability sets of the
+    /// process are reduced as described before it executes the program. This
+    /// is ignored on other platforms.
+    capabilities: Option<&'a Capabilities>,
+
+    /// Whether on Linux the process is barred from gaining privileges, as
+    /// with `PR_SET_NO_NEW_PRIVS`, before it executes the program. This is
+    /// ignored on other platforms.
+    no_new_privs: bool,
+
+    /// If this is Some(isolation) then on Linux the process gets a mount
+    /// namespace of its own, set up as described before it executes the
+    /// program. This is ignored on other platforms.
+    fs_isolation: Option<&'a FsIsolation>,
+
+    /// Whether the process is cut off from the network: on Linux it gets an
+    /// empty network namespace of its own, and on OS X a sandbox profile
+    /// denying network access is applied to it. Spawning the process fails
+    /// on other platforms.
+    no_network: bool,
+
+    /// If this is Some(mask) then on Unix the file mode creation mask of the
+    /// process is set to it before it executes the program. This is ignored
+    /// on Windows.
+    umask: Option<libc::mode_t>,
+
+    /// If this is Some(uid) then on Unix the process runs as the user `uid`,
+    /// which is set just before it executes the program. Spawning the
+    /// process fails on Windows.
+    uid: Option<uint>,
+
+    /// If this is Some(gid) then on Unix the process runs with the group
+    /// `gid`, which is set just before it executes the program. Spawning the
+    /// process fails on Windows.
+    gid: Option<uint>,
+
+    /// Whether the process is put into a new process group of its own, so
+    /// that the signals sent to the process group of the parent by its
+    /// terminal do not reach it. On Windows this is `CREATE_NEW_PROCESS_GROUP`.
+    new_process_group: bool,
+
+    /// Whether on Unix the process starts a new session, as with `setsid`,
+    /// which also puts it in a new process group and detaches it from the
+    /// controlling terminal of the parent. Windows has no sessions, and only
+    /// puts the process in a new process group.
+    new_session: bool,
+
+    /// Whether on Unix the process is kept from the controlling terminal of
+    /// the parent: it starts a new session as with `new_session`, so that it
+    /// is neither sent the signals of the terminal nor able to open it, and
+    /// a standard input it would inherit from a terminal is replaced with the
+    /// null device. This is ignored on Windows.
+    no_ctty: bool,
+
+    /// If this is Some(hook) then on Unix the hook is called in the process
+    /// between the fork and the exec, after everything else has been set up.
+    /// If it returns false, spawning fails with the error of the value it
+    /// left in `errno`. The hook runs in a copy of the parent in which only
+    /// the forking thread survived, so it may only call functions which are
+    /// async-signal-safe. Spawning the process fails on Windows.
+    before_exec: Option<'a || -> bool>,
+
+    /// Limits on the resources of the process, set on Unix with `setrlimit`
+    /// before it executes the program, as the resource with its soft and
+    /// hard limit. A limit of `u64::MAX` stands for no limit.
+    /// Spawning the process fails on Windows if any are given.
+    rlimits: &'a [(Resource, u64, u64)],
+
+    /// If this is Some(nice) then the process is started with the scheduling
+    /// priority `nice`, from -20 for the most favorable to 19 for the least,
+    /// as with `setpriority` on Unix. On Windows the niceness is mapped to
+    /// the closest priority class.
+    priority: Option<int>,
+
+    /// If this is Some(cpus) then the process may only run on the CPUs with
+    /// the given numbers, as set with `sched_setaffinity` on Linux and
+    /// `SetProcessAffinityMask` on Windows. Spawning the process fails on
+    /// other platforms.
+    cpu_affinity: Option<&'a [uint]>,
+
+    /// If this is Some(dir) then on Unix the root directory of the process is
+    /// changed to `dir` with `chroot` before it executes the program, which
+    /// is then looked up inside of it, as is `cwd`. This needs privileges.
+    /// Spawning the process fails on Windows.
+    chroot: Option<&'a str>,
+
+    /// The namespaces of its own the process is created in on Linux.
+    /// Spawning the process fails on other platforms if any are asked for.
+    namespaces: Namespaces,
+
+    /// Further process creation flags, such as `CREATE_DEFAULT_ERROR_MODE`,
+    /// which are or'ed on Windows into the flags implied by the other
+    /// options. This is ignored on other platforms.
+    creation_flags: u32,
+}
+
+impl<'a> ProcessConfig<'a> {
+    /// Creates a new configuration with an empty program and the defaults for
+    /// every other field, to be filled in with the struct update syntax.
+    pub fn new<'a>() -> ProcessConfig<'a> {
+        ProcessConfig {
+            program: "",
+            args: &[],
+            argv0: None,
+            raw_command_line: None,
+            quoting: AutoQuoting,
+            env: None,
+            cwd: None,
+            io: &[],
+            inherit_fds: false,
+            reset_signals: true,
+            vfork: false,
+            console: InheritConsole,
+            job_limits: None,
+            breakaway_from_job: false,
+            desktop: None,
+            process_security: None,
+            thread_security: None,
+            mitigations: Mitigations::new(),
+            spawn_attributes: None,
+            sandbox_profile: None,
+            process_controls: None,
+            parent_death_signal: None,
+            capabilities: None,
+            no_new_privs: false,
+            fs_isolation: None,
+            no_network: false,
+            umask: None,
+            uid: None,
+            gid: None,
+            new_process_group: false,
+            new_session: false,
+            no_ctty: false,
+            before_exec: None,
+            rlimits: &[],
+            priority: None,
+            cpu_affinity: None,
+            chroot: None,
+            namespaces: Namespaces::new(),
+            creation_flags: 0,
+        }
+    }
+}
+
+/// Describes which console a child process gets on Windows.
+#[deriving(Clone, Eq)]
+pub enum ConsoleMode {
+    /// The child is attached to the console of the parent, if it has one.
+    InheritConsole,
+
+    /// A new console is allocated for the child, as for a terminal emulator
+    /// starting a shell.
+    NewConsole,
+
+    /// The child runs without any console, as for a service or a GUI
+    /// launcher starting a background tool. A console program which wants one
+    /// has to allocate it itself.
+    NoConsole,
+
+    /// A new console is allocated for the child but its window is never
+    /// shown, as with `CREATE_NO_WINDOW`, so that a GUI program can run a
+    /// console helper without a window flashing up. Unlike with NoConsole,
+    /// the children of the helper share its console.
+    HiddenConsole,
+}
+
+/// Describes how the arguments of a child process are quoted in its command
+/// line on Windows, where each program splits it into arguments itself.
+#[deriving(Clone, Eq)]
+pub enum ArgQuoting {
+    /// BatchQuoting is used for programs named with the extension `.bat` or
+    /// `.cmd`, and CrtQuoting for any other.
+    AutoQuoting,
+
+    /// The quoting understood by the C runtime, which most programs split
+    /// their command line with: arguments with whitespace are quoted, and
+    /// quotes inside of them are escaped with backslashes.
+    CrtQuoting,
+
+    /// The quoting understood by `cmd.exe`, which runs batch files: arguments
+    /// with whitespace or any of the characters special to it, such as `&`
+    /// and `^`, are quoted, quotes inside of them are doubled, and `%` is
+    /// kept from expanding variables.
+    ///
+    /// libuv does not support this, and always uses CrtQuoting.
+    BatchQuoting,
+}
+
+/// Limits enforced on a child process and its descendants through a Job
+/// Object on Windows. Limits which are None are not enforced.
+#[deriving(Clone, Eq)]
+pub struct JobLimits {
+    /// The most memory, in bytes, which all processes of the job together
+    /// may commit.
+    job_memory: Option<uint>,
+
+    /// The most memory, in bytes, which any single process of the job may
+    /// commit.
+    process_memory: Option<uint>,
+
+    /// The most processes which may be part of the job at the same time.
+    active_processes: Option<uint>,
+
+    /// The percentage (from 1 to 100) of the CPU time of the machine which
+    /// the job may use, as a hard cap.
+    cpu_rate: Option<uint>,
+
+    /// Whether processes of the job may break away from it when they start
+    /// children of their own with `breakaway_from_job`, so that they can
+    /// manage jobs of their own.
+    allow_breakaway: bool,
+
+    /// Whether all processes of the job are terminated when the job is
+    /// closed, which happens when the `Process` is dropped, as with
+    /// `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`. Killing the process then
+    /// terminates the whole job as well.
+    kill_on_close: bool,
+}
+
+impl JobLimits {
+    /// Creates a set of limits which doesn't limit anything.
+    pub fn new() -> JobLimits {
+        JobLimits {
+            job_memory: None,
+            process_memory: None,
+            active_processes: None,
+            cpu_rate: None,
+            allow_breakaway: false,
+            kill_on_close: false,
+        }
+    }
+}
+
+/// Process mitigation policies which harden a child process on Windows,
+/// typically one which handles untrusted input.
+#[deriving(Clone, Eq)]
+pub struct Mitigations {
+    /// Turn on data execution prevention.
+    dep: bool,
+
+    /// Relocate every image loaded by the process, even those which were not
+    /// built to support address space layout randomization.
+    force_aslr: bool,
+
+    /// Forbid the process from creating child processes.
+    no_child_processes: bool,
+}
+
+impl Mitigations {
+    /// Creates a set of mitigations in which every policy is off.
+    pub fn new() -> Mitigations {
+        Mitigations { dep: false, force_aslr: false, no_child_processes: false }
+    }
+}
+
+/// Attributes applied to a child process on Unix between its creation and
+/// the execution of the program, mirroring the attributes and file actions
+/// of `posix_spawn`.
+#[deriving(Clone, Eq)]
+pub struct SpawnAttributes {
+    /// Signals whose disposition is reset to the default action, as with
+    /// `POSIX_SPAWN_SETSIGDEF`.
+    default_signals: ~[int],
+
+    /// If this is Some(signals) then the signal mask of the process blocks
+    /// exactly the given signals, as with `POSIX_SPAWN_SETSIGMASK`.
+    /// Otherwise no signal is blocked.
+    signal_mask: Option<~[int]>,
+
+    /// If this is Some(pgid) then the process is moved into the process
+    /// group `pgid`, or into a new process group of its own if `pgid` is 0,
+    /// as with `POSIX_SPAWN_SETPGROUP`.
+    process_group: Option<libc::pid_t>,
+
+    /// Whether the process stops itself with SIGSTOP right before executing
+    /// the program, until it is continued with SIGCONT, like
+    /// `POSIX_SPAWN_START_SUSPENDED` on Darwin. Failures to execute the
+    /// program are then reported when it is continued, as the process being
+    /// aborted, instead of as an error of the spawn.
+    start_suspended: bool,
+
+    /// Actions on the file descriptors of the process, performed in order
+    /// after its standard streams are set up. The targets of `DupFd` are kept
+    /// open in the process, unlike any other inherited descriptor.
+    file_actions: ~[FileAction],
+}
+
+impl SpawnAttributes {
+    /// Creates a set of attributes which leaves the process as it would be
+    /// without them.
+    pub fn new() -> SpawnAttributes {
+        SpawnAttributes {
+            default_signals: ~[],
+            signal_mask: None,
+            process_group: None,
+            start_suspended: false,
+            file_actions: ~[],
+        }
+    }
+}
+
+/// An action on the file descriptors of a child process, as with
+/// `posix_spawn_file_actions_t`.
+#[deriving(Clone, Eq)]
+pub enum FileAction {
+    /// Duplicates the first descriptor onto the second.
+    DupFd(libc::c_int, libc::c_int),
+
+    /// Closes the descriptor.
+    CloseFd(libc::c_int),
+}
+
+/// The namespaces of its own a child process is created in on Linux, as
+/// with `unshare`. When the current process is not root, the child is also
+/// created in a new user namespace, in which it is root with its own uid and
+/// gid mapped, so that this works without privileges.
+#[deriving(Clone, Eq)]
+pub struct Namespaces {
+    /// Whether the child gets a mount namespace, as with `CLONE_NEWNS`, so
+    /// that what it mounts is not seen by other processes.
+    mount: bool,
+
+    /// Whether the child gets a pid namespace, as with `CLONE_NEWPID`, in
+    /// which the program is the first process and sees none outside of it.
+    /// With a mount namespace as well, `/proc` is remounted to match.
+    ///
+    /// The program is run by a process forked from the child, which only
+    /// waits for it and exits like it, and it is killed if that process
+    /// dies. The signals sent with `Process::signal`, and by the methods
+    /// built on it, are sent to the program itself, which as the first
+    /// process of the namespace only receives those it handles, besides
+    /// SIGKILL and SIGSTOP.
+    pid: bool,
+
+    /// Whether the child gets a network namespace, as with `CLONE_NEWNET`,
+    /// with nothing but a loopback interface which is down.
+    net: bool,
+
+    /// Whether the child gets an IPC namespace, as with `CLONE_NEWIPC`, with
+    /// System V IPC objects and POSIX message queues of its own.
+    ipc: bool,
+
+    /// Whether the child gets a UTS namespace, as with `CLONE_NEWUTS`, in
+    /// which it can set the host name without affecting other processes.
+    uts: bool,
+}
+
+impl Namespaces {
+    /// Creates a description in which the child shares all namespaces of
+    /// the current process.
+    pub fn new() -> Namespaces {
+        Namespaces { mount: false, pid: false, net: false, ipc: false, uts: false }
+    }
+}
+
+/// A resource of a process which can be limited with `setrlimit`.
+#[deriving(Clone, Eq)]
+pub enum Resource {
+    /// The CPU time the process may use, in seconds, after which it is sent
+    /// SIGXCPU, as with `RLIMIT_CPU`.
+    CpuTime,
+
+    /// The size of the largest file the process may create, in bytes, as
+    /// with `RLIMIT_FSIZE`.
+    FileSize,
+
+    /// The size of the data segment of the process, in bytes, as with
+    /// `RLIMIT_DATA`.
+    DataSize,
+
+    /// The size of the stack of the process, in bytes, as with
+    /// `RLIMIT_STACK`.
+    StackSize,
+
+    /// The size of the core file the process may dump, in bytes, as with
+    /// `RLIMIT_CORE`.
+    CoreSize,
+
+    /// The size of the address space of the process, in bytes, as with
+    /// `RLIMIT_AS`.
+    AddressSpace,
+
+    /// The number of memory bytes the process may lock, as with
+    /// `RLIMIT_MEMLOCK`.
+    LockedMemory,
+
+    /// One more than the highest file descriptor the process may open, as
+    /// with `RLIMIT_NOFILE`.
+    OpenFiles,
+
+    /// The number of processes the user of the process may have, as with
+    /// `RLIMIT_NPROC`.
+    Processes,
+}
+
+/// Process controls applied with `procctl` on FreeBSD, which provide the
+/// cleanup guarantees that `prctl` provides on Linux.
+#[deriving(Clone, Eq)]
+pub struct ProcessControls {
+    /// Whether the current process acquires reaper status before spawning
+    /// the child, so that descendants of the child which are orphaned are
+    /// reparented to the current process rather than to init, as with
+    /// `PROC_REAP_ACQUIRE`.
+    reaper: bool,
+
+    /// Whether the child and its future descendants are protected from being
+    /// killed when the system runs out of swap space, as with
+    /// `PROC_SPROTECT`. This needs superuser privileges.
+    protect: bool,
+
+    /// If this is Some(signal) then the signal is delivered to the child
+    /// when the current process exits, as with `PROC_PDEATHSIG_CTL`.
+    parent_death_signal: Option<int>,
+}
+
+impl ProcessControls {
+    /// Creates a set of controls which leaves the processes as they would be
+    /// without them.
+    pub fn new() -> ProcessControls {
+        ProcessControls { reaper: false, protect: false, parent_death_signal: None }
+    }
+}
+
+/// The capabilities a child is left with on Linux, for privileged daemons
+/// which spawn helpers needing only a few of their privileges.
+///
+/// Capabilities are given by their numbers in `<linux/capability.h>`, such
+/// as 10 for `CAP_NET_BIND_SERVICE`.
+#[deriving(Clone, Eq)]
+pub struct Capabilities {
+    /// The capabilities kept in the effective, permitted and inheritable sets
+    /// of the child, if it has them. The others are dropped.
+    keep: ~[uint],
+
+    /// Whether the capabilities which are not kept are also dropped from the
+    /// bounding set, so that nothing the child executes can regain them.
+    /// This needs `CAP_SETPCAP`. A child running as root regains every
+    /// capability of the bounding set when it executes the program, so
+    /// spawning one raises the `io_error` condition with InvalidInput unless
+    /// this is set.
+    bound: bool,
+}
+
+impl Capabilities {
+    /// Creates a description which drops every capability, but leaves the
+    /// bounding set alone.
+    pub fn new() -> Capabilities {
+        Capabilities { keep: ~[], bound: false }
+    }
+}
+
+/// The isolation of the filesystem of a child on Linux, which gets a mount
+/// namespace of its own so that the mounts made for it are invisible to the
+/// other processes. When the current process is not root, the child gets a
+/// user namespace of its own too, in which it may mount.
+#[deriving(Clone, Eq)]
+pub struct FsIsolation {
+    /// Whether the child gets an empty tmpfs of its own mounted on `/tmp`,
+    /// which hides the files of the other processes there, and which
+    /// disappears along with the child and its descendants.
+    private_tmp: bool,
+
+    /// Directories which are read-only for the child, along with everything
+    /// below them.
+    read_only: ~[Path],
+}
+
+impl FsIsolation {
+    /// Creates a description which gives the child a mount namespace of its
+    /// own without changing anything in it.
+    pub fn new() -> FsIsolation {
+        FsIsolation { private_tmp: false, read_only: ~[] }
+    }
 }
 
 /// Describes what to do with a standard io stream for a child process.
@@ -78,11 +654,60 @@ pub enum StdioContainer {
     /// second is whether it is writable. These properties are from the view of
     /// the *child* process, not the parent process.
     CreatePipe(bool /* readable */, bool /* writable */),
+
+    /// Connects the stream to the slave side of a new pseudo-terminal, which
+    /// becomes the controlling terminal of the child in a session of its own.
+    /// All of the streams given this share the same terminal, and its master
+    /// side is returned for the first of them only, as a stream which can be
+    /// both read and written.
+    ///
+    /// This is only supported on unix, and only by native tasks.
+    CreatePty,
+}
+
+/// Describes what dropping a `Process` does to the child if it has not been
+/// waited for yet.
+#[deriving(Clone, Eq)]
+pub enum DropPolicy {
+    /// The child is waited for, which blocks until it exits.
+    WaitOnDrop,
+
+    /// The child is sent `MustDieSignal` and then waited for, so that it
+    /// does not outlive the `Process`.
+    KillOnDrop,
+
+    /// The child is left running and is handed over to the reaper of the
+    /// runtime, which collects its exit status once it exits so that it
+    /// does not linger as a zombie. The runtime does not exit until then.
+    DetachOnDrop,
+}
+
+/// The resources a child used over its lifetime, as reported by the system
+/// once it has exited.
+#[deriving(Clone, Eq)]
+pub struct ResourceUsage {
+    /// The CPU time spent running the code of the child, in microseconds.
+    user_time: u64,
+
+    /// The CPU time the system spent on behalf of the child, in
+    /// microseconds.
+    system_time: u64,
+
+    /// The largest resident set size of the child, in bytes. On Windows this
+    /// is its peak working set.
+    max_rss: u64,
+
+    /// The page faults which were serviced without any I/O. Windows doesn't
+    /// tell these apart, and counts all page faults as minor faults.
+    minor_faults: u64,
+
+    /// The page faults which required I/O.
+    major_faults: u64,
 }
 
 /// Describes the result of a process after it has terminated.
 /// Note that Windows have no signals, so the result is usually ExitStatus.
-#[deriving(Eq)]
+#[deriving(Clone, Eq)]
 pub enum ProcessExit {
     /// Normal termination with an exit status.
     ExitStatus(int),
@@ -113,6 +738,32 @@ impl ProcessExit {
     pub fn matches_exit_status(&self, wanted: int) -> bool {
         *self == ExitStatus(wanted)
     }
+
+    /// Did the process exit by itself rather than being killed by a signal?
+    pub fn exited(&self) -> bool {
+        match *self {
+            ExitStatus(..) => true,
+            ExitSignal(..) => false,
+        }
+    }
+
+    /// Returns the exit status of the process, or None if it was killed by a
+    /// signal.
+    pub fn code(&self) -> Option<int> {
+        match *self {
+            ExitStatus(code) => Some(code),
+            ExitSignal(..) => None,
+        }
+    }
+
+    /// Returns the signal the process was killed by, or None if it exited by
+    /// itself.
+    pub fn signal(&self) -> Option<int> {
+        match *self {
+            ExitStatus(..) => None,
+            ExitSignal(signum) => Some(signum),
+        }
+    }
 }
 
 impl Process {
@@ -123,17 +774,51 @@ impl Process {
         LocalIo::maybe_raise(|io| {
             io.spawn(config.take_unwrap()).map(|(p, io)| {
                 Process {
-                    handle: p,
+                    handle: Some(p),
                     io: io.move_iter().map(|p| {
                         p.map(|p| io::PipeStream::new(p))
-                    }).collect()
+                    }).collect(),
+                    drop_policy: WaitOnDrop,
+                    forgotten: false,
+                }
+            })
+        })
+    }
+
+    /// Takes over the management of the child `pid`, which was spawned by a
+    /// previous image of this process before it exec'd, or by a process
+    /// which has handed it over with `forget`. The adopted process has no
+    /// pipes to the child.
+    ///
+    /// On unix `pid` must be a child of this process which has not been
+    /// waited for yet. Adopting is not supported by libuv, only by native
+    /// tasks.
+    ///
+    /// If the child cannot be adopted, then the `io_error` condition is
+    /// raised and None is returned.
+    pub fn adopt(pid: libc::pid_t) -> Option<Process> {
+        LocalIo::maybe_raise(|io| {
+            io.adopt_process(pid).map(|p| {
+                Process {
+                    handle: Some(p),
+                    io: ~[None, None, None],
+                    drop_policy: WaitOnDrop,
+                    forgotten: false,
                 }
             })
         })
     }
 
     /// Returns the process id of this child process
-    pub fn id(&self) -> libc::pid_t { self.handle.id() }
+    pub fn id(&self) -> libc::pid_t { self.handle.get_ref().id() }
+
+    /// Returns a pidfd referring to this child process, if one could be
+    /// opened. This is only supported by native tasks on Linux 5.3 and
+    /// later.
+    ///
+    /// The descriptor becomes readable once the child exits, and stays owned
+    /// by this `Process`.
+    pub fn pidfd(&self) -> Option<libc::c_int> { self.handle.get_ref().pidfd() }
 
     /// Sends the specified signal to the child process, returning whether the
     /// signal could be delivered or not.
@@ -141,9 +826,14 @@ impl Process {
     /// Note that this is purely a wrapper around libuv's `uv_process_kill`
     /// function.
     ///
+    /// Windows has no signals: `PleaseExitSignal` and `MustDieSignal`
+    /// terminate the child, and in native tasks SIGINT (2) and SIGBREAK (21)
+    /// send it a Ctrl+Break event, which only reaches a child spawned in a
+    /// new process group. Other signals are not supported there.
+    ///
     /// If the signal delivery fails, then the `io_error` condition is raised on
     pub fn signal(&mut self, signal: int) {
-        match self.handle.kill(signal) {
+        match self.handle.get_mut_ref().kill(signal) {
             Ok(()) => {}
             Err(err) => {
                 io_error::cond.raise(err)
@@ -151,10 +841,91 @@ impl Process {
         }
     }
 
+    /// Stops the child until it is resumed, with SIGSTOP on unix and by
+    /// suspending all of its threads on Windows, which libuv does not
+    /// support.
+    ///
+    /// If the child cannot be stopped, then the `io_error` condition is
+    /// raised.
+    pub fn pause(&mut self) {
+        match self.handle.get_mut_ref().pause() {
+            Ok(()) => {}
+            Err(err) => io_error::cond.raise(err),
+        }
+    }
+
+    /// Continues the child after it was stopped with `pause`, with SIGCONT
+    /// on unix and by resuming all of its threads on Windows.
+    ///
+    /// If the child cannot be continued, then the `io_error` condition is
+    /// raised.
+    pub fn resume(&mut self) {
+        match self.handle.get_mut_ref().resume() {
+            Ok(()) => {}
+            Err(err) => io_error::cond.raise(err),
+        }
+    }
+
     /// Wait for the child to exit completely, returning the status that it
     /// exited with. This function will continue to have the same return value
     /// after it has been called at least once.
-    pub fn wait(&mut self) -> ProcessExit { self.handle.wait() }
+    pub fn wait(&mut self) -> ProcessExit { self.handle.get_mut_ref().wait() }
+
+    /// Returns the status the child exited with if it has exited, without
+    /// waiting for it otherwise. Once this has returned the status, it keeps
+    /// returning it, as does `wait`.
+    pub fn try_wait(&mut self) -> Option<ProcessExit> {
+        self.handle.get_mut_ref().try_wait()
+    }
+
+    /// Waits up to `msecs` milliseconds for the child to exit, returning the
+    /// status it exited with, or None if it is still running by then. The
+    /// task blocks on the exit of the child and a timer rather than polling,
+    /// except in native tasks on platforms which cannot be notified of it,
+    /// such as Linux before 5.3.
+    pub fn wait_timeout(&mut self, msecs: u64) -> Option<ProcessExit> {
+        self.handle.get_mut_ref().wait_timeout(msecs)
+    }
+
+    /// Returns the resources the child used, once it has been waited for.
+    ///
+    /// On unix these are collected with `wait4` and include the children the
+    /// child waited for itself. On Windows the CPU times come from
+    /// `GetProcessTimes` and the memory use from `GetProcessMemoryInfo`. The
+    /// usage is only known to native tasks, and None is returned if the
+    /// child is still running or if its usage is not known.
+    pub fn rusage(&self) -> Option<ResourceUsage> { self.handle.get_ref().rusage() }
+
+    /// Returns whether the child dumped core when it was killed by a signal,
+    /// once it has been waited for.
+    ///
+    /// This is only known to native tasks on unix, and false is returned
+    /// otherwise.
+    pub fn core_dumped(&self) -> bool { self.handle.get_ref().core_dumped() }
+
+    /// Gives up the management of the child without waiting for it, closing
+    /// the pipes to it and returning its id. The child is left running, and
+    /// lingers as a zombie once it exits until it is adopted and waited for.
+    pub fn forget(mut self) -> libc::pid_t {
+        self.drop_policy = DetachOnDrop;
+        self.forgotten = true;
+        self.id()
+    }
+
+    /// Gives up the management of the child without waiting for it, closing
+    /// the pipes to it. The child is left running and handed over to the
+    /// reaper of the runtime, which calls `f` with its exit status in a task
+    /// of its own once it has exited. The runtime does not exit until then.
+    pub fn on_exit(mut self, f: proc(ProcessExit)) {
+        self.io.clear();
+        reaper::adopt(self.handle.take_unwrap(), f);
+    }
+
+    /// Sets what dropping this `Process` does to the child if it has not
+    /// been waited for yet, which is to wait for it by default.
+    pub fn set_drop_policy(&mut self, policy: DropPolicy) {
+        self.drop_policy = policy;
+    }
 }
 
 impl Drop for Process {
@@ -168,7 +939,25 @@ impl Drop for Process {
             }
         }
 
-        self.wait();
+        // A child handed over to the reaper is no longer ours to wait for.
+        if self.handle.is_none() {
+            return;
+        }
+
+        match self.drop_policy {
+            WaitOnDrop => { self.wait(); }
+            KillOnDrop => {
+                if self.try_wait().is_none() {
+                    let _ = self.handle.get_mut_ref().kill(MustDieSignal);
+                }
+                self.wait();
+            }
+            DetachOnDrop => {
+                if !self.forgotten && self.try_wait().is_none() {
+                    reaper::adopt(self.handle.take_unwrap(), proc(_status) {});
+                }
+            }
+        }
     }
 }
 
@@ -185,9 +974,8 @@ mod tests {
         let args = ProcessConfig {
             program: "/bin/sh",
             args: &[~"-c", ~"true"],
-            env: None,
-            cwd: None,
             io: io,
+            .. ProcessConfig::new()
         };
         let p = Process::new(args);
         assert!(p.is_some());
@@ -202,9 +990,8 @@ mod tests {
         let args = ProcessConfig {
             program: "if-this-is-a-binary-then-the-world-has-ended",
             args: &[],
-            env: None,
-            cwd: None,
             io: io,
+            .. ProcessConfig::new()
         };
         match io::result(|| Process::new(args)) {
             Ok(..) => fail!(),
@@ -219,9 +1006,8 @@ mod tests {
         let args = ProcessConfig {
             program: "/bin/sh",
             args: &[~"-c", ~"exit 1"],
-            env: None,
-            cwd: None,
             io: io,
+            .. ProcessConfig::new()
         };
         let p = Process::new(args);
         assert!(p.is_some());
@@ -235,9 +1021,8 @@ mod tests {
         let args = ProcessConfig {
             program: "/bin/sh",
             args: &[~"-c", ~"kill -1 $$"],
-            env: None,
-            cwd: None,
             io: io,
+            .. ProcessConfig::new()
         };
         let p = Process::new(args);
         assert!(p.is_some());
@@ -248,6 +1033,33 @@ mod tests {
         }
     })
 
+    #[test]
+    fn exit_accessors() {
+        use io::process::{ExitStatus, ExitSignal};
+
+        assert!(ExitStatus(3).exited());
+        assert_eq!(ExitStatus(3).code(), Some(3));
+        assert_eq!(ExitStatus(3).signal(), None);
+
+        assert!(!ExitSignal(9).exited());
+        assert_eq!(ExitSignal(9).code(), None);
+        assert_eq!(ExitSignal(9).signal(), Some(9));
+    }
+
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn no_core_dump_reported() {
+        let args = ProcessConfig {
+            program: "/bin/sh",
+            args: &[~"-c", ~"kill -9 $$"],
+            io: &[],
+            .. ProcessConfig::new()
+        };
+        let mut p = Process::new(args).unwrap();
+        assert!(!p.core_dumped());
+        assert_eq!(p.wait(), process::ExitSignal(9));
+        assert!(!p.core_dumped());
+    })
+
     pub fn read_all(input: &mut Reader) -> ~str {
         let mut ret = ~"";
         let mut buf = [0, ..1024];
@@ -278,9 +1090,8 @@ mod tests {
         let args = ProcessConfig {
             program: "/bin/sh",
             args: &[~"-c", ~"echo foobar"],
-            env: None,
-            cwd: None,
             io: io,
+            .. ProcessConfig::new()
         };
         assert_eq!(run_output(args), ~"foobar\n");
     })
@@ -293,9 +1104,9 @@ mod tests {
         let args = ProcessConfig {
             program: "/bin/sh",
             args: &[~"-c", ~"pwd"],
-            env: None,
             cwd: cwd,
             io: io,
+            .. ProcessConfig::new()
         };
         assert_eq!(run_output(args), ~"/\n");
     })
@@ -308,9 +1119,8 @@ mod tests {
         let args = ProcessConfig {
             program: "/bin/sh",
             args: &[~"-c", ~"read line; echo $line"],
-            env: None,
-            cwd: None,
             io: io,
+            .. ProcessConfig::new()
         };
         let mut p = Process::new(args).expect("didn't create a proces?!");
         p.io[0].get_mut_ref().write("foobar".as_bytes());
@@ -320,4 +1130,24 @@ mod tests {
         assert_eq!(out, ~"foobar\n");
     })
 
+    // FIXME(#10380)
+    #[cfg(unix, not(target_os="android"))]
+    iotest!(fn extra_fds_work() {
+        let io = ~[Ignored, CreatePipe(false, true), Ignored,
+                   CreatePipe(false, true)];
+        let args = ProcessConfig {
+            program: "/bin/sh",
+            args: &[~"-c", ~"echo foo; echo bar >&3"],
+            io: io,
+            .. ProcessConfig::new()
+        };
+        let mut p = Process::new(args).expect("didn't create a proces?!");
+        assert_eq!(p.io.len(), 4);
+        let out = read_all(p.io[1].get_mut_ref() as &mut Reader);
+        let extra = read_all(p.io[3].get_mut_ref() as &mut Reader);
+        assert!(p.wait().success());
+        assert_eq!(out, ~"foo\n");
+        assert_eq!(extra, ~"bar\n");
+    })
+
 }
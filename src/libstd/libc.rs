@@ -1601,6 +1601,23 @@ pub mod consts {
             pub static STARTF_USESIZE : DWORD = 0x00000002;
             pub static STARTF_USESTDHANDLES : DWORD = 0x00000100;
 
+            pub static DEBUG_PROCESS : DWORD = 0x00000001;
+            pub static DEBUG_ONLY_THIS_PROCESS : DWORD = 0x00000002;
+            pub static CREATE_SUSPENDED : DWORD = 0x00000004;
+            pub static DETACHED_PROCESS : DWORD = 0x00000008;
+            pub static CREATE_NEW_CONSOLE : DWORD = 0x00000010;
+            pub static CREATE_NEW_PROCESS_GROUP : DWORD = 0x00000200;
+            pub static CREATE_UNICODE_ENVIRONMENT : DWORD = 0x00000400;
+            pub static CREATE_BREAKAWAY_FROM_JOB : DWORD = 0x01000000;
+            pub static CREATE_DEFAULT_ERROR_MODE : DWORD = 0x04000000;
+            pub static CREATE_NO_WINDOW : DWORD = 0x08000000;
+
+            pub static IDLE_PRIORITY_CLASS : DWORD = 0x00000040;
+            pub static BELOW_NORMAL_PRIORITY_CLASS : DWORD = 0x00004000;
+            pub static NORMAL_PRIORITY_CLASS : DWORD = 0x00000020;
+            pub static ABOVE_NORMAL_PRIORITY_CLASS : DWORD = 0x00008000;
+            pub static HIGH_PRIORITY_CLASS : DWORD = 0x00000080;
+
             pub static WAIT_ABANDONED : DWORD = 0x00000080;
             pub static WAIT_OBJECT_0 : DWORD = 0x00000000;
             pub static WAIT_TIMEOUT : DWORD = 0x00000102;
@@ -3990,6 +4007,10 @@ pub mod funcs {
                                            -> DWORD;
                 pub fn TerminateProcess(hProcess: HANDLE, uExitCode: c_uint)
                                         -> BOOL;
+                pub fn ResumeThread(hThread: HANDLE) -> DWORD;
+                pub fn SetProcessAffinityMask(hProcess: HANDLE,
+                                              dwProcessAffinityMask: SIZE_T)
+                                              -> BOOL;
                 pub fn GetExitCodeProcess(hProcess: HANDLE,
                                           lpExitCode: LPDWORD)
                                           -> BOOL;
@@ -0,0 +1,170 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reaping of detached children
+//!
+//! On unix a child which has exited lingers in the process table as a zombie
+//! until its parent collects the exit status. A child whose `Process` is given
+//! up without waiting for it, be it by dropping it or by detaching it, is
+//! moved into a reaper task, which checks all of the children it was given at
+//! growing intervals of up to a tenth of a second, and the child's id is kept
+//! in a global registry until it has exited.
+//!
+//! A child can only be waited for by tasks on the event loop it was spawned
+//! from, so there is a reaper task for each event loop children are adopted
+//! from, rather than a task for each child. A reaper task exits once it has
+//! no children left.
+//!
+//! Note that the reaper tasks are normal tasks, so the runtime will not exit
+//! until all of the detached children it is waiting on have terminated.
+
+use cast;
+use cmp;
+use io::process::ProcessExit;
+use io::timer;
+use libc::pid_t;
+use prelude::*;
+use rt::rtio::{LocalIo, RtioProcess};
+use task;
+use unstable::mutex::{Once, ONCE_INIT};
+use unstable::sync::Exclusive;
+use util;
+
+/// A child adopted by a reaper task, along with what to call once it exits.
+struct Orphan {
+    process: ~RtioProcess,
+    on_exit: proc(ProcessExit),
+}
+
+/// The reaper task of an event loop.
+struct Reaper {
+    /// The event loop the task waits on children of.
+    io: uint,
+
+    /// The children adopted since the task last looked.
+    incoming: ~[Orphan],
+}
+
+struct Registry {
+    pending: ~[pid_t],
+    reapers: ~[Reaper],
+}
+
+static mut REGISTRY: *mut Exclusive<Registry> = 0 as *mut Exclusive<Registry>;
+
+fn with_registry<T>(f: |&mut Registry| -> T) -> T {
+    static mut INIT: Once = ONCE_INIT;
+
+    unsafe {
+        INIT.doit(|| {
+            let registry: ~Exclusive<Registry> = ~Exclusive::new(Registry {
+                pending: ~[],
+                reapers: ~[],
+            });
+            REGISTRY = cast::transmute(registry);
+        });
+        (*REGISTRY).with(f)
+    }
+}
+
+/// Identifies the event loop of the current task, as `local_id` does in
+/// librustuv.
+fn local_io_id() -> uint {
+    match LocalIo::borrow() {
+        Some(mut io) => unsafe {
+            let (_vtable, ptr): (uint, uint) = cast::transmute(io.get());
+            ptr
+        },
+        None => 0,
+    }
+}
+
+/// Takes ownership of `p`, collecting its exit status in the background once
+/// it terminates, and calling `f` with it then. `f` runs in a task of its own.
+pub fn adopt(p: ~RtioProcess, f: proc(ProcessExit)) {
+    let pid = p.id();
+    let io = local_io_id();
+    let mut orphan = Some(Orphan { process: p, on_exit: f });
+    let start = with_registry(|registry| {
+        registry.pending.push(pid);
+        match registry.reapers.mut_iter().find(|r| r.io == io) {
+            Some(reaper) => {
+                reaper.incoming.push(orphan.take_unwrap());
+                return false;
+            }
+            None => {}
+        }
+        registry.reapers.push(Reaper { io: io, incoming: ~[orphan.take_unwrap()] });
+        true
+    });
+    if start {
+        let mut builder = task::task();
+        builder.name("<process reaper>");
+        do builder.spawn {
+            reap(io)
+        }
+    }
+}
+
+/// The body of the reaper task of the event loop `io`.
+fn reap(io: uint) {
+    let mut orphans: ~[Orphan] = ~[];
+    let mut delay = 1;
+    loop {
+        let incoming = with_registry(|registry| {
+            let i = registry.reapers.iter().position(|r| r.io == io).unwrap();
+            let incoming = util::replace(&mut registry.reapers[i].incoming, ~[]);
+            if incoming.is_empty() && orphans.is_empty() {
+                registry.reapers.swap_remove(i);
+                None
+            } else {
+                Some(incoming)
+            }
+        });
+        match incoming {
+            Some(incoming) => {
+                if !incoming.is_empty() {
+                    delay = 1;
+                }
+                orphans.push_all_move(incoming);
+            }
+            None => return,
+        }
+
+        let mut running = ~[];
+        for orphan in orphans.move_iter() {
+            let Orphan { process: p, on_exit } = orphan;
+            let mut p = p;
+            match p.try_wait() {
+                Some(status) => {
+                    let pid = p.id();
+                    debug!("reaped detached child {}: {}", pid, status);
+                    with_registry(|registry| registry.pending.retain(|&other| other != pid));
+                    // A callback which fails must not take the other children
+                    // down with it.
+                    do task::spawn {
+                        on_exit(status)
+                    }
+                }
+                None => running.push(Orphan { process: p, on_exit: on_exit }),
+            }
+        }
+        orphans = running;
+        if !orphans.is_empty() {
+            timer::sleep(delay);
+            delay = cmp::min(delay * 2, 100);
+        }
+    }
+}
+
+/// Returns the ids of all adopted children which have not yet been reaped.
+pub fn pending() -> ~[pid_t] {
+    with_registry(|registry| registry.pending.clone())
+}
@@ -24,7 +24,7 @@ use ai = io::net::addrinfo;
 use io;
 use io::IoError;
 use io::net::ip::{IpAddr, SocketAddr};
-use io::process::{ProcessConfig, ProcessExit};
+use io::process::{ProcessConfig, ProcessExit, ResourceUsage};
 use io::signal::Signum;
 use io::{FileMode, FileAccess, FileStat, FilePermission};
 use io::{SeekStyle};
@@ -189,6 +189,7 @@ pub trait IoFactory {
     fn timer_init(&mut self) -> Result<~RtioTimer, IoError>;
     fn spawn(&mut self, config: ProcessConfig)
             -> Result<(~RtioProcess, ~[Option<~RtioPipe>]), IoError>;
+    fn adopt_process(&mut self, pid: libc::pid_t) -> Result<~RtioProcess, IoError>;
     fn pipe_open(&mut self, fd: c_int) -> Result<~RtioPipe, IoError>;
     fn tty_open(&mut self, fd: c_int, readable: bool)
             -> Result<~RtioTTY, IoError>;
@@ -257,8 +258,15 @@ pub trait RtioFileStream {
 
 pub trait RtioProcess {
     fn id(&self) -> libc::pid_t;
+    fn pidfd(&self) -> Option<c_int> { None }
     fn kill(&mut self, signal: int) -> Result<(), IoError>;
     fn wait(&mut self) -> ProcessExit;
+    fn try_wait(&mut self) -> Option<ProcessExit>;
+    fn wait_timeout(&mut self, msecs: u64) -> Option<ProcessExit>;
+    fn rusage(&self) -> Option<ResourceUsage> { None }
+    fn core_dumped(&self) -> bool { false }
+    fn pause(&mut self) -> Result<(), IoError>;
+    fn resume(&mut self) -> Result<(), IoError>;
 }
 
 pub trait RtioPipe {
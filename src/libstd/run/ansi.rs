@@ -0,0 +1,186 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Removal of terminal control garbage from captured output
+
+use cmp;
+use prelude::*;
+use vec;
+
+static ESC: u8 = 0x1b;
+static BEL: u8 = 0x07;
+
+enum State {
+    Text,
+    Escape,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+/// A `Reader` which filters terminal escape sequences and carriage-return
+/// progress updates out of the stream it wraps.
+///
+/// When stripping escapes, control sequences (`ESC [ ... final`), operating
+/// system commands (`ESC ] ... BEL`) and two-byte `ESC x` sequences are
+/// dropped entirely.
+///
+/// When collapsing carriage returns, a `\r` which is not part of a `\r\n` pair
+/// discards the part of the line which preceded it, leaving only the last
+/// update of a progress line in the output. `\r\n` is normalized to `\n`.
+/// Output is only produced a line at a time in this mode.
+pub struct AnsiFilter<R> {
+    priv inner: R,
+    priv strip_escapes: bool,
+    priv collapse_cr: bool,
+    priv state: State,
+    priv pending_cr: bool,
+    priv line: ~[u8],
+    priv out: ~[u8],
+    priv pos: uint,
+    priv eof: bool,
+}
+
+impl<R: Reader> AnsiFilter<R> {
+    /// Creates a new filter over `inner`.
+    pub fn new(inner: R, strip_escapes: bool, collapse_cr: bool) -> AnsiFilter<R> {
+        AnsiFilter {
+            inner: inner,
+            strip_escapes: strip_escapes,
+            collapse_cr: collapse_cr,
+            state: Text,
+            pending_cr: false,
+            line: ~[],
+            out: ~[],
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Unwraps this filter, returning the underlying reader.
+    ///
+    /// Note that any filtered data which has not been read yet is lost.
+    pub fn unwrap(self) -> R { self.inner }
+
+    fn push(&mut self, b: u8) {
+        match self.state {
+            Text if b == ESC && self.strip_escapes => { self.state = Escape; }
+            Text => self.emit(b),
+            Escape if b == '[' as u8 => { self.state = Csi; }
+            Escape if b == ']' as u8 => { self.state = Osc; }
+            Escape => { self.state = Text; }
+            Csi if b >= 0x40 && b <= 0x7e => { self.state = Text; }
+            Csi => {}
+            Osc if b == BEL => { self.state = Text; }
+            Osc if b == ESC => { self.state = OscEscape; }
+            Osc => {}
+            OscEscape => { self.state = Text; }
+        }
+    }
+
+    fn emit(&mut self, b: u8) {
+        if !self.collapse_cr {
+            self.out.push(b);
+            return
+        }
+        if self.pending_cr {
+            self.pending_cr = false;
+            if b != '\n' as u8 {
+                self.line.truncate(0);
+            }
+        }
+        if b == '\r' as u8 {
+            self.pending_cr = true;
+        } else if b == '\n' as u8 {
+            self.out.push_all(self.line.as_slice());
+            self.out.push(b);
+            self.line.truncate(0);
+        } else {
+            self.line.push(b);
+        }
+    }
+
+    fn fill(&mut self) {
+        let mut buf = [0u8, ..4096];
+        match self.inner.read(buf) {
+            Some(n) => {
+                for &b in buf.slice_to(n).iter() {
+                    self.push(b);
+                }
+            }
+            None => {
+                // Whatever was last written to the line is what a terminal
+                // would be left showing.
+                self.eof = true;
+                self.out.push_all(self.line.as_slice());
+                self.line.truncate(0);
+            }
+        }
+    }
+}
+
+impl<R: Reader> Reader for AnsiFilter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
+        while self.pos == self.out.len() {
+            if self.eof { return None }
+            self.out.truncate(0);
+            self.pos = 0;
+            self.fill();
+        }
+        let n = cmp::min(buf.len(), self.out.len() - self.pos);
+        vec::bytes::copy_memory(buf, self.out.slice(self.pos, self.pos + n));
+        self.pos += n;
+        Some(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use io::mem::MemReader;
+    use prelude::*;
+    use super::AnsiFilter;
+
+    fn strip(bytes: &[u8]) -> ~[u8] {
+        AnsiFilter::new(MemReader::new(bytes.to_owned()), true, false).read_to_end()
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip(bytes!("plain")), bytes!("plain").to_owned());
+        assert_eq!(strip(bytes!("\x1b[1;31merror\x1b[0m: oops")),
+                   bytes!("error: oops").to_owned());
+        assert_eq!(strip(bytes!("a\x1b7b\x1b8c")), bytes!("abc").to_owned());
+        assert_eq!(strip(bytes!("\x1b]0;title\x07text")), bytes!("text").to_owned());
+        assert_eq!(strip(bytes!("\x1b]0;title\x1b\\text")), bytes!("text").to_owned());
+        assert_eq!(strip(bytes!("truncated\x1b[1;3")), bytes!("truncated").to_owned());
+    }
+
+    #[test]
+    fn test_collapse_cr() {
+        let input = bytes!("start\n10%\r50%\r100%\ndone\r\nlast\r").to_owned();
+        let mut filter = AnsiFilter::new(MemReader::new(input), false, true);
+        assert_eq!(filter.read_to_end(), bytes!("start\n100%\ndone\nlast").to_owned());
+    }
+
+    #[test]
+    fn test_small_reads() {
+        let input = bytes!("\x1b[32mok\x1b[0m\r\n").to_owned();
+        let mut filter = AnsiFilter::new(MemReader::new(input), true, true);
+        let mut buf = [0u8];
+        let mut ret = ~[];
+        loop {
+            match filter.read(buf) {
+                Some(n) => ret.push_all(buf.slice_to(n)),
+                None => break,
+            }
+        }
+        assert_eq!(ret, bytes!("ok\n").to_owned());
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Answering password prompts of an interactive child
+//!
+//! Programs such as `sudo` and `ssh` ask for secrets on their terminal and
+//! leave the prompt on an unterminated line while they wait for the answer.
+//! An `AskPassReader` watches the output of such a child for prompts and
+//! writes the answer given by an `AskPass` callback to its input, so that the
+//! secret never has to be passed on the command line. The child turns off
+//! the echo of its terminal itself while it reads the secret, so this is
+//! meant for the master side of a pseudo-terminal.
+
+use prelude::*;
+use str;
+
+use super::progress::match_line;
+
+/// Provides the secrets asked for by a child.
+pub trait AskPass {
+    /// Returns the secret asked for with `prompt`, or None to stop answering
+    /// prompts altogether.
+    fn ask(&mut self, prompt: &str) -> Option<~str>;
+}
+
+/// Returns the patterns recognizing the prompts of the usual programs, such
+/// as `Password:`, `[sudo] password for user:` and
+/// `Enter passphrase for key '/home/user/.ssh/id_rsa':`.
+pub fn default_password_prompts() -> ~[~str] {
+    ~[~"*assword*:", ~"*assphrase*:"]
+}
+
+/// A `Reader` which passes the output of a child through unchanged, answering
+/// the prompts it finds in it by writing to the input of the child.
+///
+/// Prompts are recognized with the patterns of `run::ProgressWatcher`, which
+/// are matched against the unterminated line at the end of the output so far
+/// without its trailing whitespace.
+pub struct AskPassReader<R, W> {
+    priv inner: R,
+    priv input: W,
+    priv askpass: Option<~AskPass>,
+    priv prompts: ~[~str],
+    priv line: ~[u8],
+}
+
+impl<R: Reader, W: Writer> AskPassReader<R, W> {
+    /// Creates a new reader which answers the prompts recognized by
+    /// `prompts` in the output `inner` by writing to `input`.
+    pub fn new(inner: R, input: W, askpass: ~AskPass,
+               prompts: ~[~str]) -> AskPassReader<R, W> {
+        AskPassReader {
+            inner: inner,
+            input: input,
+            askpass: Some(askpass),
+            prompts: prompts,
+            line: ~[],
+        }
+    }
+
+    /// Returns the wrapped output and input of the child.
+    pub fn unwrap(self) -> (R, W) {
+        let AskPassReader { inner, input, .. } = self;
+        (inner, input)
+    }
+
+    fn check_prompt(&mut self) {
+        let prompt = match str::from_utf8(self.line) {
+            Some(line) => line.trim_right().to_owned(),
+            None => return,
+        };
+        if !self.prompts.iter().any(|p| match_line(*p, prompt).is_some()) {
+            return
+        }
+        self.line.truncate(0);
+        let secret = match self.askpass {
+            Some(ref mut askpass) => askpass.ask(prompt),
+            None => return,
+        };
+        match secret {
+            Some(secret) => {
+                self.input.write(secret.as_bytes());
+                self.input.write(['\n' as u8]);
+                self.input.flush();
+            }
+            None => self.askpass = None,
+        }
+    }
+}
+
+impl<R: Reader, W: Writer> Reader for AskPassReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
+        let ret = self.inner.read(buf);
+        for &n in ret.iter() {
+            for &b in buf.slice_to(n).iter() {
+                if b == '\n' as u8 || b == '\r' as u8 {
+                    self.line.truncate(0);
+                } else {
+                    self.line.push(b);
+                }
+            }
+            if !self.line.is_empty() {
+                self.check_prompt();
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::mem::{MemReader, MemWriter};
+    use super::{AskPass, AskPassReader, default_password_prompts};
+
+    struct Secret;
+
+    impl AskPass for Secret {
+        fn ask(&mut self, prompt: &str) -> Option<~str> {
+            assert_eq!(prompt, "[sudo] password for me:");
+            Some(~"hunter2")
+        }
+    }
+
+    #[test]
+    fn test_askpass_reader() {
+        // The child waits for the answer, so a prompt always ends the output
+        // read so far.
+        let output = bytes!("Connecting...\n[sudo] password for me: ").to_owned();
+        let mut reader = AskPassReader::new(MemReader::new(output.clone()), MemWriter::new(),
+                                            ~Secret as ~AskPass,
+                                            default_password_prompts());
+        assert_eq!(reader.read_to_end(), output);
+        let (_, input) = reader.unwrap();
+        assert_eq!(input.unwrap(), bytes!("hunter2\n").to_owned());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Feeding the same input to many children
+
+use comm;
+use io::Writer;
+use io;
+use prelude::*;
+
+use super::Process;
+
+/// Describes what a `BroadcastWriter` does about a child which reads its
+/// input more slowly than the others.
+#[deriving(Clone, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Wait until every child has been given the data before returning from
+    /// a write, so that the slowest child paces the writer.
+    WaitForSlowest,
+
+    /// Queue the data for each child, and stop feeding a child which would
+    /// have more than the given number of bytes queued. The stdin of that child
+    /// is closed once it has been given what was queued for it.
+    DropSlowerThan(uint),
+}
+
+/// A consumer of the broadcast, whose input is written by a task of its own.
+struct Consumer {
+    chan: Chan<~[u8]>,
+    acks: Port<uint>,
+    queued: uint,
+}
+
+/**
+ * A `Writer` duplicating the data written to it into the input of several
+ * children, for feeding the same stream to several analyzers or compressors
+ * at once.
+ *
+ * A child whose input can no longer be written, for example because it has
+ * exited, is dropped from the broadcast without disturbing the others.
+ * Dropping the writer closes the input of every child once it has been
+ * given all of the data.
+ */
+pub struct BroadcastWriter {
+    priv consumers: ~[Option<Consumer>],
+    priv policy: SlowConsumerPolicy,
+}
+
+impl BroadcastWriter {
+    /// Creates a new writer without any consumers.
+    pub fn new(policy: SlowConsumerPolicy) -> BroadcastWriter {
+        BroadcastWriter { consumers: ~[], policy: policy }
+    }
+
+    /// Adds `writer` to the consumers, returning its index.
+    pub fn add<W: Writer + Send>(&mut self, writer: W) -> uint {
+        let (port, chan) = Chan::new();
+        let (acks, ack_chan) = Chan::new();
+        do spawn {
+            let mut writer = writer;
+            loop {
+                let data: ~[u8] = match port.recv_opt() {
+                    Some(data) => data,
+                    None => break,
+                };
+                match io::result(|| { writer.write(data); writer.flush(); }) {
+                    Ok(()) => {}
+                    Err(..) => break,
+                }
+                if !ack_chan.try_send(data.len()) {
+                    break
+                }
+            }
+        }
+        self.consumers.push(Some(Consumer { chan: chan, acks: acks, queued: 0 }));
+        self.consumers.len() - 1
+    }
+
+    /// Takes the stdin of `process` and adds it to the consumers, returning
+    /// its index.
+    ///
+    /// Fails if there is no stdin available (it's already been removed by
+    /// take_input)
+    pub fn add_input(&mut self, process: &mut Process) -> uint {
+        self.add(process.take_input().expect("the stdin of the process was taken"))
+    }
+
+    /// Returns whether the consumer with the given index is still fed.
+    pub fn is_fed(&self, index: uint) -> bool {
+        self.consumers[index].is_some()
+    }
+
+    /// Receives the acknowledgements of the consumers, dropping those which
+    /// are gone, and waiting for all of the data to be written if `block`.
+    fn drain_acks(&mut self, block: bool) {
+        for slot in self.consumers.mut_iter() {
+            let gone = match *slot {
+                Some(ref mut c) => {
+                    let mut gone = false;
+                    while c.queued > 0 {
+                        let ack = if block {
+                            c.acks.recv_opt()
+                        } else {
+                            match c.acks.try_recv() {
+                                comm::Data(n) => Some(n),
+                                comm::Empty => break,
+                                comm::Disconnected => None,
+                            }
+                        };
+                        match ack {
+                            Some(n) => c.queued -= n,
+                            None => { gone = true; break }
+                        }
+                    }
+                    gone
+                }
+                None => false,
+            };
+            if gone {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl Writer for BroadcastWriter {
+    fn write(&mut self, buf: &[u8]) {
+        if buf.is_empty() {
+            return
+        }
+        self.drain_acks(false);
+        let limit = match self.policy {
+            WaitForSlowest => None,
+            DropSlowerThan(n) => Some(n),
+        };
+        for slot in self.consumers.mut_iter() {
+            let keep = match *slot {
+                Some(ref mut c) => {
+                    let slow = match limit {
+                        Some(n) => c.queued > 0 && c.queued + buf.len() > n,
+                        None => false,
+                    };
+                    if !slow && c.chan.try_send(buf.to_owned()) {
+                        c.queued += buf.len();
+                        true
+                    } else {
+                        false
+                    }
+                }
+                None => true,
+            };
+            if !keep {
+                *slot = None;
+            }
+        }
+        if limit.is_none() {
+            self.drain_acks(true);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bounded capture of the output of a child
+
+use io::fs::{File, unlink};
+use io::{Reader, Writer};
+use io;
+use os;
+use prelude::*;
+use rand;
+use vec;
+
+/// Describes how much of each output stream of a child is kept by
+/// `finish_with_output`.
+#[deriving(Clone, Eq)]
+pub enum CaptureMode {
+    /// Keep all of the output.
+    CaptureAll,
+
+    /// Keep only the last given number of bytes of the output, for chatty
+    /// long-running children of which only the tail matters when they fail.
+    CaptureTail(uint),
+
+    /// Keep the first and the last given numbers of bytes of the output, with
+    /// a marker saying how many bytes were elided in between, so that a
+    /// summary of a log shows both its banner and its final error.
+    CaptureHeadTail(uint, uint),
+
+    /// Write the output to a temporary file as it arrives instead of keeping
+    /// it in memory, for children producing more output than is reasonable
+    /// to hold. The file is untouched by `output_encoding`.
+    CaptureToFile,
+}
+
+/// An output stream of a child, as read by `finish_with_output`.
+pub struct Captured {
+    bytes: ~[u8],
+    discarded: uint,
+    digest: Option<u32>,
+    file: Option<CapturedFile>,
+}
+
+/// A temporary file holding the output of a child, which is deleted when it
+/// is dropped unless it is kept with `keep`.
+pub struct CapturedFile {
+    priv path: Option<Path>,
+}
+
+impl CapturedFile {
+    /// Returns the path to the file.
+    pub fn path<'a>(&'a self) -> &'a Path {
+        self.path.get_ref()
+    }
+
+    /// Opens the file for reading the output from its beginning.
+    pub fn open(&self) -> Option<File> {
+        File::open(self.path())
+    }
+
+    /// Keeps the file instead of deleting it, returning its path.
+    pub fn keep(mut self) -> Path {
+        self.path.take_unwrap()
+    }
+}
+
+impl Drop for CapturedFile {
+    fn drop(&mut self) {
+        for path in self.path.iter() {
+            let _guard = io::ignore_io_error();
+            unlink(path);
+        }
+    }
+}
+
+/// Reads all of `reader`, appending the part of it described by `mode` to
+/// `buf`. The digest of the result is left for the caller to fill in.
+pub fn read(mut reader: ~Reader, mode: CaptureMode, mut buf: ~[u8]) -> Captured {
+    let (kept, discarded) = match mode {
+        CaptureAll => {
+            append_to_end(&mut reader, &mut buf);
+            return Captured { bytes: buf, discarded: 0, digest: None, file: None }
+        }
+        CaptureTail(n) => read_tail(reader, n),
+        CaptureHeadTail(head, tail) => read_head_tail(reader, head, tail),
+        CaptureToFile => {
+            let file = read_to_file(reader);
+            return Captured { bytes: buf, discarded: 0, digest: None, file: file }
+        }
+    };
+    buf.push_all(kept);
+    Captured { bytes: buf, discarded: discarded, digest: None, file: None }
+}
+
+/// Appends all of `reader` to `buf`, like `read_to_end` does to a new vector.
+fn append_to_end(reader: &mut ~Reader, buf: &mut ~[u8]) {
+    let mut keep_reading = true;
+    io::io_error::cond.trap(|e| {
+        if e.kind == io::EndOfFile {
+            keep_reading = false;
+        } else {
+            io::io_error::cond.raise(e)
+        }
+    }).inside(|| {
+        while keep_reading {
+            reader.push_bytes(buf, 64 * 1024)
+        }
+    });
+}
+
+/// Copies all of `reader` to a new temporary file. Returns None if the file
+/// could not be created, in which case the output is discarded.
+fn read_to_file(mut reader: ~Reader) -> Option<CapturedFile> {
+    let path = os::tmpdir().join(format!("rust-output-{}", rand::random::<u32>()));
+    let mut file = File::create(&path);
+    let ret = file.as_ref().map(|_| CapturedFile { path: Some(path) });
+    let mut buf = vec::from_elem(64 * 1024, 0u8);
+    loop {
+        match reader.read(buf) {
+            Some(len) => {
+                for file in file.mut_iter() {
+                    file.write(buf.slice_to(len));
+                }
+            }
+            None => break,
+        }
+    }
+    ret
+}
+
+fn read_head_tail(mut reader: ~Reader, head: uint, tail: uint) -> (~[u8], uint) {
+    let mut ret = ~[];
+    while ret.len() < head {
+        let mut buf = vec::from_elem(head - ret.len(), 0u8);
+        match reader.read(buf) {
+            Some(len) => ret.push_all(buf.slice_to(len)),
+            None => return (ret, 0),
+        }
+    }
+    let (rest, discarded) = read_tail(reader, tail);
+    if discarded > 0 {
+        ret.push_all(format!("\n[... {} bytes elided ...]\n", discarded).as_bytes());
+    }
+    ret.push_all(rest);
+    (ret, discarded)
+}
+
+fn read_tail(mut reader: ~Reader, n: uint) -> (~[u8], uint) {
+    let mut buf = vec::from_elem(64 * 1024, 0u8);
+    let mut tail = ~[];
+    let mut discarded = 0;
+    loop {
+        match reader.read(buf) {
+            Some(len) => tail.push_all(buf.slice_to(len)),
+            None => break,
+        }
+        // Only shift the kept bytes once the buffer has doubled, so that
+        // this takes amortized constant time per byte.
+        if tail.len() > 2 * n {
+            discarded += drop_front(&mut tail, n);
+        }
+    }
+    discarded += drop_front(&mut tail, n);
+    (tail, discarded)
+}
+
+/// Drops bytes from the front of `bytes` until at most `n` remain, returning
+/// how many were dropped.
+fn drop_front(bytes: &mut ~[u8], n: uint) -> uint {
+    if bytes.len() <= n {
+        return 0;
+    }
+    let excess = bytes.len() - n;
+    *bytes = bytes.slice_from(excess).to_owned();
+    excess
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::Reader;
+    use io::mem::MemReader;
+    use super::{read, CaptureAll, CaptureTail, CaptureHeadTail, CaptureToFile};
+
+    #[test]
+    fn test_capture_all() {
+        let r = ~MemReader::new(bytes!("hello").to_owned()) as ~Reader;
+        let c = read(r, CaptureAll, ~[]);
+        assert_eq!((c.bytes, c.discarded), (bytes!("hello").to_owned(), 0));
+    }
+
+    #[test]
+    fn test_capture_tail() {
+        let r = ~MemReader::new(bytes!("hello world").to_owned()) as ~Reader;
+        let c = read(r, CaptureTail(5), ~[]);
+        assert_eq!((c.bytes, c.discarded), (bytes!("world").to_owned(), 6));
+
+        let r = ~MemReader::new(bytes!("hi").to_owned()) as ~Reader;
+        let c = read(r, CaptureTail(5), ~[]);
+        assert_eq!((c.bytes, c.discarded), (bytes!("hi").to_owned(), 0));
+    }
+
+    #[test]
+    fn test_capture_appends() {
+        let r = ~MemReader::new(bytes!(" world").to_owned()) as ~Reader;
+        let c = read(r, CaptureAll, bytes!("hello").to_owned());
+        assert_eq!(c.bytes, bytes!("hello world").to_owned());
+
+        let r = ~MemReader::new(bytes!(" world").to_owned()) as ~Reader;
+        let c = read(r, CaptureTail(3), bytes!("hello").to_owned());
+        assert_eq!(c.bytes, bytes!("hellorld").to_owned());
+    }
+
+    #[test]
+    fn test_capture_head_tail() {
+        let r = ~MemReader::new(bytes!("banner, noise, error").to_owned()) as ~Reader;
+        let c = read(r, CaptureHeadTail(6, 5), ~[]);
+        assert_eq!((c.bytes, c.discarded),
+                   (bytes!("banner\n[... 9 bytes elided ...]\nerror").to_owned(), 9));
+
+        let r = ~MemReader::new(bytes!("short").to_owned()) as ~Reader;
+        let c = read(r, CaptureHeadTail(3, 3), ~[]);
+        assert_eq!((c.bytes, c.discarded), (bytes!("short").to_owned(), 0));
+    }
+
+    #[test]
+    fn test_capture_to_file() {
+        let r = ~MemReader::new(bytes!("hello").to_owned()) as ~Reader;
+        let c = read(r, CaptureToFile, ~[]);
+        assert!(c.bytes.is_empty());
+        let file = c.file.unwrap();
+        let path = file.path().clone();
+        let mut reader = file.open().unwrap();
+        assert_eq!(reader.read_to_end(), bytes!("hello").to_owned());
+        drop(reader);
+        drop(file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading the output of a child in chunks of a fixed size
+
+use io::Reader;
+use prelude::*;
+use vec;
+
+/// An iterator over the chunks of a given size of a stream, for binary
+/// protocols such as the frames of raw video written by a child.
+///
+/// Short reads are retried until a chunk is complete, so every chunk has the
+/// given size except possibly the last one, which holds whatever was left at
+/// the end of the stream. No empty chunk is ever returned.
+pub struct Chunks<R> {
+    priv inner: R,
+    priv size: uint,
+    priv done: bool,
+}
+
+impl<R: Reader> Chunks<R> {
+    /// Creates a new iterator over the chunks of `size` bytes of `inner`.
+    ///
+    /// Fails if `size` is 0.
+    pub fn new(inner: R, size: uint) -> Chunks<R> {
+        assert!(size > 0, "chunks must not be empty");
+        Chunks { inner: inner, size: size, done: false }
+    }
+
+    /// Returns the wrapped stream.
+    pub fn unwrap(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Reader> Iterator<~[u8]> for Chunks<R> {
+    fn next(&mut self) -> Option<~[u8]> {
+        if self.done {
+            return None
+        }
+        let mut chunk = vec::from_elem(self.size, 0u8);
+        let mut len = 0;
+        while len < self.size {
+            match self.inner.read(chunk.mut_slice_from(len)) {
+                Some(n) => len += n,
+                None => {
+                    self.done = true;
+                    break
+                }
+            }
+        }
+        if len == 0 {
+            return None
+        }
+        chunk.truncate(len);
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::Reader;
+    use io::util::ChainedReader;
+    use io::mem::MemReader;
+    use super::Chunks;
+
+    #[test]
+    fn test_chunks() {
+        // Pieces which do not line up with the chunks, as short reads do.
+        let pieces = ~[MemReader::new(bytes!("ab").to_owned()),
+                       MemReader::new(bytes!("cde").to_owned()),
+                       MemReader::new(bytes!("fg").to_owned())];
+        let chunks: ~[~[u8]] = Chunks::new(ChainedReader::new(pieces.move_iter()), 3).collect();
+        assert_eq!(chunks, ~[bytes!("abc").to_owned(), bytes!("def").to_owned(),
+                             bytes!("g").to_owned()]);
+
+        let exact = MemReader::new(bytes!("abcdef").to_owned());
+        assert_eq!(Chunks::new(exact, 3).len(), 2);
+    }
+}
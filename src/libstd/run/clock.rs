@@ -0,0 +1,105 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Clocks for timing children
+//!
+//! `precise_time_ns` is the monotonic clock of `extra::time`, which `std`
+//! cannot use, and `wall_time_ms` is the time of day.
+
+#[cfg(not(windows), not(target_os = "macos"))]
+mod imp {
+    use libc::{c_int, timespec};
+
+    // Apparently android provides this in some other library?
+    #[cfg(not(target_os = "android"))]
+    #[link(name = "rt")]
+    extern {}
+
+    extern {
+        pub fn clock_gettime(clk_id: c_int, tp: *mut timespec) -> c_int;
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use libc::{c_int, mach_timebase_info};
+
+    extern {
+        pub fn mach_absolute_time() -> u64;
+        pub fn mach_timebase_info(info: *mut mach_timebase_info) -> c_int;
+    }
+}
+
+/// Returns the current value of a high-resolution performance counter in
+/// nanoseconds since an unspecified epoch.
+#[cfg(windows)]
+pub fn precise_time_ns() -> u64 {
+    use libc;
+
+    let mut ticks_per_s = 0;
+    assert_eq!(unsafe { libc::QueryPerformanceFrequency(&mut ticks_per_s) }, 1);
+    let ticks_per_s = if ticks_per_s == 0 {1} else {ticks_per_s};
+    let mut ticks = 0;
+    assert_eq!(unsafe { libc::QueryPerformanceCounter(&mut ticks) }, 1);
+    (ticks as u64 * 1000000000) / (ticks_per_s as u64)
+}
+
+/// Returns the current value of a high-resolution performance counter in
+/// nanoseconds since an unspecified epoch.
+#[cfg(target_os = "macos")]
+pub fn precise_time_ns() -> u64 {
+    use libc;
+
+    let time = unsafe { imp::mach_absolute_time() };
+    let mut info = libc::mach_timebase_info { numer: 0, denom: 0 };
+    unsafe { imp::mach_timebase_info(&mut info); }
+    time * ((info.numer / info.denom) as u64)
+}
+
+/// Returns the current value of a high-resolution performance counter in
+/// nanoseconds since an unspecified epoch.
+#[cfg(not(windows), not(target_os = "macos"))]
+pub fn precise_time_ns() -> u64 {
+    use libc;
+
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { imp::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts); }
+    (ts.tv_sec as u64) * 1000000000 + (ts.tv_nsec as u64)
+}
+
+/// Returns the time of day in milliseconds since the Unix epoch.
+#[cfg(unix)]
+pub fn wall_time_ms() -> u64 {
+    use libc::types::os::common::posix01::timeval;
+    use libc::{c_int, c_void};
+    use ptr;
+
+    extern {
+        fn gettimeofday(tv: *mut timeval, tz: *c_void) -> c_int;
+    }
+
+    let mut tv = timeval { tv_sec: 0, tv_usec: 0 };
+    unsafe { gettimeofday(&mut tv, ptr::null()); }
+    (tv.tv_sec as u64) * 1000 + (tv.tv_usec as u64) / 1000
+}
+
+/// Returns the time of day in milliseconds since the Unix epoch.
+#[cfg(windows)]
+pub fn wall_time_ms() -> u64 {
+    use libc;
+
+    // FILETIMEs count units of 100 nanoseconds since 1601.
+    static EPOCH_OFFSET_MS: u64 = 11644473600000;
+
+    let mut ft = libc::FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+    unsafe { libc::GetSystemTimeAsFileTime(&mut ft); }
+    let ticks = (ft.dwHighDateTime as u64 << 32) | ft.dwLowDateTime as u64;
+    ticks / 10000 - EPOCH_OFFSET_MS
+}
@@ -0,0 +1,125 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Transcoding of output written in a Windows code page
+//!
+//! Console programs on Windows write their output in the code page of the
+//! console they are attached to, which is almost never UTF-8. Captured output
+//! of `dir` or of a localized tool is therefore not valid UTF-8 unless it is
+//! transcoded first.
+
+use prelude::*;
+
+/// Describes how the captured output of a child is encoded.
+#[deriving(Clone, Eq)]
+pub enum OutputEncoding {
+    /// Keep the output exactly as the child wrote it.
+    Untranscoded,
+
+    /// Transcode the output to UTF-8 from the code page which console
+    /// programs use on Windows: the output code page of the console of the
+    /// current process if it has one, and the OEM code page otherwise. This
+    /// is the same as Untranscoded on other platforms.
+    ConsoleCodePage,
+
+    /// Transcode the output to UTF-8 from the given Windows code page. This is
+    /// the same as Untranscoded on other platforms.
+    CodePage(uint),
+}
+
+/// Returns the code page console programs started by the current process
+/// write their output in.
+#[cfg(windows)]
+pub fn console_code_page() -> uint {
+    unsafe {
+        match imp::GetConsoleOutputCP() {
+            0 => imp::GetOEMCP() as uint,
+            cp => cp as uint,
+        }
+    }
+}
+
+/// Transcodes `bytes` to UTF-8 according to `encoding`.
+///
+/// Bytes which are invalid in the code page are replaced with U+FFFD, so
+/// that the result is always valid UTF-8.
+#[cfg(windows)]
+pub fn transcode(bytes: ~[u8], encoding: OutputEncoding) -> ~[u8] {
+    use libc::c_int;
+    use str;
+    use vec;
+
+    let cp = match encoding {
+        Untranscoded => return bytes,
+        ConsoleCodePage => console_code_page(),
+        CodePage(cp) => cp,
+    };
+    if cp == imp::CP_UTF8 as uint || bytes.is_empty() {
+        return bytes;
+    }
+    unsafe {
+        let len = imp::MultiByteToWideChar(cp as imp::UINT, 0,
+                                           bytes.as_ptr(), bytes.len() as c_int,
+                                           0 as *mut u16, 0);
+        if len <= 0 {
+            return bytes;
+        }
+        let mut wide = vec::from_elem(len as uint, 0u16);
+        imp::MultiByteToWideChar(cp as imp::UINT, 0,
+                                 bytes.as_ptr(), bytes.len() as c_int,
+                                 wide.as_mut_ptr(), len);
+        str::from_utf16(wide).into_bytes()
+    }
+}
+
+#[cfg(not(windows))]
+pub fn transcode(bytes: ~[u8], _encoding: OutputEncoding) -> ~[u8] {
+    bytes
+}
+
+#[cfg(windows)]
+mod imp {
+    use libc::c_int;
+
+    pub type UINT = u32;
+
+    pub static CP_UTF8: UINT = 65001;
+
+    extern "system" {
+        pub fn GetConsoleOutputCP() -> UINT;
+        pub fn GetOEMCP() -> UINT;
+        pub fn MultiByteToWideChar(CodePage: UINT, dwFlags: u32,
+                                   lpMultiByteStr: *u8, cbMultiByte: c_int,
+                                   lpWideCharStr: *mut u16,
+                                   cchWideChar: c_int) -> c_int;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::{transcode, Untranscoded};
+
+    #[test]
+    fn test_untranscoded() {
+        let bytes = ~[0x82u8, 0xa0];
+        assert_eq!(transcode(bytes.clone(), Untranscoded), bytes);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_transcode() {
+        use super::CodePage;
+
+        // "é" in code page 850 and 1252
+        assert_eq!(transcode(~[0x82u8], CodePage(850)), "é".as_bytes().to_owned());
+        assert_eq!(transcode(~[0xe9u8], CodePage(1252)), "é".as_bytes().to_owned());
+    }
+}
@@ -0,0 +1,442 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Commands as values, and shell-like combinations of them
+
+use io::process::{ProcessExit, ExitStatus, ExitSignal};
+use io;
+use libc;
+use prelude::*;
+use vec;
+
+use super::{classify_exit, ExitSuccess};
+use super::{Process, ProcessOptions, env, job};
+
+/// A program along with its arguments, which can be run any number of times
+/// and combined with other commands like in a shell.
+///
+/// Commands are built by chaining calls, as in
+/// `Command::new("git").arg("status").cwd(&repo).env("GIT_PAGER", "cat")`,
+/// and can then be spawned as a `Process` or run in one go.
+#[deriving(Clone)]
+pub struct Command {
+    priv program: ~str,
+    priv args: ~[~str],
+    priv dir: Option<Path>,
+    priv env: ~[(~str, ~str)],
+}
+
+impl Command {
+    /// Creates a command which runs `program` without any arguments, in the
+    /// working directory and with the environment of the current process.
+    pub fn new(program: &str) -> Command {
+        Command { program: program.to_owned(), args: ~[], dir: None, env: ~[] }
+    }
+
+    /// Appends an argument to the command line.
+    pub fn arg(mut self, arg: &str) -> Command {
+        self.args.push(arg.to_owned());
+        self
+    }
+
+    /// Appends a number of arguments to the command line.
+    pub fn args(mut self, args: &[~str]) -> Command {
+        self.args.push_all(args);
+        self
+    }
+
+    /// Sets the working directory the command runs in.
+    pub fn cwd(mut self, dir: &Path) -> Command {
+        self.dir = Some(dir.clone());
+        self
+    }
+
+    /// Sets the variable `key` to `value` in the environment the command
+    /// inherits from the current process.
+    pub fn env(mut self, key: &str, value: &str) -> Command {
+        env::set(&mut self.env, key, value);
+        self
+    }
+
+    /// Returns the program this command runs.
+    pub fn get_program<'a>(&'a self) -> &'a str { self.program.as_slice() }
+
+    /// Returns the arguments this command passes to the program.
+    pub fn get_args<'a>(&'a self) -> &'a [~str] { self.args.as_slice() }
+
+    /// Returns the working directory the command runs in, if it was set.
+    pub fn get_cwd<'a>(&'a self) -> Option<&'a Path> { self.dir.as_ref() }
+
+    /// Returns the options a `Process` is spawned with for this command.
+    pub fn options<'a>(&'a self) -> ProcessOptions<'a> {
+        ProcessOptions {
+            dir: self.dir.as_ref(),
+            env_set: self.env.clone(),
+            .. ProcessOptions::new()
+        }
+    }
+
+    /// Spawns the command with pipes to its standard streams, returning the
+    /// `Process` running it.
+    ///
+    /// If the command cannot be started, then the `io_error` condition is
+    /// raised and None is returned.
+    pub fn spawn(&self) -> Option<Process> {
+        Process::new(self.program, self.args, self.options())
+    }
+
+    /// Runs the command with the standard streams of the current process and
+    /// waits for it to terminate.
+    ///
+    /// As in a shell, a command which cannot be started is reported with the
+    /// exit status 127.
+    pub fn status(&self) -> ProcessExit {
+        let opts = ProcessOptions {
+            in_fd: Some(inherit(libc::STDIN_FILENO)),
+            out_fd: Some(inherit(libc::STDOUT_FILENO)),
+            err_fd: Some(inherit(libc::STDERR_FILENO)),
+            .. self.options()
+        };
+        match io::result(|| Process::new(self.program, self.args, opts)) {
+            Ok(Some(mut p)) => p.finish(),
+            Ok(None) | Err(..) => ExitStatus(127),
+        }
+    }
+
+    /// Starts the command with its output captured and returns right away.
+    ///
+    /// The output and exit status of the command are collected in the
+    /// background, and can be retrieved later from any task by passing the
+    /// returned id to `run::collect`. Returns None if the command could not be
+    /// started.
+    pub fn start_background(&self) -> Option<job::JobId> {
+        self.spawn().map(|p| job::start(p))
+    }
+
+    /// Runs this command, followed by `next` only if this command succeeded.
+    /// This is the `&&` of a shell.
+    pub fn and_then(self, next: Command) -> Chain {
+        let chain = Chain { first: self, rest: ~[] };
+        chain.and_then(next)
+    }
+
+    /// Runs this command, followed by `next` only if this command failed.
+    /// This is the `||` of a shell.
+    pub fn or_else(self, next: Command) -> Chain {
+        let chain = Chain { first: self, rest: ~[] };
+        chain.or_else(next)
+    }
+
+    /// Runs this command with its output fed to the input of `next`. This is
+    /// the `|` of a shell.
+    pub fn pipe(self, next: Command) -> Pipeline {
+        Pipeline { stages: ~[self, next] }
+    }
+
+    fn succeeded(&self, status: &ProcessExit) -> bool {
+        classify_exit(self.program, status) == ExitSuccess
+    }
+}
+
+#[deriving(Clone, Eq)]
+enum Connector {
+    And,
+    Or,
+}
+
+/// A list of commands joined with `&&` and `||`, which like in a shell are
+/// evaluated from left to right with equal precedence.
+#[deriving(Clone)]
+pub struct Chain {
+    priv first: Command,
+    priv rest: ~[(Connector, Command)],
+}
+
+/// The result of running a `Chain`.
+pub struct ChainStatus {
+    /// The status of each command of the chain, in order, or None for the
+    /// commands which were skipped.
+    statuses: ~[Option<ProcessExit>],
+
+    /// Whether the last command which ran succeeded, which is what a shell
+    /// would report as the status of the whole chain.
+    success: bool,
+}
+
+impl Chain {
+    /// Appends `next`, to be run only if the chain so far succeeded.
+    pub fn and_then(mut self, next: Command) -> Chain {
+        self.rest.push((And, next));
+        self
+    }
+
+    /// Appends `next`, to be run only if the chain so far failed.
+    pub fn or_else(mut self, next: Command) -> Chain {
+        self.rest.push((Or, next));
+        self
+    }
+
+    /// Runs the commands of the chain, skipping those which the status of the
+    /// last command which ran rules out.
+    pub fn status(&self) -> ChainStatus {
+        let status = self.first.status();
+        let mut success = self.first.succeeded(&status);
+        let mut statuses = ~[Some(status)];
+        for &(connector, ref cmd) in self.rest.iter() {
+            if (connector == And) != success {
+                statuses.push(None);
+                continue
+            }
+            let status = cmd.status();
+            success = cmd.succeeded(&status);
+            statuses.push(Some(status));
+        }
+        ChainStatus { statuses: statuses, success: success }
+    }
+}
+
+/// A list of commands joined with `|`, which like in a shell run at the same
+/// time, each with its output fed to the input of the next one.
+#[deriving(Clone)]
+pub struct Pipeline {
+    priv stages: ~[Command],
+}
+
+/// The result of running a `Pipeline`.
+pub struct PipelineStatus {
+    /// The status of each stage of the pipeline, in order. A stage which
+    /// could not be started has the exit status 127, as in a shell.
+    statuses: ~[ProcessExit],
+
+    /// Whether the last stage succeeded, which is what a shell reports as the
+    /// status of the whole pipeline by default.
+    success: bool,
+
+    /// The index of the last stage which failed, which is what a shell with
+    /// `set -o pipefail` reports. A stage killed by SIGPIPE is not counted
+    /// unless it is the last one, since it was only stopped because a later
+    /// stage exited without reading all of its output, as in `yes | head`.
+    failed_stage: Option<uint>,
+}
+
+impl PipelineStatus {
+    /// Returns whether every stage succeeded, like the status of the whole
+    /// pipeline reported by a shell with `set -o pipefail`.
+    pub fn pipefail(&self) -> bool {
+        self.failed_stage.is_none()
+    }
+}
+
+impl Pipeline {
+    /// Appends `next`, to be fed the output of the pipeline so far.
+    pub fn pipe(mut self, next: Command) -> Pipeline {
+        self.stages.push(next);
+        self
+    }
+
+    /// Runs the stages of the pipeline and waits for all of them to
+    /// terminate. The first stage reads the stdin of the current process,
+    /// the last one writes to its stdout, and they all write to its stderr.
+    pub fn status(&self) -> PipelineStatus {
+        let last = self.stages.len() - 1;
+        let mut procs = ~[];
+        for (i, cmd) in self.stages.iter().enumerate() {
+            let opts = ProcessOptions {
+                in_fd: if i == 0 { Some(inherit(libc::STDIN_FILENO)) } else { None },
+                out_fd: if i == last { Some(inherit(libc::STDOUT_FILENO)) } else { None },
+                err_fd: Some(inherit(libc::STDERR_FILENO)),
+                .. cmd.options()
+            };
+            procs.push(match io::result(|| Process::new(cmd.program, cmd.args, opts)) {
+                Ok(Some(p)) => Some(p),
+                Ok(None) | Err(..) => None,
+            });
+        }
+        for i in range(0, last) {
+            let output = procs[i].as_mut().and_then(|p| p.take_output());
+            let input = procs[i + 1].as_mut().and_then(|p| p.take_input());
+            pump(output, input);
+        }
+
+        let statuses: ~[ProcessExit] = procs.mut_iter().map(|p| {
+            match *p {
+                Some(ref mut p) => p.finish(),
+                None => ExitStatus(127),
+            }
+        }).collect();
+        let mut failed_stage = None;
+        for (i, (cmd, status)) in self.stages.iter().zip(statuses.iter()).enumerate() {
+            if !cmd.succeeded(status) && (i == last || !killed_by_sigpipe(status)) {
+                failed_stage = Some(i);
+            }
+        }
+        let success = self.stages[last].succeeded(&statuses[last]);
+        PipelineStatus { statuses: statuses, success: success, failed_stage: failed_stage }
+    }
+}
+
+/// Returns a duplicate of the file descriptor `fd` of the current process,
+/// for a child to use.
+fn inherit(fd: libc::c_int) -> libc::c_int {
+    unsafe { libc::dup(fd) }
+}
+
+/// Copies `from` to `to` in a new task until either of them is closed. When
+/// `to` is closed first, `from` is closed too so that the stage writing to
+/// it is stopped by SIGPIPE, as in a shell.
+fn pump(from: Option<io::PipeStream>, to: Option<io::PipeStream>) {
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return,
+    };
+    do spawn {
+        let _guard = io::ignore_io_error();
+        let (mut from, mut to) = (from, to);
+        let mut buf = vec::from_elem(64 * 1024, 0u8);
+        loop {
+            let n = match from.read(buf) {
+                Some(n) => n,
+                None => break,
+            };
+            match io::result(|| to.write(buf.slice_to(n))) {
+                Ok(()) => {}
+                Err(..) => break,
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn killed_by_sigpipe(status: &ProcessExit) -> bool {
+    *status == ExitSignal(libc::SIGPIPE as int)
+}
+
+#[cfg(windows)]
+fn killed_by_sigpipe(_status: &ProcessExit) -> bool {
+    false
+}
+
+/**
+ * Runs each of `cmds` in turn until one of them fails, like a shell script
+ * with `set -e`.
+ *
+ * Whether a command failed is decided by `classify_exit`.
+ *
+ * # Return value
+ *
+ * The statuses of the commands which were run, the last of which is the
+ * failure if there was one.
+ */
+pub fn sequence(cmds: &[Command]) -> ~[ProcessExit] {
+    let mut statuses = ~[];
+    for cmd in cmds.iter() {
+        let status = cmd.status();
+        let success = cmd.succeeded(&status);
+        statuses.push(status);
+        if !success { break }
+    }
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use io::process::ExitStatus;
+    use prelude::*;
+    use super::{Command, sequence};
+    use os;
+    use run;
+    use str;
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_spawn() {
+        let dir = os::tmpdir();
+        let mut prog = Command::new("sh").arg("-c").arg("echo $RUN_TEST_COMMAND; pwd")
+            .cwd(&dir)
+            .env("RUN_TEST_COMMAND", "set")
+            .spawn()
+            .unwrap();
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        let lines: ~[&str] = output.lines().collect();
+        assert_eq!(lines[0], "set");
+        assert_eq!(Path::new(lines[1]).stat().unstable.inode, dir.stat().unstable.inode);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_and_then() {
+        let status = Command::new("true").and_then(Command::new("false")).status();
+        assert_eq!(status.statuses, ~[Some(ExitStatus(0)), Some(ExitStatus(1))]);
+        assert!(!status.success);
+
+        let status = Command::new("false").and_then(Command::new("true")).status();
+        assert_eq!(status.statuses, ~[Some(ExitStatus(1)), None]);
+        assert!(!status.success);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_or_else() {
+        let status = Command::new("false").or_else(Command::new("true"))
+                                          .and_then(Command::new("true"))
+                                          .status();
+        assert_eq!(status.statuses,
+                   ~[Some(ExitStatus(1)), Some(ExitStatus(0)), Some(ExitStatus(0))]);
+        assert!(status.success);
+
+        let status = Command::new("true").or_else(Command::new("false")).status();
+        assert_eq!(status.statuses, ~[Some(ExitStatus(0)), None]);
+        assert!(status.success);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_start_background() {
+        let first = Command::new("echo").arg("first").start_background().unwrap();
+        let second = Command::new("sh").arg("-c").arg("exit 2").start_background().unwrap();
+
+        let (port, chan) = Chan::new();
+        do spawn {
+            chan.send(run::collect(second).unwrap().status);
+        }
+        let output = run::collect(first.clone()).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.output, bytes!("first\n").to_owned());
+        assert_eq!(port.recv(), ExitStatus(2));
+
+        assert!(run::collect(first).is_none());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_pipeline() {
+        let status = Command::new("sh").arg("-c").arg("echo hi; exit 3")
+            .pipe(Command::new("sh").arg("-c").arg("read line && test \"$line\" = hi"))
+            .status();
+        assert_eq!(status.statuses, ~[ExitStatus(3), ExitStatus(0)]);
+        assert!(status.success);
+        assert_eq!(status.failed_stage, Some(0));
+        assert!(!status.pipefail());
+
+        let status = Command::new("true").pipe(Command::new("no-program-by-this-name")).status();
+        assert_eq!(status.statuses, ~[ExitStatus(0), ExitStatus(127)]);
+        assert!(!status.success);
+        assert_eq!(status.failed_stage, Some(1));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_sequence() {
+        let cmds = [Command::new("true"),
+                    Command::new("sh").arg("-c").arg("exit 4"),
+                    Command::new("true")];
+        assert_eq!(sequence(cmds), ~[ExitStatus(0), ExitStatus(4)]);
+    }
+}
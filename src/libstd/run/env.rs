@@ -0,0 +1,281 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Utilities for working with the environment of a child process
+//!
+//! The names of variables are compared regardless of their case on Windows,
+//! where `PATH` and `Path` name the same variable.
+
+use ascii::StrAsciiExt;
+use fmt;
+use prelude::*;
+use str;
+
+/// A single difference between the environment of the current process and
+/// the environment that a child is spawned with.
+#[deriving(Clone, Eq)]
+pub enum EnvChange {
+    /// The variable is only set for the child, with the given value.
+    EnvAdded(~str, ~str),
+
+    /// The variable is set for both, but with a different value. The values
+    /// are the parent's followed by the child's.
+    EnvChanged(~str, ~str, ~str),
+
+    /// The variable is only set for the parent.
+    EnvRemoved(~str),
+}
+
+impl fmt::Default for EnvChange {
+    /// Format an EnvChange in a compact form suitable for logging.
+    fn fmt(obj: &EnvChange, f: &mut fmt::Formatter) {
+        match *obj {
+            EnvAdded(ref k, ref v) => write!(f.buf, "+{}={}", *k, *v),
+            EnvChanged(ref k, ref old, ref new) => {
+                write!(f.buf, "~{}={} (was {})", *k, *new, *old)
+            }
+            EnvRemoved(ref k) => write!(f.buf, "-{}", *k),
+        }
+    }
+}
+
+/// Returns the environment of a reproducible child, made of the variables
+/// of `env` which locate the system and its tools, `PATH` and `HOME` (and
+/// `SystemRoot` and `TEMP` on Windows, without which many programs fail),
+/// along with the C locale and the UTC timezone.
+pub fn reproducible(env: &[(~str, ~str)]) -> ~[(~str, ~str)] {
+    let kept: &[&str] = if cfg!(windows) {
+        &["PATH", "HOME", "SystemRoot", "TEMP"]
+    } else {
+        &["PATH", "HOME"]
+    };
+    let mut ret: ~[(~str, ~str)] = env.iter().filter(|&&(ref k, _)| {
+        kept.iter().any(|&name| same_name(*k, name))
+    }).map(|kv| kv.clone()).collect();
+    set(&mut ret, "LANG", "C");
+    set(&mut ret, "LC_ALL", "C");
+    set(&mut ret, "TZ", "UTC");
+    ret
+}
+
+/// Checks whether `a` and `b` name the same variable.
+pub fn same_name(a: &str, b: &str) -> bool {
+    if cfg!(windows) { a.eq_ignore_ascii_case(b) } else { a == b }
+}
+
+/// Returns the value of `key` in `env`, if it is present.
+pub fn lookup<'a>(env: &'a [(~str, ~str)], key: &str) -> Option<&'a str> {
+    for &(ref k, ref v) in env.iter() {
+        if same_name(*k, key) {
+            return Some(v.as_slice());
+        }
+    }
+    None
+}
+
+/// Sets `key` to `value` in `env`, replacing any previous value.
+pub fn set(env: &mut ~[(~str, ~str)], key: &str, value: &str) {
+    env.retain(|&(ref k, _)| !same_name(*k, key));
+    env.push((key.to_owned(), value.to_owned()));
+}
+
+/// Merges the variables of `env` which have the same name into the first of
+/// them, with the value of the last, which is the one a lookup in the
+/// environment block of a child would not find on Windows.
+pub fn normalize(env: &[(~str, ~str)]) -> ~[(~str, ~str)] {
+    let mut ret: ~[(~str, ~str)] = ~[];
+    for &(ref k, ref v) in env.iter() {
+        match ret.iter().position(|&(ref name, _)| same_name(*name, *k)) {
+            Some(i) => {
+                let name = match ret[i] { (ref name, _) => name.clone() };
+                ret[i] = (name, v.clone());
+            }
+            None => ret.push((k.clone(), v.clone())),
+        }
+    }
+    ret
+}
+
+/// Removes the variable `key` from `env`.
+pub fn remove(env: &mut ~[(~str, ~str)], key: &str) {
+    env.retain(|&(ref k, _)| !same_name(*k, key));
+}
+
+/// Checks whether the variable `name` matches `pattern`, in which a `*`
+/// matches any (possibly empty) sequence of characters.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    if cfg!(windows) {
+        matches_exactly(pattern.to_ascii_upper(), name.to_ascii_upper())
+    } else {
+        matches_exactly(pattern, name)
+    }
+}
+
+fn matches_exactly(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(i) => {
+            let prefix = pattern.slice_to(i);
+            let rest = pattern.slice_from(i + 1);
+            if !name.starts_with(prefix) { return false }
+            let name = name.slice_from(prefix.len());
+            range(0, name.len() + 1).any(|j| {
+                name.is_char_boundary(j) && matches_exactly(rest, name.slice_from(j))
+            })
+        }
+    }
+}
+
+/// Removes all variables whose name matches any of `patterns` from `env`.
+pub fn deny(env: &mut ~[(~str, ~str)], patterns: &[~str]) {
+    env.retain(|&(ref k, _)| !patterns.iter().any(|p| matches(*p, *k)));
+}
+
+/// Computes the changes which turn the `parent` environment into the `child`
+/// environment. Variables present in `parent` are reported in their original
+/// order, followed by the variables which only exist in `child`.
+pub fn diff(parent: &[(~str, ~str)], child: &[(~str, ~str)]) -> ~[EnvChange] {
+    let mut changes = ~[];
+    for &(ref k, ref v) in parent.iter() {
+        match lookup(child, *k) {
+            None => changes.push(EnvRemoved(k.clone())),
+            Some(new) if new != v.as_slice() => {
+                changes.push(EnvChanged(k.clone(), v.clone(), new.to_owned()))
+            }
+            Some(..) => {}
+        }
+    }
+    for &(ref k, ref v) in child.iter() {
+        if lookup(parent, *k).is_none() {
+            changes.push(EnvAdded(k.clone(), v.clone()));
+        }
+    }
+    changes
+}
+
+/**
+ * Expands the `${VAR}` references in `arg` to the values of the variables in
+ * `env`. A `$$` stands for a single `$`, and a `$` which is followed by
+ * anything else is kept as it is.
+ *
+ * # Return value
+ *
+ * The expanded argument, or a description of the problem if a referenced
+ * variable is not set in `env` or if a reference is not terminated.
+ */
+pub fn expand(arg: &str, env: &[(~str, ~str)]) -> Result<~str, ~str> {
+    let mut ret = str::with_capacity(arg.len());
+    let mut rest = arg;
+    loop {
+        let i = match rest.find('$') {
+            Some(i) => i,
+            None => break,
+        };
+        ret.push_str(rest.slice_to(i));
+        rest = rest.slice_from(i + 1);
+        if rest.starts_with("$") {
+            ret.push_char('$');
+            rest = rest.slice_from(1);
+        } else if rest.starts_with("{") {
+            let end = match rest.find('}') {
+                Some(end) => end,
+                None => return Err(format!("unterminated variable reference in `{}`", arg)),
+            };
+            let name = rest.slice(1, end);
+            match lookup(env, name) {
+                Some(value) => ret.push_str(value),
+                None => return Err(format!("undefined variable `{}`", name)),
+            }
+            rest = rest.slice_from(end + 1);
+        } else {
+            ret.push_char('$');
+        }
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::{diff, deny, expand, matches, reproducible, EnvAdded, EnvChanged, EnvRemoved};
+    use super::{lookup, normalize, set};
+
+    #[test]
+    fn test_diff() {
+        let parent = [(~"A", ~"1"), (~"B", ~"2"), (~"C", ~"3")];
+        let child = [(~"A", ~"1"), (~"C", ~"4"), (~"D", ~"5")];
+        assert_eq!(diff(parent, child),
+                   ~[EnvRemoved(~"B"),
+                     EnvChanged(~"C", ~"3", ~"4"),
+                     EnvAdded(~"D", ~"5")]);
+        assert_eq!(diff(parent, parent), ~[]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_reproducible() {
+        let env = [(~"PATH", ~"/bin"), (~"LANG", ~"fr_FR.UTF-8"), (~"EDITOR", ~"vi")];
+        assert_eq!(reproducible(env),
+                   ~[(~"PATH", ~"/bin"), (~"LANG", ~"C"), (~"LC_ALL", ~"C"), (~"TZ", ~"UTC")]);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_case_insensitive() {
+        let mut env = normalize([(~"Path", ~"a"), (~"TEMP", ~"t"), (~"PATH", ~"b")]);
+        assert_eq!(env, ~[(~"Path", ~"b"), (~"TEMP", ~"t")]);
+        assert_eq!(lookup(env, "path"), Some("b"));
+        set(&mut env, "PATH", "c");
+        assert_eq!(env, ~[(~"TEMP", ~"t"), (~"PATH", ~"c")]);
+        assert!(matches("*_token", "NPM_TOKEN"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_case_sensitive() {
+        let mut env = normalize([(~"Path", ~"a"), (~"A", ~"1"), (~"PATH", ~"b"), (~"A", ~"2")]);
+        assert_eq!(env, ~[(~"Path", ~"a"), (~"A", ~"2"), (~"PATH", ~"b")]);
+        assert_eq!(lookup(env, "path"), None);
+        set(&mut env, "PATH", "c");
+        assert_eq!(env, ~[(~"Path", ~"a"), (~"A", ~"2"), (~"PATH", ~"c")]);
+        assert!(!matches("*_token", "NPM_TOKEN"));
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("SSH_AUTH_SOCK", "SSH_AUTH_SOCK"));
+        assert!(!matches("SSH_AUTH_SOCK", "SSH_AUTH_SOCKET"));
+        assert!(matches("*_TOKEN", "GITHUB_TOKEN"));
+        assert!(!matches("*_TOKEN", "GITHUB_TOKENS"));
+        assert!(matches("AWS_*", "AWS_SECRET_ACCESS_KEY"));
+        assert!(matches("*SECRET*", "AWS_SECRET_ACCESS_KEY"));
+        assert!(matches("*", ""));
+        assert!(!matches("A*B", "AC"));
+    }
+
+    #[test]
+    fn test_deny() {
+        let mut env = ~[(~"PATH", ~"/bin"), (~"AWS_KEY", ~"x"), (~"NPM_TOKEN", ~"y")];
+        deny(&mut env, [~"AWS_*", ~"*_TOKEN"]);
+        assert_eq!(env, ~[(~"PATH", ~"/bin")]);
+    }
+
+    #[test]
+    fn test_expand() {
+        let env = [(~"HOME", ~"/home/me"), (~"EMPTY", ~"")];
+        assert_eq!(expand("${HOME}/bin", env), Ok(~"/home/me/bin"));
+        assert_eq!(expand("a${EMPTY}b", env), Ok(~"ab"));
+        assert_eq!(expand("$$HOME costs $5", env), Ok(~"$HOME costs $5"));
+        assert_eq!(expand("$${HOME}", env), Ok(~"${HOME}"));
+        assert!(expand("${NOPE}", env).is_err());
+        assert!(expand("${HOME", env).is_err());
+    }
+}
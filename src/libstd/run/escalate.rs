@@ -0,0 +1,59 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Escalation of the signals sent to a child which is being destroyed
+
+use libc::pid_t;
+
+#[cfg(unix)] use comm::Select;
+#[cfg(unix)] use io::Timer;
+#[cfg(unix)] use libc::c_int;
+#[cfg(unix)] use libc;
+use prelude::*;
+
+/**
+ * Starts sending the signals of `steps` to the child `pid` in turn, each
+ * after waiting the given number of milliseconds for the child to exit.
+ *
+ * # Return value
+ *
+ * A channel which stops the escalation when it is dropped, which must happen
+ * as soon as the child has been waited for since its id may be reused from
+ * then on. None is returned if there is nothing to escalate to, or on
+ * Windows, where the first signal already terminates the child.
+ */
+#[cfg(unix)]
+pub fn escalate(pid: pid_t, steps: ~[(u64, int)]) -> Option<Chan<()>> {
+    if steps.is_empty() {
+        return None
+    }
+    let mut timer = match Timer::new() {
+        Some(timer) => timer,
+        None => return None,
+    };
+    let (mut done, chan) = Chan::new();
+    do spawn {
+        for &(msecs, signal) in steps.iter() {
+            let mut timeout = timer.oneshot(msecs);
+            let sel = Select::new();
+            let done = sel.add(&mut done);
+            sel.add(&mut timeout);
+            // Nothing is ever sent on `done`, it only becomes ready once the
+            // child has been waited for.
+            if sel.wait() == done.id { break }
+            debug!("escalating to signal {} for child {}", signal, pid);
+            unsafe { libc::funcs::posix88::signal::kill(pid, signal as c_int); }
+        }
+    }
+    Some(chan)
+}
+
+#[cfg(windows)]
+pub fn escalate(_pid: pid_t, _steps: ~[(u64, int)]) -> Option<Chan<()>> { None }
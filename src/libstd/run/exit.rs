@@ -0,0 +1,171 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Per-program classification of exit codes
+//!
+//! Not every program uses a nonzero exit code to signal failure: `grep` exits
+//! with 1 when nothing matched, `curl -f` exits with 22 on HTTP errors which
+//! are often worth retrying, and `robocopy` reports success through a bitmask
+//! of informational flags. Applications can register rules for such programs
+//! once, and the helpers in `run` which turn an exit status into a result
+//! consult them.
+
+use cast;
+use io::process::{ProcessExit, ExitStatus, ExitSignal};
+use prelude::*;
+use unstable::mutex::{Once, ONCE_INIT};
+use unstable::sync::Exclusive;
+
+/// How the exit status of a program should be interpreted.
+#[deriving(Clone, Eq)]
+pub enum ExitClass {
+    /// The program did what was asked of it.
+    ExitSuccess,
+
+    /// The program failed, but running it again may succeed.
+    ExitRetryable,
+
+    /// The program failed.
+    ExitFatal,
+}
+
+/// A rule mapping exit codes of a program to an `ExitClass`.
+#[deriving(Clone, Eq)]
+pub enum ExitRule {
+    /// Exactly the given exit code.
+    ExitCode(int, ExitClass),
+
+    /// Any exit code in the given inclusive range.
+    ExitRange(int, int, ExitClass),
+
+    /// Any exit code which has none of the bits in the given mask set. For
+    /// `robocopy`, `ExitMask(!7, ExitSuccess)` marks every code below 8 as a
+    /// success.
+    ExitMask(int, ExitClass),
+}
+
+impl ExitRule {
+    fn classify(&self, code: int) -> Option<ExitClass> {
+        match *self {
+            ExitCode(c, class) if c == code => Some(class),
+            ExitRange(lo, hi, class) if lo <= code && code <= hi => Some(class),
+            ExitMask(mask, class) if code & mask == 0 => Some(class),
+            _ => None,
+        }
+    }
+}
+
+type Registry = Exclusive<~[(~str, ~[ExitRule])]>;
+
+static mut REGISTRY: *mut Registry = 0 as *mut Registry;
+
+fn with_registry<T>(f: |&mut ~[(~str, ~[ExitRule])]| -> T) -> T {
+    static mut INIT: Once = ONCE_INIT;
+
+    unsafe {
+        INIT.doit(|| {
+            let registry: ~Registry = ~Exclusive::new(~[]);
+            REGISTRY = cast::transmute(registry);
+        });
+        (*REGISTRY).with(f)
+    }
+}
+
+/// Returns the name under which the rules for `program` are registered: its
+/// file name without any directory or extension, so that `/usr/bin/grep`
+/// and `grep.exe` both map to `grep`.
+fn key(program: &str) -> ~str {
+    let path = Path::new(program);
+    match path.filestem_str() {
+        Some(stem) => stem.to_owned(),
+        None => program.to_owned(),
+    }
+}
+
+/// Registers the rules used to classify the exit codes of `program`,
+/// replacing any which were previously registered. Rules are tried in order
+/// and the first matching rule wins.
+pub fn register_exit_codes(program: &str, rules: ~[ExitRule]) {
+    let key = key(program);
+    with_registry(|registry| {
+        registry.retain(|&(ref k, _)| *k != key);
+        registry.push((key.clone(), rules.clone()));
+    })
+}
+
+/// Removes the rules registered for `program`, if any.
+pub fn unregister_exit_codes(program: &str) {
+    let key = key(program);
+    with_registry(|registry| registry.retain(|&(ref k, _)| *k != key))
+}
+
+/// Classifies the exit status of a run of `program` according to the rules
+/// registered for it.
+///
+/// Exit codes which no rule covers are a success if they are zero and fatal
+/// otherwise, and termination by a signal is always fatal.
+pub fn classify_exit(program: &str, status: &ProcessExit) -> ExitClass {
+    let code = match *status {
+        ExitStatus(code) => code,
+        ExitSignal(..) => return ExitFatal,
+    };
+    let key = key(program);
+    let registered = with_registry(|registry| {
+        let mut ret = None;
+        for &(ref k, ref rules) in registry.iter() {
+            if *k == key {
+                ret = rules.iter().filter_map(|r| r.classify(code)).next();
+                break
+            }
+        }
+        ret
+    });
+    match registered {
+        Some(class) => class,
+        None if code == 0 => ExitSuccess,
+        None => ExitFatal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use io::process::{ExitStatus, ExitSignal};
+    use prelude::*;
+    use super::{register_exit_codes, unregister_exit_codes, classify_exit};
+    use super::{ExitCode, ExitRange, ExitMask};
+    use super::{ExitSuccess, ExitRetryable, ExitFatal};
+
+    #[test]
+    fn test_default_classification() {
+        assert_eq!(classify_exit("run-test-unregistered", &ExitStatus(0)), ExitSuccess);
+        assert_eq!(classify_exit("run-test-unregistered", &ExitStatus(1)), ExitFatal);
+        assert_eq!(classify_exit("run-test-unregistered", &ExitSignal(9)), ExitFatal);
+    }
+
+    #[test]
+    fn test_registered_classification() {
+        register_exit_codes("run-test-grep", ~[ExitCode(1, ExitSuccess)]);
+        assert_eq!(classify_exit("/bin/run-test-grep", &ExitStatus(1)), ExitSuccess);
+        assert_eq!(classify_exit("run-test-grep.exe", &ExitStatus(2)), ExitFatal);
+
+        register_exit_codes("run-test-curl", ~[ExitCode(22, ExitRetryable),
+                                               ExitRange(5, 7, ExitRetryable)]);
+        assert_eq!(classify_exit("run-test-curl", &ExitStatus(22)), ExitRetryable);
+        assert_eq!(classify_exit("run-test-curl", &ExitStatus(6)), ExitRetryable);
+        assert_eq!(classify_exit("run-test-curl", &ExitStatus(3)), ExitFatal);
+
+        register_exit_codes("run-test-robocopy", ~[ExitMask(!7, ExitSuccess)]);
+        assert_eq!(classify_exit("run-test-robocopy", &ExitStatus(3)), ExitSuccess);
+        assert_eq!(classify_exit("run-test-robocopy", &ExitStatus(8)), ExitFatal);
+
+        unregister_exit_codes("run-test-grep");
+        assert_eq!(classify_exit("run-test-grep", &ExitStatus(1)), ExitFatal);
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Merging the output of many children into one stream
+
+use cmp;
+use comm::SharedChan;
+use io::{Buffer, BufferedReader, Reader};
+use io;
+use prelude::*;
+use vec;
+
+use super::Process;
+
+/**
+ * A `Reader` merging several streams, such as the outputs of workers which
+ * each process a shard of the input, into a single stream.
+ *
+ * The streams are split into records ending with a delimiter, a newline for
+ * line-oriented output, and the records of different streams are never
+ * interleaved: each one appears whole, in the order in which the records
+ * were completed. The last record of a stream is given its delimiter if it
+ * lacks one, so that it stays apart from the next record. The merged stream
+ * ends once every stream has ended.
+ */
+pub struct FanInReader {
+    priv port: Port<Option<~[u8]>>,
+    priv chan: SharedChan<Option<~[u8]>>,
+    priv delim: u8,
+    priv open: uint,
+    priv record: ~[u8],
+    priv pos: uint,
+}
+
+impl FanInReader {
+    /// Creates a new reader without any streams, splitting the streams which
+    /// are added into records ending with `delim`.
+    pub fn new(delim: u8) -> FanInReader {
+        let (port, chan) = SharedChan::new();
+        FanInReader { port: port, chan: chan, delim: delim, open: 0, record: ~[], pos: 0 }
+    }
+
+    /// Creates a new reader without any streams, merging the lines of the
+    /// streams which are added.
+    pub fn lines() -> FanInReader {
+        FanInReader::new('\n' as u8)
+    }
+
+    /// Adds `stream` to the merged streams, starting to read it.
+    pub fn add<R: Reader + Send>(&mut self, stream: R) {
+        self.open += 1;
+        let chan = self.chan.clone();
+        let delim = self.delim;
+        do spawn {
+            let _guard = io::ignore_io_error();
+            let mut reader = BufferedReader::new(stream);
+            loop {
+                match reader.read_until(delim) {
+                    Some(mut record) => {
+                        if record.last() != Some(&delim) {
+                            record.push(delim);
+                        }
+                        if !chan.try_send(Some(record)) {
+                            break
+                        }
+                    }
+                    None => break,
+                }
+            }
+            chan.try_send(None);
+        }
+    }
+
+    /// Takes the stdout of `process` and adds it to the merged streams.
+    ///
+    /// Fails if there is no stdout available (it's already been removed by
+    /// take_output)
+    pub fn add_output(&mut self, process: &mut Process) {
+        self.add(process.take_output().expect("the stdout of the process was taken"))
+    }
+}
+
+impl Reader for FanInReader {
+    fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
+        while self.pos == self.record.len() {
+            if self.open == 0 {
+                return None
+            }
+            match self.port.recv() {
+                Some(record) => {
+                    self.record = record;
+                    self.pos = 0;
+                }
+                None => self.open -= 1,
+            }
+        }
+        let n = cmp::min(buf.len(), self.record.len() - self.pos);
+        vec::bytes::copy_memory(buf.mut_slice_to(n), self.record.slice(self.pos, self.pos + n));
+        self.pos += n;
+        Some(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::mem::MemReader;
+    use super::FanInReader;
+
+    #[test]
+    fn test_fan_in_reader() {
+        let mut reader = FanInReader::lines();
+        reader.add(MemReader::new(bytes!("a1\na2\n").to_owned()));
+        reader.add(MemReader::new(bytes!("b1\nb2").to_owned()));
+        let mut lines = reader.read_to_end().split(|&b| b == '\n' as u8)
+                                            .map(|l| l.to_owned()).collect::<~[~[u8]]>();
+        lines.sort();
+        assert_eq!(lines, ~[~[], bytes!("a1").to_owned(), bytes!("a2").to_owned(),
+                            bytes!("b1").to_owned(), bytes!("b2").to_owned()]);
+    }
+}
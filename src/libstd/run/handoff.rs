@@ -0,0 +1,131 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Handing children over to a successor of the current process
+//!
+//! A supervisor which upgrades itself by exec'ing a new version of its binary
+//! keeps its children, since they remain children of the same process, but
+//! loses the `Process` values tracking them. Handing a child off turns its
+//! `Process` into a `ProcessHandoff`, a line of text which can be passed to
+//! the new image in an argument, an environment variable or a file, and from
+//! which the new image adopts the child again.
+//!
+//! The id of a child which exits and is reaped by someone else may be reused
+//! by an unrelated process in the meantime. Where the system provides it, a
+//! handoff therefore also records when the child was started, and adopting
+//! it fails if the process with its id was started at another time.
+
+use libc::pid_t;
+use prelude::*;
+
+#[cfg(target_os = "linux")] use io::File;
+#[cfg(target_os = "linux")] use str;
+
+/// The state needed to adopt a child which was handed off.
+#[deriving(Clone, Eq)]
+pub struct ProcessHandoff {
+    /// The id of the child.
+    pid: pid_t,
+
+    /// When the child was started, in a unit specific to the system, if this
+    /// is known.
+    start_time: Option<u64>,
+
+    /// The program the child was spawned from, which is used to classify its
+    /// exit status.
+    program: ~str,
+}
+
+impl ToStr for ProcessHandoff {
+    /// Serializes the handoff as a single line, in the form `pid start_time
+    /// program`, with a start time of `-` if it is not known.
+    fn to_str(&self) -> ~str {
+        match self.start_time {
+            Some(t) => format!("{} {} {}", self.pid, t, self.program),
+            None => format!("{} - {}", self.pid, self.program),
+        }
+    }
+}
+
+impl FromStr for ProcessHandoff {
+    /// Parses a handoff serialized by `to_str`.
+    fn from_str(s: &str) -> Option<ProcessHandoff> {
+        let mut parts = s.splitn(' ', 2);
+        let pid = match parts.next().and_then(|p| from_str::<pid_t>(p)) {
+            Some(pid) => pid,
+            None => return None,
+        };
+        let start_time = match parts.next() {
+            Some("-") => None,
+            Some(t) => match from_str::<u64>(t) {
+                Some(t) => Some(t),
+                None => return None,
+            },
+            None => return None,
+        };
+        let program = parts.next().unwrap_or("").to_owned();
+        Some(ProcessHandoff { pid: pid, start_time: start_time, program: program })
+    }
+}
+
+/// Returns when the process `pid` was started, if this can be found out.
+///
+/// On Linux this is the start time of `/proc/<pid>/stat`, in clock ticks
+/// since the boot of the system.
+#[cfg(target_os = "linux")]
+pub fn start_time(pid: pid_t) -> Option<u64> {
+    let path = Path::new(format!("/proc/{}/stat", pid));
+    let stat = match File::open(&path).map(|mut f| f.read_to_end()) {
+        Some(stat) => stat,
+        None => return None,
+    };
+    let stat = match str::from_utf8_owned(stat) {
+        Some(stat) => stat,
+        None => return None,
+    };
+    // The name of the program comes second, in parentheses, and may contain
+    // spaces and parentheses itself. The start time is the 20th field after
+    // it.
+    let fields = match stat.rfind(')') {
+        Some(i) => stat.slice_from(i + 1),
+        None => return None,
+    };
+    fields.words().nth(19).and_then(|t| from_str::<u64>(t))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn start_time(_pid: pid_t) -> Option<u64> { None }
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::ProcessHandoff;
+
+    #[test]
+    fn test_handoff_to_str() {
+        let h = ProcessHandoff { pid: 42, start_time: Some(1234), program: ~"my prog" };
+        assert_eq!(h.to_str(), ~"42 1234 my prog");
+        assert_eq!(from_str::<ProcessHandoff>(h.to_str()), Some(h));
+
+        let h = ProcessHandoff { pid: 42, start_time: None, program: ~"sleep" };
+        assert_eq!(h.to_str(), ~"42 - sleep");
+        assert_eq!(from_str::<ProcessHandoff>(h.to_str()), Some(h));
+
+        assert_eq!(from_str::<ProcessHandoff>("42"), None);
+        assert_eq!(from_str::<ProcessHandoff>("x - sleep"), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_start_time() {
+        use libc;
+        assert!(super::start_time(unsafe { libc::getpid() }).is_some());
+    }
+}
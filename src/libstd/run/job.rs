@@ -0,0 +1,87 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Background jobs which are started in one place and collected in another
+//!
+//! Each job is a child process whose output is collected by a dedicated task
+//! as it runs. The result is kept in a global table, keyed by the id of the
+//! job, until some task collects it.
+
+use cast;
+use prelude::*;
+use unstable::mutex::{Once, ONCE_INIT};
+use unstable::sync::Exclusive;
+
+use super::{Process, ProcessOutput};
+
+/// Identifies a job started with `Command::start_background`.
+#[deriving(Clone, Eq)]
+pub struct JobId(uint);
+
+struct Jobs {
+    next_id: uint,
+    pending: ~[(uint, Port<ProcessOutput>)],
+}
+
+static mut JOBS: *mut Exclusive<Jobs> = 0 as *mut Exclusive<Jobs>;
+
+fn with_jobs<T>(f: |&mut Jobs| -> T) -> T {
+    static mut INIT: Once = ONCE_INIT;
+
+    unsafe {
+        INIT.doit(|| {
+            let jobs: ~Exclusive<Jobs> = ~Exclusive::new(Jobs {
+                next_id: 0,
+                pending: ~[],
+            });
+            JOBS = cast::transmute(jobs);
+        });
+        (*JOBS).with(f)
+    }
+}
+
+/// Registers `p` as a new job, collecting its output in the background.
+pub fn start(p: Process) -> JobId {
+    let (port, chan) = Chan::new();
+    let id = with_jobs(|jobs| {
+        let id = jobs.next_id;
+        jobs.next_id += 1;
+        jobs.pending.push((id, port));
+        id
+    });
+    do spawn {
+        let mut p = p;
+        chan.send(p.finish_with_output());
+    }
+    JobId(id)
+}
+
+/**
+ * Waits for the job `job` to finish, and returns its output.
+ *
+ * This may be called from any task, but the output of a job can only be
+ * collected once.
+ *
+ * # Return value
+ *
+ * The output of the job, or None if no such job was started or it was already
+ * collected.
+ */
+pub fn collect(job: JobId) -> Option<ProcessOutput> {
+    let JobId(id) = job;
+    let port = with_jobs(|jobs| {
+        match jobs.pending.iter().position(|&(other, _)| other == id) {
+            Some(i) => Some(jobs.pending.swap_remove(i)),
+            None => None,
+        }
+    });
+    // Receive outside of the lock, as this may block for a while.
+    port.map(|(_, port)| port.recv())
+}
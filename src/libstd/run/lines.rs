@@ -0,0 +1,82 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading the output of a child line by line
+
+use io::{Reader, Buffer, BufferedReader};
+use prelude::*;
+use str;
+
+/// An iterator over the lines of a stream, such as the progress reported by
+/// a long-running child, which yields each line as soon as it is complete.
+///
+/// The lines are returned without their line ending, which is either "\n"
+/// or "\r\n". The last line is returned even if the stream does not end
+/// with a line ending. A line which is not valid UTF-8 has each of its
+/// non-ASCII bytes replaced with U+FFFD.
+pub struct OutputLines<R> {
+    priv inner: BufferedReader<R>,
+}
+
+impl<R: Reader> OutputLines<R> {
+    /// Creates a new iterator over the lines of `inner`.
+    pub fn new(inner: R) -> OutputLines<R> {
+        OutputLines { inner: BufferedReader::new(inner) }
+    }
+
+    /// Returns the wrapped stream, dropping whatever was buffered from it
+    /// but not returned yet.
+    pub fn unwrap(self) -> R {
+        self.inner.unwrap()
+    }
+}
+
+impl<R: Reader> Iterator<~str> for OutputLines<R> {
+    fn next(&mut self) -> Option<~str> {
+        let mut line = match self.inner.read_until('\n' as u8) {
+            Some(line) => line,
+            None => return None,
+        };
+        if line.last() == Some(&('\n' as u8)) {
+            line.pop();
+            if line.last() == Some(&('\r' as u8)) {
+                line.pop();
+            }
+        }
+        match str::from_utf8_owned(line.clone()) {
+            Some(line) => Some(line),
+            None => Some(line.iter().map(|&b| if b < 0x80 { b as char } else { '\uFFFD' })
+                             .collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::util::ChainedReader;
+    use io::mem::MemReader;
+    use super::OutputLines;
+
+    #[test]
+    fn test_output_lines() {
+        // A line split across reads is only returned once it is complete.
+        let pieces = ~[MemReader::new(bytes!("one\ntw").to_owned()),
+                       MemReader::new(bytes!("o\r\n\nthree").to_owned())];
+        let lines: ~[~str] = OutputLines::new(ChainedReader::new(pieces.move_iter())).collect();
+        assert_eq!(lines, ~[~"one", ~"two", ~"", ~"three"]);
+
+        let invalid = MemReader::new(~[0x61, 0xff, 0x62, 0x0a]);
+        let lines: ~[~str] = OutputLines::new(invalid).collect();
+        assert_eq!(lines, ~[~"a\uFFFDb"]);
+
+        assert_eq!(OutputLines::new(MemReader::new(~[])).len(), 0);
+    }
+}
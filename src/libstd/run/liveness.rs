@@ -0,0 +1,159 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checking whether a process is still running
+//!
+//! A health check only needs to know whether a child is still there, and
+//! must not collect its exit status, which belongs to whoever will finish
+//! the child. The check is therefore made by id, without waiting for the
+//! child, so it can be made from any task.
+
+use libc::pid_t;
+
+#[cfg(unix)] use libc;
+
+#[cfg(target_os = "linux")] use io::File;
+#[cfg(target_os = "linux")] use io;
+#[cfg(target_os = "linux")] #[cfg(windows)] use prelude::*;
+#[cfg(target_os = "linux")] use str;
+
+/**
+ * Returns whether the process `pid` is running, without waiting for it.
+ *
+ * On unix this sends it the null signal, which fails once the process is
+ * gone. A child of the current process which has exited but has not been
+ * reaped yet is recognized as such with `waitid`, which leaves it to be
+ * reaped. Any other process which has exited is recognized as a zombie on
+ * Linux, and reported as running until it is reaped elsewhere. On Windows
+ * the process is running until its handle is signaled.
+ *
+ * Note that the id of a process which has been reaped may be reused by an
+ * unrelated process.
+ */
+#[cfg(unix)]
+pub fn is_alive(pid: pid_t) -> bool {
+    use libc::funcs::posix88::signal::kill;
+    use os;
+
+    // A process which may not be signaled by this one still exists.
+    let exists = unsafe { kill(pid, 0) == 0 } || os::errno() == libc::EPERM as int;
+    exists && !is_zombie(pid)
+}
+
+#[cfg(windows)]
+pub fn is_alive(pid: pid_t) -> bool {
+    use libc::types::os::arch::extra::DWORD;
+    use libc::consts::os::extra::{FALSE, SYNCHRONIZE, WAIT_TIMEOUT};
+    use libc::funcs::extra::kernel32::{OpenProcess, WaitForSingleObject, CloseHandle};
+
+    unsafe {
+        let handle = OpenProcess(SYNCHRONIZE, FALSE, pid as DWORD);
+        if handle.is_null() {
+            return false;
+        }
+        let running = WaitForSingleObject(handle, 0) == WAIT_TIMEOUT;
+        CloseHandle(handle);
+        running
+    }
+}
+
+/// Returns whether the process `pid` has exited but has not been reaped.
+#[cfg(unix)]
+fn is_zombie(pid: pid_t) -> bool {
+    match exited_child(pid) {
+        Some(exited) => exited,
+        None => is_other_zombie(pid),
+    }
+}
+
+/// Returns whether the child `pid` of the current process has exited, or
+/// None if it is not a child of the current process.
+#[cfg(unix)]
+fn exited_child(pid: pid_t) -> Option<bool> {
+    use libc::c_int;
+    use unstable::intrinsics;
+    use self::consts::{id_t, P_PID, WEXITED, WNOHANG, WNOWAIT};
+
+    // Only the signal number, which comes first, is looked at, and the rest
+    // is larger than the siginfo_t of any of the systems.
+    struct SigInfo {
+        si_signo: c_int,
+        _rest: [c_int, ..63],
+    }
+
+    extern {
+        fn waitid(idtype: c_int, id: id_t, infop: *mut SigInfo, options: c_int) -> c_int;
+    }
+
+    // Without WNOWAIT the exit status would be collected here, and with
+    // WNOHANG nothing is filled in while the child is running.
+    let mut info: SigInfo = unsafe { intrinsics::init() };
+    let ret = unsafe {
+        waitid(P_PID, pid as id_t, &mut info, WEXITED | WNOHANG | WNOWAIT)
+    };
+    if ret == 0 {
+        Some(info.si_signo == libc::SIGCHLD)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+mod consts {
+    use libc::c_int;
+    #[allow(non_camel_case_types)] pub type id_t = u32;
+    pub static P_PID: c_int = 1;
+    pub static WEXITED: c_int = 4;
+    pub static WNOHANG: c_int = 1;
+    pub static WNOWAIT: c_int = 0x01000000;
+}
+
+#[cfg(target_os = "macos")]
+mod consts {
+    use libc::c_int;
+    #[allow(non_camel_case_types)] pub type id_t = u32;
+    pub static P_PID: c_int = 1;
+    pub static WEXITED: c_int = 4;
+    pub static WNOHANG: c_int = 1;
+    pub static WNOWAIT: c_int = 0x20;
+}
+
+#[cfg(target_os = "freebsd")]
+mod consts {
+    use libc::c_int;
+    #[allow(non_camel_case_types)] pub type id_t = i64;
+    pub static P_PID: c_int = 0;
+    pub static WEXITED: c_int = 16;
+    pub static WNOHANG: c_int = 1;
+    pub static WNOWAIT: c_int = 8;
+}
+
+/// Returns whether the process `pid`, which is not a child of the current
+/// process, has exited but has not been reaped, as the state following its
+/// name in `/proc/<pid>/stat` says.
+#[cfg(target_os = "linux")]
+fn is_other_zombie(pid: pid_t) -> bool {
+    let _guard = io::ignore_io_error();
+    let stat = match File::open(&Path::new(format!("/proc/{}/stat", pid))) {
+        Some(mut f) => f.read_to_end(),
+        None => return false,
+    };
+    match str::from_utf8_owned(stat) {
+        Some(stat) => match stat.rfind(')') {
+            Some(i) => stat.slice_from(i + 1).words().next() == Some("Z"),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+#[cfg(unix, not(target_os = "linux"))]
+fn is_other_zombie(_pid: pid_t) -> bool { false }
@@ -0,0 +1,3992 @@
+// Copyright 2012-2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Utilities for spawning and managing processes
+
+#[allow(missing_doc)];
+
+use comm::SharedChan;
+use io::Reader;
+use io::fs;
+use io::process;
+use io;
+use libc::{pid_t, c_int};
+use libc;
+use logging;
+use os;
+use prelude::*;
+use rt;
+use str;
+use util;
+
+use self::capture::Captured;
+use self::tempcwd::TempCwd;
+
+pub use io::process::{ConsoleMode, InheritConsole, NewConsole, NoConsole, HiddenConsole};
+pub use io::process::{ArgQuoting, AutoQuoting, CrtQuoting, BatchQuoting};
+pub use io::process::{DropPolicy, WaitOnDrop, KillOnDrop, DetachOnDrop};
+pub use io::process::{ProcessExit, ExitStatus, ExitSignal};
+pub use io::process::ResourceUsage;
+pub use io::process::{JobLimits, Mitigations};
+pub use io::process::{SpawnAttributes, FileAction, DupFd, CloseFd};
+pub use io::process::{ProcessControls, Capabilities, FsIsolation, Namespaces};
+pub use io::process::{Resource, CpuTime, FileSize, DataSize, StackSize, CoreSize};
+pub use io::process::{AddressSpace, LockedMemory, OpenFiles, Processes};
+pub use self::ansi::AnsiFilter;
+pub use self::askpass::{AskPass, AskPassReader, default_password_prompts};
+pub use self::broadcast::{BroadcastWriter, SlowConsumerPolicy, WaitForSlowest, DropSlowerThan};
+pub use self::capture::{CaptureMode, CaptureAll, CaptureTail, CaptureHeadTail, CaptureToFile};
+pub use self::capture::CapturedFile;
+pub use self::chunks::Chunks;
+pub use self::codepage::{OutputEncoding, Untranscoded, ConsoleCodePage, CodePage};
+pub use self::command::{Command, Chain, ChainStatus, Pipeline, PipelineStatus, sequence};
+pub use self::digest::{Crc32, DigestReader};
+pub use self::env::{EnvChange, EnvAdded, EnvChanged, EnvRemoved};
+pub use self::exit::{ExitClass, ExitSuccess, ExitRetryable, ExitFatal};
+pub use self::exit::{ExitRule, ExitCode, ExitRange, ExitMask};
+pub use self::exit::{register_exit_codes, unregister_exit_codes, classify_exit};
+pub use self::fanin::FanInReader;
+pub use self::handoff::ProcessHandoff;
+pub use self::job::{JobId, collect};
+pub use self::lines::OutputLines;
+pub use self::liveness::is_alive;
+pub use self::mux::{Multiplexer, MuxEvent, MuxData, MuxEnd};
+pub use self::mux::{ChildStream, ChildStdout, ChildStderr};
+pub use self::progress::{ProgressEvent, ProgressWatcher, ProgressReader};
+pub use self::redirect::{Redirect, RedirectNull, RedirectTruncate, RedirectAppend};
+pub use self::select::wait_any;
+pub use self::shell::{Shell, default_shell, shell, shell_output};
+pub use self::supervisor::{Supervisor, ChildSpec, RestartPolicy};
+pub use self::supervisor::{RestartAlways, RestartOnFailure, RestartNever};
+pub use self::supervisor::{SupervisorEvent, ChildStarted, ChildSpawnFailed, ChildExited};
+pub use self::supervisor::ChildRetired;
+pub use self::timeout::TimeoutReader;
+pub use self::trace::{ProcessSpan, enable_tracing, process_trace, clear_trace};
+pub use self::trace::write_chrome_trace;
+pub use self::validate::{SpawnProblem, ProgramNotFound, ProgramNotExecutable};
+pub use self::validate::{CwdNotFound, CwdNotDirectory, MalformedEnvVar, BadFd};
+pub use self::validate::{ProgramSearch, SearchEnvPath, RequirePath, SearchDirs};
+
+#[cfg(unix)]
+pub mod unix;
+#[cfg(windows)]
+pub mod windows;
+
+mod ansi;
+mod askpass;
+mod broadcast;
+mod capture;
+mod chunks;
+mod clock;
+mod codepage;
+mod digest;
+mod command;
+mod env;
+mod escalate;
+mod exit;
+mod fanin;
+mod handoff;
+mod job;
+mod lines;
+mod liveness;
+mod mux;
+mod progress;
+mod redirect;
+mod select;
+mod shell;
+mod supervisor;
+mod suspend;
+mod tempcwd;
+mod timeout;
+mod trace;
+mod tree;
+mod validate;
+mod watch;
+
+/**
+ * A value representing a child process.
+ *
+ * The lifetime of this value is linked to the lifetime of the actual
+ * process - the Process destructor calls self.finish() which waits
+ * for the process to terminate.
+ */
+pub struct Process {
+    priv inner: process::Process,
+    priv program: ~str,
+    priv color: ColorMode,
+    priv collapse_progress: bool,
+    priv progress: Option<ProgressWatcher>,
+    priv encoding: OutputEncoding,
+    priv capture: CaptureMode,
+    priv digest: bool,
+    priv suspend: Option<Chan<()>>,
+    priv temp_cwd: Option<TempCwd>,
+    priv watched: Option<Port<~[u8]>>,
+    priv trace: Option<uint>,
+    priv destroy_signal: int,
+    priv destroy_escalation: ~[(u64, int)],
+    priv force_destroy_signal: int,
+    priv pty: Option<io::PipeStream>,
+    priv spawn_time_ms: u64,
+    priv start_ns: u64,
+    priv end_ns: Option<u64>,
+}
+
+/// Describes whether a child should produce colored output.
+#[deriving(Eq)]
+pub enum ColorMode {
+    /// Leave the decision to the child, which usually disables colors when its
+    /// output is not a terminal.
+    ColorAuto,
+
+    /// Ask the child to produce colored output even though its output is
+    /// captured, by setting `CLICOLOR_FORCE` and `FORCE_COLOR` in its
+    /// environment.
+    ColorAlways,
+
+    /// Ask the child not to produce colored output by setting `NO_COLOR` and
+    /// `TERM=dumb` in its environment, and strip any escape sequences it
+    /// emits regardless from the output returned by `finish_with_output`.
+    ColorNever,
+}
+
+/// The file mode creation mask of a reproducible child, which leaves the files
+/// it creates writable by their owner only.
+static REPRODUCIBLE_UMASK: libc::mode_t = (io::GroupWrite | io::OtherWrite) as libc::mode_t;
+
+/// Options that can be given when starting a Process.
+///
+/// The options which do not work the same on every platform are private,
+/// and are set with the `ProcessOptionsExt` traits of `run::unix` and
+/// `run::windows` instead.
+pub struct ProcessOptions<'a> {
+    /**
+     * If this is None then the new process will have the same initial
+     * environment as the parent process.
+     *
+     * If this is Some(vec-of-names-and-values) then the new process will
+     * have an environment containing the given named values only.
+     */
+    env: Option<~[(~str, ~str)]>,
+
+    /**
+     * If this is None then the new process will use the same initial working
+     * directory as the parent process.
+     *
+     * If this is Some(path) then the new process will use the given path
+     * for its initial working directory.
+     */
+    dir: Option<&'a Path>,
+
+    /**
+     * If this is None then a new pipe will be created for the new process's
+     * input and Process.input() will provide a Writer to write to this pipe.
+     *
+     * If this is Some(file-descriptor) then the new process will read its input
+     * from the given file descriptor, Process.input_redirected() will return
+     * true, and Process.input() will fail.
+     */
+    in_fd: Option<c_int>,
+
+    /**
+     * If this is None then a new pipe will be created for the new program's
+     * output and Process.output() will provide a Reader to read from this pipe.
+     *
+     * If this is Some(file-descriptor) then the new process will write its output
+     * to the given file descriptor, Process.output_redirected() will return
+     * true, and Process.output() will fail.
+     */
+    out_fd: Option<c_int>,
+
+    /**
+     * If this is None then a new pipe will be created for the new program's
+     * error stream and Process.error() will provide a Reader to read from this pipe.
+     *
+     * If this is Some(file-descriptor) then the new process will write its error output
+     * to the given file descriptor, Process.error_redirected() will return true, and
+     * and Process.error() will fail.
+     */
+    err_fd: Option<c_int>,
+
+    /**
+     * If this is Some(redirect) and `in_fd` is None then the new process
+     * reads its input from where `redirect` says, as if it was given the
+     * descriptor of it in `in_fd`, which is opened and closed again by the
+     * spawn. A file is read from its start, and is neither created nor
+     * truncated.
+     */
+    in_redirect: Option<Redirect>,
+
+    /**
+     * If this is Some(redirect) and `out_fd` is None then the new process
+     * writes its output to where `redirect` says, for example to the null
+     * device to discard it or straight to a log file, as if it was given the
+     * descriptor of it in `out_fd`.
+     */
+    out_redirect: Option<Redirect>,
+
+    /**
+     * If this is Some(redirect) and `err_fd` is None then the new process
+     * writes its error output to where `redirect` says, as if it was given
+     * the descriptor of it in `err_fd`.
+     */
+    err_redirect: Option<Redirect>,
+
+    /**
+     * If this is true then the error output of the new process goes to the
+     * same place as its output, as with `2>&1`, unless it is given its own in
+     * `err_fd` or `err_redirect`. When the output is captured, both are read
+     * from a single pipe by `output()` and `finish_with_output`, interleaved
+     * in the order in which the process wrote them.
+     */
+    stderr_to_stdout: bool,
+
+    /**
+     * If this is Some(bytes) and the input of the new process is a pipe,
+     * then `bytes` are written to it by a task of its own, which closes it
+     * afterwards. The process can meanwhile write output without waiting
+     * for it to be read, so that finishing it with `finish_with_output`
+     * cannot deadlock, and `input()` fails.
+     */
+    input_bytes: Option<~[u8]>,
+
+    /**
+     * If this is Some(line) then on Windows the new process is given `line`
+     * as its command line verbatim, starting with the program, instead of
+     * one joined from the program and the arguments with the quoting of the
+     * C runtime, which `cmd.exe`, MSBuild and other programs parsing their
+     * own command line do not understand. The arguments should then be
+     * empty. This is ignored on other platforms.
+     */
+    priv raw_command_line: Option<~str>,
+
+    /**
+     * How the program and the arguments are quoted in the command line of
+     * the new process on Windows. By default batch files, which `cmd.exe`
+     * runs, are given arguments quoted so that spaces, carets and the like
+     * reach them intact, and other programs arguments quoted as the C
+     * runtime expects. This is ignored on other platforms.
+     */
+    priv quoting: ArgQuoting,
+
+    /**
+     * If this is true, as it is by default, then on Unix the new process
+     * starts with the default action for every signal and no signal blocked,
+     * rather than ignoring the signals the current process ignores, like
+     * SIGPIPE, and blocking those it blocks, which breaks programs expecting
+     * the defaults. This is ignored on Windows, and in green tasks, where
+     * the new process keeps the signal handling of the current process
+     * whatever this says.
+     */
+    priv reset_signals: bool,
+
+    /**
+     * If this is true then on Unix the new process is spawned with `vfork`,
+     * which is much faster than forking for a process with a large heap, but
+     * only supports setting up the standard streams and the environment of
+     * the new process: spawning it fails if any other option needing setup
+     * in it is given, such as `dir`, `pass_fds`, `uid`, `umask`, `pty` or
+     * `detached`. This is only supported in native tasks, and ignored on
+     * Windows.
+     */
+    priv vfork: bool,
+
+    /**
+     * Names of variables to strip from the environment of the new process
+     * before it is spawned, in which a `*` matches any sequence of
+     * characters (e.g. `*_TOKEN`, `AWS_*` or `SSH_AUTH_SOCK`).
+     *
+     * This applies to the inherited environment if `env` is None, and to the
+     * given environment otherwise.
+     */
+    env_deny: ~[~str],
+
+    /**
+     * If this is true then the new process starts from an empty environment
+     * instead of inheriting the environment of the parent, before
+     * `env_remove` and `env_set` are applied. It has no effect if `env` is
+     * given.
+     */
+    env_clear: bool,
+
+    /**
+     * Names of variables to remove from the environment of the new process,
+     * so that it can inherit everything else without the caller taking a
+     * snapshot of `os::env()`.
+     */
+    env_remove: ~[~str],
+
+    /**
+     * Variables to set in the environment of the new process, replacing the
+     * values it would otherwise have. These are set after `env_remove` and
+     * `env_deny` are applied, so they always reach the new process. On
+     * Windows names are compared regardless of their case, here as in the
+     * other options, so that setting `Path` replaces `PATH`.
+     */
+    env_set: ~[(~str, ~str)],
+
+    /**
+     * Whether the new process should produce colored output. The default of
+     * ColorAuto leaves the environment and the output of the process alone.
+     */
+    color: ColorMode,
+
+    /**
+     * If this is true then carriage-return progress updates are collapsed in
+     * the output returned by `finish_with_output`, so that only the final
+     * update of each progress line is kept. See `AnsiFilter` for details.
+     */
+    collapse_progress: bool,
+
+    /**
+     * If this is Some(watcher) then every line of output read by
+     * `finish_with_output`, on both stdout and stderr, is matched against the
+     * patterns of the watcher as it arrives, and the resulting progress
+     * events are sent to the watcher's port.
+     */
+    progress: Option<ProgressWatcher>,
+
+    /**
+     * If this is true then the new process is stopped whenever the current
+     * process is suspended from the terminal with Ctrl-Z (SIGTSTP), and
+     * continued when the current process is. This is what an interactive
+     * wrapper around a foreground child wants, as otherwise a child in
+     * another process group keeps running after the shell reports the job
+     * as stopped.
+     *
+     * This is only supported on Unix, and is ignored elsewhere. Note that
+     * SIGTSTP stops the current process through a handler while the child
+     * is running, rather than through the default action.
+     */
+    priv follow_suspend: bool,
+
+    /**
+     * Which console the new process is attached to on Windows: the console
+     * of the current process (InheritConsole, the default), a new one
+     * (NewConsole), a new one whose window is never shown (HiddenConsole),
+     * or none at all (NoConsole). This is ignored on other platforms.
+     */
+    priv console: ConsoleMode,
+
+    /**
+     * Further process creation flags which are or'ed on Windows into the
+     * ones implied by the other options, for the flags of `CreateProcess`
+     * which have no option of their own. This is ignored on other
+     * platforms.
+     */
+    priv creation_flags: u32,
+
+    /**
+     * If this is Some(limits) then on Windows the new process is placed in a
+     * Job Object which enforces the given memory, process count and CPU rate
+     * limits on it and on every process it starts in turn. With
+     * `kill_on_close`, the whole tree of processes is terminated by `destroy`
+     * and when the `Process` is dropped, rather than only the new process.
+     * This is ignored on other platforms.
+     */
+    priv job_limits: Option<JobLimits>,
+
+    /**
+     * If this is true then on Windows the new process breaks away from the
+     * Job Object the current process is in, if any, which CI agents and
+     * service managers commonly use. This needs the job to allow breakaway,
+     * and lets `job_limits` work for a current process which is in a job
+     * itself. This is ignored on other platforms.
+     */
+    priv breakaway_from_job: bool,
+
+    /**
+     * How the output of the new process is encoded. If this is not the
+     * default of Untranscoded then on Windows the output returned by
+     * `finish_with_output` is transcoded to UTF-8 from the given code page,
+     * which is what console programs such as `dir` or `ipconfig` write in.
+     */
+    priv output_encoding: OutputEncoding,
+
+    /**
+     * If this is Some(desktop) then on Windows the new process is started on
+     * the given window station and desktop, such as `winsta0\default` for
+     * the desktop of the interactive user, which services need in order to
+     * show anything to the user. This is ignored on other platforms.
+     */
+    priv desktop: Option<~str>,
+
+    /**
+     * Security descriptors, in the security descriptor definition language
+     * (SDDL), for the handles of the new process and of its main thread on
+     * Windows. These are ignored on other platforms.
+     */
+    priv process_security: Option<~str>,
+    priv thread_security: Option<~str>,
+
+    /**
+     * The mitigation policies (data execution prevention, forced address
+     * space layout randomization, no child processes) the new process is
+     * started with on Windows, for hardening helpers which handle untrusted
+     * input. This is ignored on other platforms.
+     */
+    priv mitigations: Mitigations,
+
+    /**
+     * If this is Some(attributes) then on Unix the given `posix_spawn`-style
+     * attributes (default signal dispositions, signal mask, process group,
+     * suspended start and file actions) are applied to the new process
+     * before it executes the program. This is ignored on other platforms.
+     */
+    priv spawn_attributes: Option<SpawnAttributes>,
+
+    /**
+     * If this is Some(profile) then on OS X the given sandbox profile, in the
+     * same language as the profiles of `sandbox-exec`, is applied to the new
+     * process before it executes the program, restricting the files and the
+     * network it can access. This is ignored on other platforms.
+     */
+    priv sandbox_profile: Option<~str>,
+
+    /**
+     * If this is Some(controls) then on FreeBSD the given process controls
+     * (reaper status, protection from being killed when out of swap, a
+     * signal on the exit of the current process) are applied with
+     * `procctl`. This is ignored on other platforms.
+     */
+    priv process_controls: Option<ProcessControls>,
+
+    /**
+     * If this is Some(signal) then on Linux and FreeBSD the signal is
+     * delivered to the new process when the current process exits, so that
+     * a helper does not outlive a supervisor which was killed. Spawning the
+     * process raises the `io_error` condition with IoUnavailable on other
+     * platforms.
+     */
+    priv parent_death_signal: Option<int>,
+
+    /**
+     * If this is Some(capabilities) then on Linux the capability sets of the
+     * new process are reduced to the capabilities kept, and its bounding set
+     * too if asked, before it executes the program. This is ignored on other
+     * platforms.
+     */
+    priv capabilities: Option<Capabilities>,
+
+    /**
+     * If this is true then on Linux the new process and everything it runs
+     * are barred from gaining privileges, by executing setuid programs or
+     * programs with file capabilities, as with `PR_SET_NO_NEW_PRIVS`. This
+     * is also needed for installing a seccomp filter without privileges. This
+     * is ignored on other platforms.
+     */
+    priv no_new_privs: bool,
+
+    /**
+     * If this is Some(isolation) then on Linux the new process gets a mount
+     * namespace of its own, with a private `/tmp` and read-only directories
+     * as described, for build sandboxes which keep commands from touching
+     * what they should not. A working directory in `/tmp`, such as the one of
+     * `temp_cwd`, is hidden by a private `/tmp`. This is ignored on other
+     * platforms.
+     */
+    priv fs_isolation: Option<FsIsolation>,
+
+    /**
+     * If this is true then the new process is cut off from the network, so
+     * that a hermetic build or test runner can guarantee that a command did
+     * not access it. On Linux the process gets an empty network namespace of
+     * its own, and on OS X a sandbox profile denying network access is
+     * applied to it, after the one of `sandbox_profile` if any. This is not
+     * supported on other platforms, where spawning the process raises the
+     * `io_error` condition with IoUnavailable instead.
+     */
+    priv no_network: bool,
+
+    /**
+     * The namespaces of its own the new process is created in on Linux, for
+     * sandboxes which would otherwise shell out to `unshare`. They work
+     * without privileges where unprivileged user namespaces are allowed.
+     * With a pid namespace, `get_id` returns the id of a process which runs
+     * the program as its child and exits like it, while signals are sent to
+     * the program itself, which only receives those it handles besides
+     * SIGKILL and SIGSTOP. Asking for any namespaces is not supported on
+     * other platforms, where spawning the process raises the `io_error`
+     * condition with IoUnavailable instead.
+     */
+    priv namespaces: Namespaces,
+
+    /**
+     * If this is true then the new process runs in a reproducible setting,
+     * so that build systems can execute commands hermetically and cache
+     * their results safely: its environment is scrubbed down to `PATH` and
+     * `HOME` (and `SystemRoot` and `TEMP` on Windows) with `LANG=C`,
+     * `LC_ALL=C` and `TZ=UTC`, its file mode creation mask is 022 on Unix,
+     * and it must be given its working directory in `dir` or `temp_cwd`
+     * instead of inheriting it. The other options still apply on top, and
+     * the child never inherits file descriptors beyond its standard streams.
+     */
+    reproducible: bool,
+
+    /**
+     * If this is true then the `${VAR}` references in the arguments are
+     * expanded to the values of the variables in the environment of the new
+     * process, without involving a shell. A `$$` stands for a single `$`.
+     * Referring to a variable which is not set raises the `io_error`
+     * condition with InvalidInput instead of spawning the process.
+     */
+    expand_env: bool,
+
+    /**
+     * If this is true then the working directory given in `dir` is created,
+     * along with any missing parent directories, if it does not exist yet.
+     * Failing to create it raises the `io_error` condition instead of
+     * spawning the process.
+     */
+    create_cwd: bool,
+
+    /**
+     * If this is true then the new process runs in a freshly created
+     * temporary directory instead of in `dir`. The directory is deleted with
+     * everything in it once the process has been finished, or when the
+     * `Process` is dropped.
+     */
+    temp_cwd: bool,
+
+    /**
+     * If this is true then the temporary directory of `temp_cwd` is kept when
+     * the process does not exit successfully, so that what it left behind
+     * can be inspected.
+     */
+    keep_temp_cwd_on_failure: bool,
+
+    /**
+     * How much of each output stream `finish_with_output` keeps. Output
+     * beyond that is read and discarded, and only counted in the
+     * `ProcessOutput`.
+     */
+    capture: CaptureMode,
+
+    /**
+     * If this is true then `finish_with_output` computes the CRC-32 checksum
+     * of each output stream as the process wrote it, while the stream is
+     * read, so that the output can be fingerprinted without keeping all of
+     * it.
+     */
+    digest_output: bool,
+
+    /**
+     * The signal sent to the process by `destroy`, SIGTERM by default. Some
+     * programs only clean up after SIGINT, as sent by Ctrl-C, and JVMs dump
+     * their threads on SIGQUIT.
+     *
+     * On Windows only `PleaseExitSignal` and `MustDieSignal` are supported,
+     * and both terminate the process.
+     */
+    destroy_signal: int,
+
+    /**
+     * Further signals `destroy` sends to the process if it does not exit,
+     * each after waiting the given number of milliseconds, such as
+     * `~[(5000, MustDieSignal)]` to kill it once it has had five seconds to
+     * clean up. This is empty by default, in which case `destroy` waits for
+     * the process for as long as it takes. It has no effect on Windows.
+     */
+    destroy_escalation: ~[(u64, int)],
+
+    /// The signal sent to the process by `force_destroy`, SIGKILL by default.
+    force_destroy_signal: int,
+
+    /**
+     * If this is Some(uid) then the new process runs as the user `uid`, so
+     * that a server running as root can spawn its workers unprivileged.
+     * When the user is changed, the supplementary groups of the process are
+     * dropped as well. Spawning the process fails on Windows.
+     */
+    priv uid: Option<uint>,
+
+    /**
+     * If this is Some(gid) then the new process runs with the group `gid`.
+     * Spawning the process fails on Windows.
+     */
+    priv gid: Option<uint>,
+
+    /**
+     * If this is true then the new process is put into a process group of
+     * its own, so that signals sent by the terminal to the foreground
+     * process group, such as SIGINT for Ctrl-C, do not reach it.
+     */
+    new_process_group: bool,
+
+    /**
+     * If this is true then on Unix the new process starts a new session, as
+     * with `setsid`, which also puts it into a process group of its own and
+     * detaches it from the controlling terminal. On Windows this is the same
+     * as `new_process_group`.
+     */
+    new_session: bool,
+
+    /**
+     * If this is true then on Unix the new process never gets the controlling
+     * terminal of the current one, so that a daemon is not killed when the
+     * terminal hangs up and cannot read from it: it starts a new session,
+     * and gets the null device as its standard input if it would otherwise
+     * inherit a terminal. This is ignored on Windows.
+     */
+    priv no_ctty: bool,
+
+    /**
+     * If this is true then the new process is disassociated from the current
+     * one as a daemon would be: it starts a new session without a
+     * controlling terminal (or on Windows runs without a console), the
+     * standard streams which are not redirected are connected to the null
+     * device instead of to pipes, and dropping the `Process` does not wait
+     * for it. The process can still be finished explicitly.
+     */
+    detached: bool,
+
+    /**
+     * What dropping the `Process` does to the child if it has not been
+     * finished yet: by default it waits for the child to exit, which blocks
+     * for as long as the child runs, but it can also kill the child or leave
+     * it running, in which case it is reaped in the background once it
+     * exits. A detached process is left running unless this asks for it to
+     * be killed.
+     */
+    drop_policy: DropPolicy,
+
+    /**
+     * If this is true then the standard streams which are not redirected are
+     * connected to a new pseudo-terminal instead of to pipes, for programs
+     * such as ssh and sudo which behave differently when they do not talk to
+     * a terminal. The process runs in a session of its own with the terminal
+     * as its controlling terminal, and the master side of the terminal,
+     * returned by `pty`, is both where its output is read and where its input
+     * is written. This is only supported in native tasks on Unix.
+     */
+    priv pty: bool,
+
+    /**
+     * If this is Some(hook) then on Unix the hook is called in the new
+     * process between the fork and the exec, once all of the other options
+     * have been applied, for setup which they do not cover. If it returns
+     * false, the process is not spawned and the `io_error` condition is
+     * raised with the error of the value the hook left in `errno`.
+     *
+     * The hook runs in a copy of the current process in which only the
+     * thread which spawned it survived, with any lock another thread held
+     * still taken. It must therefore only call functions which are
+     * async-signal-safe: it must not allocate, fail, log, or use anything of
+     * the runtime, including the `io` module. Raw system calls through
+     * `libc` are fine.
+     *
+     * This is only supported in native tasks on Unix.
+     */
+    priv before_exec: Option<'a || -> bool>,
+
+    /**
+     * Limits on the resources of the new process, as the resource with its
+     * soft and hard limit, so that a runaway child is contained without a
+     * wrapper script calling `ulimit`. A limit of `u64::MAX` stands for no
+     * limit. Only the superuser can raise a hard limit.
+     *
+     * Spawning the process fails on Windows if any limits are given. Job
+     * Objects limit the memory and CPU time of processes there instead, as
+     * set with `job_limits`.
+     */
+    priv rlimits: ~[(Resource, u64, u64)],
+
+    /**
+     * If this is Some(nice) then the new process runs with the scheduling
+     * priority `nice`, from -20 for the most favorable to 19 for the least,
+     * so that batch jobs do not compete with interactive work. Only the
+     * superuser can give a process a better priority than its own.
+     *
+     * On Windows the niceness selects the closest priority class: 15 and
+     * above is idle, 5 and above below normal, -5 and below above normal, and
+     * -15 and below high.
+     */
+    priv priority: Option<int>,
+
+    /**
+     * If this is Some(cpus) then the new process may only run on the CPUs
+     * with the given numbers, starting at 0, as benchmark harnesses need for
+     * reproducible timings. Its children inherit the restriction.
+     *
+     * This is only supported in native tasks, on Linux and Windows. CPUs
+     * beyond the 1024th on Linux, or beyond the number of bits of a `uint`
+     * on Windows, are ignored.
+     */
+    priv cpu_affinity: Option<~[uint]>,
+
+    /**
+     * If this is Some(dir) then on Unix the new process runs with `dir` as
+     * its root directory, as with `chroot`, so that together with `uid` and
+     * `gid` a helper can be confined without a wrapper program. The program
+     * and `dir` are looked up inside of the new root, and the process starts
+     * at its top if it is given no `dir`. Changing the root directory needs
+     * privileges, and it is not a security boundary against a process which
+     * keeps them.
+     *
+     * This is only supported in native tasks. Spawning the process fails on
+     * Windows.
+     */
+    priv chroot: Option<Path>,
+
+    /**
+     * If this is Some(name) then the new process is given `name` as its
+     * `argv[0]` instead of the program it is spawned from, as multiplexing
+     * binaries like busybox and login shells (named with a leading `-`)
+     * expect. The program is still located from its own name, except on
+     * Windows, where it then has to be given as a path with its extension.
+     */
+    argv0: Option<~str>,
+
+    /**
+     * Where the program is looked for if it is not given as a path. By
+     * default it is searched for in the `PATH`, which a security-sensitive
+     * caller may not trust. With RequirePath a program which is not a path
+     * is not spawned, and with SearchDirs only the given directories are
+     * searched; the program is then resolved before it is spawned, and gets
+     * its name as given as its `argv[0]` unless `argv0` says otherwise.
+     */
+    program_search: ProgramSearch,
+
+    /**
+     * If this is Some(mask) then on Unix the file mode creation mask of the
+     * new process is set to `mask` between the fork and the exec, so that the
+     * current process does not have to change its own around the spawn,
+     * racing with the other tasks. It overrides the mask of a reproducible
+     * process. This is ignored on Windows, and not supported in green tasks,
+     * where spawning the process raises the `io_error` condition with
+     * IoUnavailable instead.
+     */
+    priv umask: Option<libc::mode_t>,
+
+    /**
+     * Descriptors of the current process which the new process is given
+     * under numbers of its own, as pairs of the descriptor and the number it
+     * gets in the new process, for example to hand listening sockets to
+     * pre-forked workers. The numbers have to be above those of the standard
+     * streams, which are given by `in_fd`, `out_fd` and `err_fd`, and those
+     * in between which are not given are closed in the new process. The
+     * descriptors stay open in the current process.
+     *
+     * This is not supported on Windows, where spawning the process raises
+     * the `io_error` condition with IoUnavailable.
+     */
+    priv pass_fds: ~[(c_int, c_int)],
+
+    /**
+     * If this is true then the new process inherits every descriptor of the
+     * current process which is not marked close-on-exec, as shells do, on
+     * top of those it is explicitly given. By default the others are closed
+     * in it, so that descriptors which happen not to be marked do not leak
+     * into it; a reproducible process never inherits them.
+     *
+     * Green tasks cannot close them, so there the new process inherits them
+     * whatever this says, and on Windows every inheritable handle is
+     * inherited either way.
+     */
+    inherit_fds: bool,
+}
+
+impl <'a> ProcessOptions<'a> {
+    /// Return a ProcessOptions that has None (or the default) in every field.
+    pub fn new<'a>() -> ProcessOptions<'a> {
+        ProcessOptions {
+            env: None,
+            dir: None,
+            in_fd: None,
+            out_fd: None,
+            err_fd: None,
+            in_redirect: None,
+            out_redirect: None,
+            err_redirect: None,
+            stderr_to_stdout: false,
+            input_bytes: None,
+            raw_command_line: None,
+            quoting: AutoQuoting,
+            reset_signals: true,
+            vfork: false,
+            env_deny: ~[],
+            env_clear: false,
+            env_remove: ~[],
+            env_set: ~[],
+            color: ColorAuto,
+            collapse_progress: false,
+            progress: None,
+            follow_suspend: false,
+            console: InheritConsole,
+            creation_flags: 0,
+            job_limits: None,
+            breakaway_from_job: false,
+            output_encoding: Untranscoded,
+            desktop: None,
+            process_security: None,
+            thread_security: None,
+            mitigations: Mitigations::new(),
+            spawn_attributes: None,
+            sandbox_profile: None,
+            process_controls: None,
+            parent_death_signal: None,
+            capabilities: None,
+            no_new_privs: false,
+            fs_isolation: None,
+            no_network: false,
+            namespaces: Namespaces::new(),
+            reproducible: false,
+            expand_env: false,
+            create_cwd: false,
+            temp_cwd: false,
+            keep_temp_cwd_on_failure: false,
+            capture: CaptureAll,
+            digest_output: false,
+            destroy_signal: process::PleaseExitSignal,
+            destroy_escalation: ~[],
+            force_destroy_signal: process::MustDieSignal,
+            uid: None,
+            gid: None,
+            new_process_group: false,
+            new_session: false,
+            no_ctty: false,
+            detached: false,
+            drop_policy: WaitOnDrop,
+            pty: false,
+            before_exec: None,
+            rlimits: ~[],
+            priority: None,
+            cpu_affinity: None,
+            chroot: None,
+            argv0: None,
+            program_search: SearchEnvPath,
+            umask: None,
+            pass_fds: ~[],
+            inherit_fds: false,
+        }
+    }
+
+    /**
+     * Checks that `prog` can be spawned with these options: that the program
+     * exists and is executable, that the working directory exists, that the
+     * environment is well formed and that the file descriptors to hand to
+     * the child are open.
+     *
+     * # Return value
+     *
+     * Every problem which was found, so that all of them can be reported at
+     * once. This is empty if the process can be spawned.
+     */
+    pub fn validate(&self, prog: &str) -> ~[SpawnProblem] {
+        let env = match self.child_env() {
+            Some(env) => env,
+            None => os::env(),
+        };
+        let mut problems = ~[];
+        match validate::check_program(prog, &self.program_search, env) {
+            Ok(..) => {}
+            Err(problem) => problems.push(problem),
+        }
+        if !self.create_cwd && !self.temp_cwd {
+            for dir in self.dir.iter() {
+                problems.push_all_move(validate::check_cwd(*dir).move_iter().collect());
+            }
+        }
+        problems.push_all_move(validate::check_env(env));
+        for fd in [self.in_fd, self.out_fd, self.err_fd].iter().filter_map(|fd| *fd) {
+            problems.push_all_move(validate::check_fd(fd).move_iter().collect());
+        }
+        problems
+    }
+
+    /**
+     * Returns how the environment of a child spawned with these options
+     * differs from the environment of the current process.
+     *
+     * Variables which are inherited unchanged are not reported, so this is
+     * empty if `env` is None and none of `env_clear`, `env_remove`,
+     * `env_set`, `env_deny`, `color` and `reproducible` changes the
+     * environment either.
+     */
+    pub fn env_diff(&self) -> ~[EnvChange] {
+        match self.child_env() {
+            Some(child) => env::diff(os::env(), child),
+            None => ~[],
+        }
+    }
+
+    /**
+     * Returns the environment a child spawned with these options gets, with
+     * the variables which have the same name merged into one, as `PATH` and
+     * `Path` are on Windows.
+     */
+    pub fn child_environment(&self) -> ~[(~str, ~str)] {
+        match self.child_env() {
+            Some(child) => child,
+            None => env::normalize(os::env()),
+        }
+    }
+
+    /// Returns the environment to hand to the new process, or None if it
+    /// should inherit the environment of the current process unchanged.
+    fn child_env(&self) -> Option<~[(~str, ~str)]> {
+        if self.env_deny.is_empty() && self.color == ColorAuto && !self.reproducible &&
+                !self.env_clear && self.env_remove.is_empty() && self.env_set.is_empty() {
+            return self.env.as_ref().map(|env| env::normalize(*env));
+        }
+        let mut child = match self.env {
+            Some(ref env) => env::normalize(*env),
+            None if self.env_clear => ~[],
+            None => env::normalize(os::env()),
+        };
+        if self.reproducible {
+            child = env::reproducible(child);
+        }
+        env::deny(&mut child, self.env_deny);
+        for key in self.env_remove.iter() {
+            env::remove(&mut child, *key);
+        }
+        for &(ref key, ref value) in self.env_set.iter() {
+            env::set(&mut child, *key, *value);
+        }
+        match self.color {
+            ColorAuto => {}
+            ColorAlways => {
+                env::set(&mut child, "CLICOLOR_FORCE", "1");
+                env::set(&mut child, "FORCE_COLOR", "1");
+            }
+            ColorNever => {
+                env::set(&mut child, "NO_COLOR", "1");
+                env::set(&mut child, "TERM", "dumb");
+            }
+        }
+        Some(child)
+    }
+}
+
+/// The output of a finished process.
+pub struct ProcessOutput {
+    /// The status (exit code) of the process.
+    status: ProcessExit,
+
+    /// The data that the process wrote to stdout.
+    output: ~[u8],
+
+    /// The data that the process wrote to stderr.
+    error: ~[u8],
+
+    /// The number of bytes of stdout and of stderr which were discarded
+    /// according to the `capture` mode of the process.
+    output_discarded: uint,
+    error_discarded: uint,
+
+    /// The CRC-32 checksums of the complete stdout and stderr of the process,
+    /// if it was spawned with `digest_output`.
+    output_digest: Option<u32>,
+    error_digest: Option<u32>,
+
+    /// The temporary files holding stdout and stderr, if the process was
+    /// spawned with `CaptureToFile` and the files could be created. The files
+    /// are deleted when these are dropped.
+    output_file: Option<CapturedFile>,
+    error_file: Option<CapturedFile>,
+
+    /// When the process was spawned, in milliseconds since the Unix epoch.
+    spawn_time_ms: u64,
+
+    /// How long the process ran, in nanoseconds, from when it was spawned
+    /// until it was found to have exited.
+    elapsed_ns: u64,
+
+    /// The CPU time, memory and page faults the process used, if these are
+    /// known, as returned by `finish_with_rusage`.
+    rusage: Option<ResourceUsage>,
+}
+
+impl Process {
+    /**
+     * Spawns a new Process.
+     *
+     * # Arguments
+     *
+     * * prog - The path to an executable.
+     * * args - Vector of arguments to pass to the child process.
+     * * options - Options to configure the environment of the process,
+     *             the working directory and the standard IO streams.
+     */
+    pub fn new(prog: &str, args: &[~str], options: ProcessOptions) -> Option<Process> {
+        if log_enabled!(logging::DEBUG) {
+            for change in options.env_diff().iter() {
+                debug!("spawning `{}` with env change {}", prog, *change);
+            }
+        }
+
+        let env = options.child_env();
+        for env in env.iter() {
+            // A malformed variable would silently turn into a different one
+            // in the environment block of the child.
+            match validate::check_env(*env).move_iter().next() {
+                Some(problem) => {
+                    io::io_error::cond.raise(io::IoError {
+                        kind: io::InvalidInput,
+                        desc: "malformed environment variable",
+                        detail: Some(format!("{}", problem)),
+                    });
+                    return None;
+                }
+                None => {}
+            }
+        }
+        // The program is only resolved here when the system would search the
+        // `PATH` for it otherwise.
+        let resolved = match options.program_search {
+            SearchEnvPath => None,
+            RequirePath if validate::is_path(prog) => None,
+            RequirePath => {
+                io::io_error::cond.raise(io::IoError {
+                    kind: io::InvalidInput,
+                    desc: "the program is not given as a path",
+                    detail: Some(prog.to_owned()),
+                });
+                return None;
+            }
+            SearchDirs(..) => {
+                let search_env = match env {
+                    Some(ref env) => env.clone(),
+                    None => os::env(),
+                };
+                match validate::find_program(prog, &options.program_search, search_env) {
+                    Some(path) => Some(path),
+                    None => {
+                        io::io_error::cond.raise(io::IoError {
+                            kind: io::FileNotFound,
+                            desc: "program not found",
+                            detail: Some(prog.to_owned()),
+                        });
+                        return None;
+                    }
+                }
+            }
+        };
+        for (i, &(_, dst)) in options.pass_fds.iter().enumerate() {
+            let taken = options.pass_fds.slice_to(i).iter().any(|&(_, d)| d == dst);
+            if dst < 3 || taken {
+                io::io_error::cond.raise(io::IoError {
+                    kind: io::InvalidInput,
+                    desc: "a descriptor cannot be passed as this number",
+                    detail: Some(format!("{}", dst)),
+                });
+                return None;
+            }
+        }
+        let expanded = if options.expand_env {
+            match expand_args(args, env.as_ref()) {
+                Some(args) => Some(args),
+                None => return None,
+            }
+        } else {
+            None
+        };
+        let args = match expanded {
+            Some(ref args) => args.as_slice(),
+            None => args,
+        };
+        if options.reproducible && options.dir.is_none() && !options.temp_cwd {
+            io::io_error::cond.raise(io::IoError {
+                kind: io::InvalidInput,
+                desc: "a reproducible process needs a working directory",
+                detail: None,
+            });
+            return None;
+        }
+        if options.create_cwd {
+            for dir in options.dir.iter() {
+                match io::result(|| fs::mkdir_recursive(*dir, io::UserDir)) {
+                    Ok(()) => {}
+                    Err(e) => {
+                        io::io_error::cond.raise(e);
+                        return None;
+                    }
+                }
+            }
+        }
+        let ProcessOptions {
+            dir, in_fd, out_fd, err_fd, color, collapse_progress, progress,
+            follow_suspend, console, creation_flags, job_limits, breakaway_from_job,
+            output_encoding, desktop, process_security, thread_security,
+            mitigations, spawn_attributes, sandbox_profile, process_controls,
+            parent_death_signal, capabilities, no_new_privs, fs_isolation, no_network,
+            namespaces, reproducible, temp_cwd, keep_temp_cwd_on_failure, capture,
+            digest_output, destroy_signal, destroy_escalation, force_destroy_signal, uid, gid,
+            new_process_group, new_session, detached, pty, before_exec, rlimits, priority,
+            cpu_affinity, chroot, argv0, umask, pass_fds, inherit_fds, in_redirect,
+            out_redirect, err_redirect, stderr_to_stdout, input_bytes, raw_command_line,
+            quoting, reset_signals, vfork, no_ctty, drop_policy, ..
+        } = options;
+        let temp_cwd = if temp_cwd {
+            match TempCwd::new(keep_temp_cwd_on_failure) {
+                Some(temp_cwd) => Some(temp_cwd),
+                None => return None,
+            }
+        } else {
+            None
+        };
+        let temp_dir = temp_cwd.as_ref().map(|t| t.path().clone());
+        let env = env.as_ref().map(|a| a.as_slice());
+        let cwd = match temp_dir {
+            Some(ref dir) => Some(dir.as_str().unwrap()),
+            None => dir.as_ref().map(|a| a.as_str().unwrap()),
+        };
+        // The streams which are redirected are connected to descriptors
+        // opened for the spawn, rather than pipes to the current process. A
+        // detached process gets the null device for the streams it is not
+        // given.
+        let mut fds = [in_fd, out_fd, err_fd];
+        let redirects = [in_redirect, out_redirect, err_redirect];
+        let mut opened = ~[];
+        for (i, (fd, redirect)) in fds.mut_iter().zip(redirects.iter()).enumerate() {
+            let redirect = match *redirect {
+                _ if fd.is_some() => continue,
+                Some(ref redirect) => redirect.clone(),
+                None if detached => RedirectNull,
+                None => continue,
+            };
+            match redirect::open(&redirect, i == 0) {
+                Some(opened_fd) => {
+                    opened.push(opened_fd);
+                    *fd = Some(opened_fd);
+                }
+                None => {
+                    for &fd in opened.iter() {
+                        unsafe { libc::close(fd); }
+                    }
+                    return None;
+                }
+            }
+        }
+        // Merged output which is captured is written to a pipe created here,
+        // since the process has to be given the same end of it twice.
+        let mut merged = None;
+        if stderr_to_stdout && fds[2].is_none() {
+            if fds[1].is_none() && !pty {
+                let pipe = os::pipe();
+                opened.push(pipe.out);
+                fds[1] = Some(pipe.out);
+                merged = Some(pipe.input);
+            }
+            fds[2] = fds[1];
+        }
+        let (in_fd, out_fd, err_fd) = (fds[0], fds[1], fds[2]);
+        let console = if detached && console == InheritConsole { NoConsole } else { console };
+        fn rtify(fd: Option<c_int>, input: bool, pty: bool) -> process::StdioContainer {
+            match fd {
+                Some(fd) => process::InheritFd(fd),
+                None if pty => process::CreatePty,
+                None => process::CreatePipe(input, !input),
+            }
+        }
+        let mut rtio = ~[rtify(in_fd, true, pty), rtify(out_fd, false, pty),
+                         rtify(err_fd, false, pty)];
+        let nfds = pass_fds.iter().map(|&(_, dst)| dst as uint + 1).max().unwrap_or(3);
+        for n in range(3, nfds) {
+            match pass_fds.iter().find(|&&(_, dst)| dst as uint == n) {
+                Some(&(src, _)) => rtio.push(process::InheritFd(src)),
+                None => rtio.push(process::Ignored),
+            }
+        }
+        // The master side of the terminal is returned for the first stream
+        // connected to it.
+        let pty_idx = if pty {
+            [in_fd, out_fd, err_fd].iter().position(|fd| fd.is_none())
+        } else {
+            None
+        };
+        let rtconfig = process::ProcessConfig {
+            program: resolved.as_ref().map_or(prog, |p| p.as_str().unwrap()),
+            args: args,
+            argv0: argv0.as_ref().map(|a| a.as_slice()).or(resolved.as_ref().map(|_| prog)),
+            raw_command_line: raw_command_line.as_ref().map(|l| l.as_slice()),
+            quoting: quoting,
+            env: env,
+            cwd: cwd,
+            io: rtio.as_slice(),
+            inherit_fds: inherit_fds && !reproducible,
+            reset_signals: reset_signals,
+            vfork: vfork,
+            console: console,
+            creation_flags: creation_flags,
+            job_limits: job_limits,
+            breakaway_from_job: breakaway_from_job,
+            desktop: desktop.as_ref().map(|d| d.as_slice()),
+            process_security: process_security.as_ref().map(|s| s.as_slice()),
+            thread_security: thread_security.as_ref().map(|s| s.as_slice()),
+            mitigations: mitigations,
+            spawn_attributes: spawn_attributes.as_ref(),
+            sandbox_profile: sandbox_profile.as_ref().map(|s| s.as_slice()),
+            process_controls: process_controls.as_ref(),
+            parent_death_signal: parent_death_signal,
+            capabilities: capabilities.as_ref(),
+            no_new_privs: no_new_privs,
+            fs_isolation: fs_isolation.as_ref(),
+            no_network: no_network,
+            namespaces: namespaces,
+            umask: umask.or(if reproducible { Some(REPRODUCIBLE_UMASK) } else { None }),
+            uid: uid,
+            gid: gid,
+            new_process_group: new_process_group,
+            new_session: new_session || detached,
+            no_ctty: no_ctty,
+            before_exec: before_exec,
+            rlimits: rlimits.as_slice(),
+            priority: priority,
+            cpu_affinity: cpu_affinity.as_ref().map(|c| c.as_slice()),
+            chroot: chroot.as_ref().map(|r| r.as_str().unwrap()),
+        };
+        let spawn_time_ms = clock::wall_time_ms();
+        let start_ns = clock::precise_time_ns();
+        let spawned = process::Process::new(rtconfig);
+        for &fd in opened.iter() {
+            unsafe { libc::close(fd); }
+        }
+        match spawned {
+            Some(mut inner) => {
+                inner.set_drop_policy(match drop_policy {
+                    WaitOnDrop if detached => DetachOnDrop,
+                    policy => policy,
+                });
+                let pty = pty_idx.and_then(|i| inner.io[i].take());
+                for &fd in merged.iter() {
+                    inner.io[1] = io::PipeStream::open(fd);
+                }
+                for bytes in input_bytes.move_iter() {
+                    match inner.io[0].take() {
+                        Some(input) => {
+                            do spawn {
+                                let _guard = io::ignore_io_error();
+                                let mut input = input;
+                                input.write(bytes);
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                let trace = trace::spawned(inner.id(), display_command(prog, args));
+                let suspend = if follow_suspend {
+                    suspend::follow(inner.id())
+                } else {
+                    None
+                };
+                Some(Process {
+                    inner: inner,
+                    program: prog.to_owned(),
+                    color: color,
+                    collapse_progress: collapse_progress,
+                    progress: progress,
+                    encoding: output_encoding,
+                    capture: capture,
+                    digest: digest_output,
+                    suspend: suspend,
+                    temp_cwd: temp_cwd,
+                    watched: None,
+                    trace: trace,
+                    destroy_signal: destroy_signal,
+                    destroy_escalation: destroy_escalation,
+                    force_destroy_signal: force_destroy_signal,
+                    pty: pty,
+                    spawn_time_ms: spawn_time_ms,
+                    start_ns: start_ns,
+                    end_ns: None,
+                })
+            }
+            None => {
+                for &fd in merged.iter() {
+                    unsafe { libc::close(fd); }
+                }
+                None
+            }
+        }
+    }
+
+    /**
+     * Takes over the management of a child which was handed off with
+     * `handoff`, by a previous image of the current process before it
+     * exec'd. The adopted process has no pipes to the child, and the options
+     * it was spawned with other than its program are lost. Its timing is
+     * counted from when it is adopted.
+     *
+     * This is only supported in native tasks.
+     *
+     * If the child cannot be adopted, for example because its id now belongs
+     * to another process, then the `io_error` condition is raised and None
+     * is returned.
+     */
+    pub fn adopt(handoff: &ProcessHandoff) -> Option<Process> {
+        let pid = handoff.pid;
+        if handoff.start_time.is_some() && handoff::start_time(pid) != handoff.start_time {
+            io::io_error::cond.raise(io::IoError {
+                kind: io::InvalidInput,
+                desc: "the handed off process was replaced by another one",
+                detail: Some(format!("process {}", pid)),
+            });
+            return None;
+        }
+        process::Process::adopt(pid).map(|inner| {
+            Process {
+                inner: inner,
+                program: handoff.program.clone(),
+                color: ColorAuto,
+                collapse_progress: false,
+                progress: None,
+                encoding: Untranscoded,
+                capture: CaptureAll,
+                digest: false,
+                suspend: None,
+                temp_cwd: None,
+                watched: None,
+                trace: None,
+                destroy_signal: process::PleaseExitSignal,
+                destroy_escalation: ~[],
+                force_destroy_signal: process::MustDieSignal,
+                pty: None,
+                spawn_time_ms: clock::wall_time_ms(),
+                start_ns: clock::precise_time_ns(),
+                end_ns: None,
+            }
+        })
+    }
+
+    /**
+     * Gives up the management of the child without waiting for it, for
+     * callers which do not care when it exits. The pipes to the child are
+     * closed, and it is left running and reaped in the background once it
+     * exits, as with `reap_in_background`, so that it does not linger as a
+     * zombie. Its temporary working directory, if any, is removed then.
+     */
+    pub fn detach(mut self) {
+        self.close_input();
+        self.close_outputs();
+        self.pty.take();
+        reap_in_background(self)
+    }
+
+    /**
+     * Gives up the management of the child like `detach`, returning a port
+     * which receives its exit code once it terminates, so that a task can
+     * `select` on the exit of children along with its other ports.
+     *
+     * The pipes to the child are closed, so what it writes to them should be
+     * read beforehand.
+     */
+    pub fn exit_port(mut self) -> Port<ProcessExit> {
+        self.close_input();
+        self.close_outputs();
+        self.pty.take();
+        let (port, chan) = Chan::new();
+        reap_with(self, proc(status) { chan.try_send(status); });
+        port
+    }
+
+    /**
+     * Gives up the management of the child like `detach`, calling `f` with
+     * its exit code in a new task once it terminates.
+     *
+     * The children are watched by a single reaper task for each event loop
+     * rather than by a task each, so a supervisor can watch any number of
+     * them. The pipes to the child are closed, so what it writes to them
+     * should be read beforehand.
+     */
+    pub fn on_exit(mut self, f: proc(ProcessExit)) {
+        self.close_input();
+        self.close_outputs();
+        self.pty.take();
+        reap_with(self, f)
+    }
+
+    /**
+     * Gives up the management of the child without waiting for it, returning
+     * what a successor of the current process needs to adopt it with
+     * `adopt`. The pipes to the child are closed, and its temporary working
+     * directory, if any, is kept.
+     *
+     * The child stays a child of the current process across an exec. Until it
+     * is adopted it is not waited for, so it lingers as a zombie if it exits
+     * in the meantime.
+     */
+    pub fn handoff(self) -> ProcessHandoff {
+        let Process { inner, program, temp_cwd, .. } = self;
+        for temp_cwd in temp_cwd.move_iter() {
+            temp_cwd.keep();
+        }
+        let pid = inner.forget();
+        ProcessHandoff { pid: pid, start_time: handoff::start_time(pid), program: program }
+    }
+
+    /// Returns the unique id of the process
+    pub fn get_id(&self) -> pid_t { self.inner.id() }
+
+    /**
+     * Returns a pidfd referring to the process, which can be polled for its
+     * exit. This is only supported in native tasks on Linux 5.3 and later,
+     * and the descriptor is closed along with the `Process`.
+     */
+    pub fn get_pidfd(&self) -> Option<c_int> { self.inner.pidfd() }
+
+    /**
+     * Returns whether the process is still running, without reaping it, so
+     * that it can still be finished afterwards. A process which has been
+     * finished is never running.
+     *
+     * Another task can make the same check with `run::is_alive` and the id
+     * of the process.
+     */
+    pub fn is_alive(&self) -> bool {
+        self.end_ns.is_none() && liveness::is_alive(self.get_id())
+    }
+
+    /// Returns the temporary directory the process runs in, if it was spawned
+    /// with `temp_cwd` and has not been finished yet.
+    pub fn temp_cwd<'a>(&'a self) -> Option<&'a Path> {
+        self.temp_cwd.as_ref().map(|t| t.path())
+    }
+
+    /**
+     * Returns an io::Writer that can be used to write to this Process's stdin.
+     *
+     * Fails if there is no stdin available (it's already been removed by
+     * take_input)
+     */
+    pub fn input<'a>(&'a mut self) -> &'a mut io::Writer {
+        self.inner.io[0].get_mut_ref() as &mut io::Writer
+    }
+
+    /**
+     * Returns an io::Reader that can be used to read from this Process's stdout.
+     *
+     * Fails if there is no stdout available (it's already been removed by
+     * take_output)
+     */
+    pub fn output<'a>(&'a mut self) -> &'a mut io::Reader {
+        self.inner.io[1].get_mut_ref() as &mut io::Reader
+    }
+
+    /**
+     * Returns an io::Reader that can be used to read from this Process's stderr.
+     *
+     * Fails if there is no stderr available (it's already been removed by
+     * take_error)
+     */
+    pub fn error<'a>(&'a mut self) -> &'a mut io::Reader {
+        self.inner.io[2].get_mut_ref() as &mut io::Reader
+    }
+
+    /**
+     * Returns the master side of the pseudo-terminal of this Process, which
+     * reads what the process writes to its terminal and writes what it reads
+     * from it.
+     *
+     * Fails if the process was not spawned with `pty`, or if the terminal has
+     * already been removed by take_pty.
+     */
+    pub fn pty<'a>(&'a mut self) -> &'a mut io::PipeStream {
+        self.pty.get_mut_ref()
+    }
+
+    /// Takes the master side of the pseudo-terminal of this Process, if it
+    /// has one.
+    pub fn take_pty(&mut self) -> Option<io::PipeStream> {
+        self.pty.take()
+    }
+
+    /**
+     * Takes this Process's stdout, returning a reader of it which can also
+     * wait for a bounded time only for the next chunk of output.
+     *
+     * Fails if there is no stdout available (it's already been removed by
+     * take_output)
+     */
+    pub fn timeout_output(&mut self) -> TimeoutReader {
+        TimeoutReader::new(self.inner.io[1].take_unwrap())
+    }
+
+    /**
+     * Takes this Process's stderr, returning a reader of it which can also
+     * wait for a bounded time only for the next chunk of output.
+     *
+     * Fails if there is no stderr available (it's already been removed by
+     * take_error)
+     */
+    pub fn timeout_error(&mut self) -> TimeoutReader {
+        TimeoutReader::new(self.inner.io[2].take_unwrap())
+    }
+
+    /**
+     * Takes this Process's stdout, returning a buffered reader of it so that
+     * its output can be read by lines or up to a delimiter.
+     *
+     * Fails if there is no stdout available (it's already been removed by
+     * take_output)
+     */
+    pub fn buffered_output(&mut self) -> io::BufferedReader<io::PipeStream> {
+        io::BufferedReader::new(self.inner.io[1].take_unwrap())
+    }
+
+    /**
+     * Takes this Process's stderr, returning a buffered reader of it so that
+     * its output can be read by lines or up to a delimiter.
+     *
+     * Fails if there is no stderr available (it's already been removed by
+     * take_error)
+     */
+    pub fn buffered_error(&mut self) -> io::BufferedReader<io::PipeStream> {
+        io::BufferedReader::new(self.inner.io[2].take_unwrap())
+    }
+
+    /**
+     * Takes this Process's stdout, returning an iterator over its chunks of
+     * `size` bytes, as read by the protocols exchanging fixed-size frames.
+     *
+     * Fails if there is no stdout available (it's already been removed by
+     * take_output)
+     */
+    pub fn output_chunks(&mut self, size: uint) -> Chunks<io::PipeStream> {
+        Chunks::new(self.inner.io[1].take_unwrap(), size)
+    }
+
+    /**
+     * Takes this Process's stdout, returning an iterator over its lines,
+     * each of which is returned as soon as the child has written all of it,
+     * without its line ending.
+     *
+     * Fails if there is no stdout available (it's already been removed by
+     * take_output)
+     */
+    pub fn stdout_lines(&mut self) -> OutputLines<io::PipeStream> {
+        OutputLines::new(self.inner.io[1].take_unwrap())
+    }
+
+    /**
+     * Waits for a line containing `pattern` to appear on this Process's stdout
+     * or stderr, and returns it without its trailing whitespace. This is how
+     * a test knows that the server it spawned is ready, for example with the
+     * pattern "Listening on port".
+     *
+     * The first call takes stdout and stderr, which are read to their end from
+     * then on; the lines which do not match are dropped.
+     *
+     * Raises the `io_error` condition with the kind TimedOut and returns None
+     * if no such line appeared within `msecs` milliseconds. Returns None if
+     * both streams ended before.
+     */
+    pub fn wait_for_output(&mut self, pattern: &str, msecs: u64) -> Option<~str> {
+        if self.watched.is_none() {
+            let streams = ~[self.inner.io[1].take(), self.inner.io[2].take()];
+            let streams = streams.move_iter().filter_map(|s| s).collect();
+            self.watched = Some(watch::watch_lines(streams));
+        }
+        watch::wait_for_line(self.watched.get_mut_ref(), pattern, msecs)
+    }
+
+    /**
+     * Closes the handle to the child process's stdin.
+     */
+    pub fn close_input(&mut self) {
+        self.inner.io[0].take();
+    }
+
+    /// Takes the handle to stdin.
+    fn take_input(&mut self) -> Option<io::PipeStream> {
+        self.inner.io[0].take()
+    }
+
+    /// Takes the handle to stdout.
+    fn take_output(&mut self) -> Option<io::PipeStream> {
+        self.inner.io[1].take()
+    }
+
+    /// Takes the handles to stdout and stderr.
+    fn take_outputs(&mut self) -> (Option<io::PipeStream>, Option<io::PipeStream>) {
+        (self.inner.io[1].take(), self.inner.io[2].take())
+    }
+
+    /**
+     * Closes the handle to stdout and stderr.
+     */
+    pub fn close_outputs(&mut self) {
+        self.inner.io[1].take();
+        self.inner.io[2].take();
+    }
+
+    /**
+     * Closes the handle to stdin, waits for the child process to terminate,
+     * and returns the exit code.
+     *
+     * If the child has already been finished then the exit code is returned.
+     */
+    pub fn finish(&mut self) -> ProcessExit {
+        let status = self.inner.wait();
+        self.finished(status)
+    }
+
+    /**
+     * Returns the exit code of the child process if it has terminated, and
+     * None without waiting for it otherwise, so that a single task can keep
+     * an eye on many children. Once the exit code has been returned, the
+     * process is finished as if by `finish`.
+     */
+    pub fn try_finish(&mut self) -> Option<ProcessExit> {
+        match self.inner.try_wait() {
+            Some(status) => Some(self.finished(status)),
+            None => None,
+        }
+    }
+
+    /**
+     * Waits up to `msecs` milliseconds for the child process to terminate,
+     * returning its exit code like `finish` if it does, and None if it is
+     * still running by then, in which case it is left running.
+     */
+    pub fn finish_with_timeout(&mut self, msecs: u64) -> Option<ProcessExit> {
+        match self.inner.wait_timeout(msecs) {
+            Some(status) => Some(self.finished(status)),
+            None => None,
+        }
+    }
+
+    /**
+     * Waits for the child process to terminate like `finish`, and also
+     * returns the CPU time, memory and page faults it used, or None if
+     * these are not known, such as in green tasks.
+     */
+    pub fn finish_with_rusage(&mut self) -> (ProcessExit, Option<ResourceUsage>) {
+        let status = self.finish();
+        (status, self.inner.rusage())
+    }
+
+    /**
+     * Returns whether the process dumped core when it was killed by a
+     * signal, once it has been finished. This is only known in native tasks
+     * on Posix OSs, and false is returned otherwise.
+     */
+    pub fn core_dumped(&self) -> bool {
+        self.inner.core_dumped()
+    }
+
+    /// Releases what is kept for the child while it runs, once it has
+    /// terminated with `status`.
+    fn finished(&mut self, status: ProcessExit) -> ProcessExit {
+        if self.end_ns.is_none() {
+            self.end_ns = Some(clock::precise_time_ns());
+        }
+        // The id of the child may be reused from now on, so it must no longer
+        // be stopped along with us.
+        self.suspend.take();
+        for &id in self.trace.take().iter() {
+            trace::exited(id, status);
+        }
+        match self.temp_cwd.take() {
+            Some(temp_cwd) => temp_cwd.finish(&status),
+            None => {}
+        }
+        status
+    }
+
+    /**
+     * Waits for the child process to terminate like `finish`, and classifies
+     * its exit status according to the rules registered for the program with
+     * `register_exit_codes`.
+     */
+    pub fn finish_classified(&mut self) -> ExitClass {
+        let status = self.finish();
+        classify_exit(self.program, &status)
+    }
+
+    /**
+     * Closes the handle to stdin, waits for the child process to terminate, and
+     * reads and returns all remaining output of stdout and stderr, along with
+     * the exit code.
+     *
+     * If the child has already been finished then the exit code and any
+     * remaining unread output of stdout and stderr will be returned.
+     *
+     * This method will fail if the child process's stdout or stderr streams
+     * were redirected to existing file descriptors.
+     *
+     * What a process spawned with `pty` writes to its terminal is read as its
+     * stdout, unless the terminal has been taken with take_pty.
+     */
+    pub fn finish_with_output(&mut self) -> ProcessOutput {
+        self.finish_capturing(~[], ~[])
+    }
+
+    /**
+     * Like `finish_with_output`, but appends the output of stdout and stderr
+     * to the given buffers instead of returning new vectors, so that the
+     * buffers can be reused when running many commands in a row.
+     *
+     * The other results of the capture, such as the checksums or the number
+     * of discarded bytes, are not reported.
+     */
+    pub fn finish_with_output_into(&mut self, output: &mut ~[u8],
+                                   error: &mut ~[u8]) -> ProcessExit {
+        let out_buf = util::replace(output, ~[]);
+        let err_buf = util::replace(error, ~[]);
+        let ProcessOutput { status, output: out_buf, error: err_buf, .. } =
+            self.finish_capturing(out_buf, err_buf);
+        *output = out_buf;
+        *error = err_buf;
+        status
+    }
+
+    /// Finishes the process, appending its output to `out_buf` and `err_buf`.
+    fn finish_capturing(&mut self, out_buf: ~[u8], err_buf: ~[u8]) -> ProcessOutput {
+        self.close_input();
+        // What the process writes to its terminal is its output.
+        let output = self.inner.io[1].take().or_else(|| self.pty.take());
+        let error = self.inner.io[2].take();
+
+        // Spawn two entire schedulers to read both stdout and sterr
+        // in parallel so we don't deadlock while blocking on one
+        // or the other. FIXME (#2625): Surely there's a much more
+        // clever way to do this.
+        let (p, ch) = SharedChan::new();
+        let ch_clone = ch.clone();
+        let err_settings = CaptureSettings {
+            strip: self.color == ColorNever,
+            collapse: self.collapse_progress,
+            progress: self.progress.clone(),
+            mode: self.capture,
+            digest: self.digest,
+            encoding: self.encoding,
+        };
+        let out_settings = err_settings.clone();
+
+        do spawn {
+            let _guard = io::ignore_io_error();
+            ch.send((2, capture(error, err_settings, err_buf)));
+        }
+        do spawn {
+            let _guard = io::ignore_io_error();
+            ch_clone.send((1, capture(output, out_settings, out_buf)));
+        }
+
+        let status = self.finish();
+
+        let (errs, outs) = match (p.recv(), p.recv()) {
+            ((1, o), (2, e)) => (e, o),
+            ((2, e), (1, o)) => (e, o),
+            ((x, _), (y, _)) => {
+                fail!("unexpected file numbers: {}, {}", x, y);
+            }
+        };
+
+        return ProcessOutput {status: status,
+                              output: outs.bytes,
+                              error: errs.bytes,
+                              output_discarded: outs.discarded,
+                              error_discarded: errs.discarded,
+                              output_digest: outs.digest,
+                              error_digest: errs.digest,
+                              output_file: outs.file,
+                              error_file: errs.file,
+                              spawn_time_ms: self.spawn_time_ms,
+                              elapsed_ns: self.end_ns.unwrap() - self.start_ns,
+                              rusage: self.inner.rusage()};
+    }
+
+    /**
+     * Sends the signal `signum` to the process, such as SIGHUP to have it
+     * reload its configuration or SIGWINCH to tell it that its terminal was
+     * resized.
+     *
+     * On Win32 only the signals of `destroy` and `force_destroy` are
+     * supported, which terminate the process, and in native tasks SIGINT and
+     * SIGBREAK, which are delivered as a Ctrl+Break event to a process
+     * started in a new process group.
+     *
+     * If the signal cannot be sent, for example because the process has been
+     * finished already, then the `io_error` condition is raised.
+     */
+    pub fn signal(&mut self, signum: int) {
+        self.inner.signal(signum)
+    }
+
+    /**
+     * Freezes the process without killing it until it is resumed with
+     * `resume`, with SIGSTOP on Posix OSs, and on Win32 by suspending all of
+     * its threads, which is only supported in native tasks. A process which
+     * is frozen keeps its memory but uses no CPU.
+     *
+     * If the process cannot be frozen, then the `io_error` condition is
+     * raised.
+     */
+    pub fn pause(&mut self) {
+        self.inner.pause()
+    }
+
+    /**
+     * Lets a process frozen with `pause` run again, with SIGCONT on Posix
+     * OSs.
+     *
+     * If the process cannot be resumed, then the `io_error` condition is
+     * raised.
+     */
+    pub fn resume(&mut self) {
+        self.inner.resume()
+    }
+
+    /**
+     * Terminates the process, giving it a chance to clean itself up if
+     * this is supported by the operating system.
+     *
+     * On Posix OSs the `destroy_signal` of the options, SIGTERM by default,
+     * will be sent to the process, followed by the signals of
+     * `destroy_escalation` for as long as it keeps running. On Win32
+     * TerminateProcess(..) will be called.
+     */
+    pub fn destroy(&mut self) {
+        self.inner.signal(self.destroy_signal);
+        let escalation = escalate::escalate(self.inner.id(), self.destroy_escalation.clone());
+        self.finish();
+        drop(escalation);
+    }
+
+    /**
+     * Terminates the process as soon as possible without giving it a
+     * chance to clean itself up.
+     *
+     * On Posix OSs the `force_destroy_signal` of the options, SIGKILL by
+     * default, will be sent to the process. On Win32 TerminateProcess(..)
+     * will be called.
+     */
+    pub fn force_destroy(&mut self) {
+        self.inner.signal(self.force_destroy_signal);
+        self.finish();
+    }
+
+    /**
+     * Asks the process to exit and kills it if it does not do so within
+     * `grace_ms` milliseconds, returning its exit code once it has been
+     * finished.
+     *
+     * On Posix OSs the `destroy_signal` of the options, SIGTERM by default,
+     * is sent to the process first, and the `force_destroy_signal`, SIGKILL
+     * by default, once the grace period is over. On Win32 both terminate the
+     * process right away. A process which has exited already is only
+     * finished.
+     */
+    pub fn shutdown(&mut self, grace_ms: u64) -> ProcessExit {
+        match self.begin_shutdown() {
+            Some(status) => status,
+            None => self.end_shutdown(grace_ms),
+        }
+    }
+
+    /// Sends the process its `destroy_signal`, unless it has exited already,
+    /// in which case it is finished and its exit code is returned.
+    fn begin_shutdown(&mut self) -> Option<ProcessExit> {
+        match self.try_finish() {
+            Some(status) => return Some(status),
+            None => {}
+        }
+        self.inner.signal(self.destroy_signal);
+        None
+    }
+
+    /// Waits up to `grace_ms` milliseconds for the process to exit after
+    /// `begin_shutdown`, killing it if it does not do so by then.
+    fn end_shutdown(&mut self, grace_ms: u64) -> ProcessExit {
+        match self.finish_with_timeout(grace_ms) {
+            Some(status) => status,
+            None => {
+                self.inner.signal(self.force_destroy_signal);
+                self.finish()
+            }
+        }
+    }
+
+    /**
+     * Terminates the process along with the processes it started, their
+     * children and so on, which `destroy` leaves running.
+     *
+     * On Posix OSs the descendants are found by following the parent ids of
+     * all processes and are sent the `destroy_signal` of the options, after
+     * which the process is destroyed with `destroy`. On Win32 they are
+     * terminated with TerminateProcess(..). A descendant whose parent exited
+     * already is no longer found.
+     */
+    pub fn destroy_tree(&mut self) {
+        tree::signal_descendants(self.inner.id(), self.destroy_signal);
+        self.destroy();
+    }
+}
+
+/// Describes how `finish_with_output` reads an output stream of a process.
+#[deriving(Clone)]
+struct CaptureSettings {
+    strip: bool,
+    collapse: bool,
+    progress: Option<ProgressWatcher>,
+    mode: CaptureMode,
+    digest: bool,
+    encoding: OutputEncoding,
+}
+
+/// Reads all of `stream`, checksumming it, reporting progress and passing it
+/// through an `AnsiFilter` as requested by `settings`, and appends what the
+/// capture mode asks for to `buf`.
+fn capture(stream: Option<io::PipeStream>, settings: CaptureSettings,
+           mut buf: ~[u8]) -> Captured {
+    let CaptureSettings { strip, collapse, progress, mode, digest, encoding } = settings;
+    let mut reader = match stream {
+        Some(stream) => ~stream as ~Reader,
+        None => return Captured { bytes: buf, discarded: 0, digest: None, file: None },
+    };
+    let mut digest_port = None;
+    if digest {
+        let (port, r) = DigestReader::new(reader);
+        reader = ~r as ~Reader;
+        digest_port = Some(port);
+    }
+    match progress {
+        Some(watcher) => reader = ~ProgressReader::new(reader, watcher) as ~Reader,
+        None => {}
+    }
+    if strip || collapse {
+        reader = ~AnsiFilter::new(reader, strip, collapse) as ~Reader;
+    }
+    let mut captured = if encoding == Untranscoded {
+        capture::read(reader, mode, buf)
+    } else {
+        // Only the new output is to be transcoded.
+        let Captured { bytes, discarded, file, .. } = capture::read(reader, mode, ~[]);
+        buf.push_all_move(codepage::transcode(bytes, encoding));
+        Captured { bytes: buf, discarded: discarded, digest: None, file: file }
+    };
+    captured.digest = digest_port.and_then(|port| port.recv_opt());
+    captured
+}
+
+/**
+ * Hands a child process over to the background reaper.
+ *
+ * The child is left running, and its exit status is collected as soon as it
+ * terminates so that it does not linger as a zombie. Until then its id is
+ * reported by `unreaped_children`.
+ */
+pub fn reap_in_background(process: Process) {
+    reap_with(process, proc(_status) {})
+}
+
+/// Hands `process` over to the background reaper, which calls `f` with its
+/// exit code once it has finished it.
+fn reap_with(process: Process, f: proc(ProcessExit)) {
+    let Process { inner, temp_cwd, trace, .. } = process;
+    inner.on_exit(proc(status) {
+        for &id in trace.iter() {
+            trace::exited(id, status);
+        }
+        for temp_cwd in temp_cwd.move_iter() {
+            temp_cwd.finish(&status);
+        }
+        f(status);
+    })
+}
+
+/// Returns the ids of the children given to `reap_in_background`, or left
+/// running when their `Process` was dropped, which have not been reaped yet.
+pub fn unreaped_children() -> ~[pid_t] {
+    rt::reaper::pending()
+}
+
+/**
+ * Spawns a process and waits for it to terminate. The process will
+ * inherit the current stdin/stdout/stderr file descriptors.
+ *
+ * # Arguments
+ *
+ * * prog - The path to an executable
+ * * args - Vector of arguments to pass to the child process
+ *
+ * # Return value
+ *
+ * The process's exit code, or None if the child process could not be started
+ */
+pub fn process_status(prog: &str, args: &[~str]) -> Option<ProcessExit> {
+    let mut opt_prog = Process::new(prog, args, ProcessOptions {
+        in_fd: Some(unsafe { libc::dup(libc::STDIN_FILENO) }),
+        out_fd: Some(unsafe { libc::dup(libc::STDOUT_FILENO) }),
+        err_fd: Some(unsafe { libc::dup(libc::STDERR_FILENO) }),
+        .. ProcessOptions::new()
+    });
+    match opt_prog {
+        Some(ref mut prog) => Some(prog.finish()),
+        None => None
+    }
+}
+
+/**
+ * Spawns a process, records all its output, and waits for it to terminate.
+ *
+ * # Arguments
+ *
+ * * prog - The path to an executable
+ * * args - Vector of arguments to pass to the child process
+ *
+ * # Return value
+ *
+ * The process's stdout/stderr output and exit code, or None if the child process could not be
+ * started.
+ */
+pub fn process_output(prog: &str, args: &[~str]) -> Option<ProcessOutput> {
+    let mut opt_prog = Process::new(prog, args, ProcessOptions::new());
+    match opt_prog {
+        Some(ref mut prog) => Some(prog.finish_with_output()),
+        None => None
+    }
+}
+
+/**
+ * Spawns a process, writes `input` to its stdin, records all its output, and
+ * waits for it to terminate.
+ *
+ * # Arguments
+ *
+ * * prog - The path to an executable
+ * * args - Vector of arguments to pass to the child process
+ * * input - The bytes the child process reads from its stdin
+ *
+ * # Return value
+ *
+ * The process's stdout/stderr output and exit code, or None if the child process could not be
+ * started.
+ */
+pub fn process_output_with_input(prog: &str, args: &[~str],
+                                 input: &[u8]) -> Option<ProcessOutput> {
+    let options = ProcessOptions { input_bytes: Some(input.to_owned()), .. ProcessOptions::new() };
+    let mut opt_prog = Process::new(prog, args, options);
+    match opt_prog {
+        Some(ref mut prog) => Some(prog.finish_with_output()),
+        None => None
+    }
+}
+
+/**
+ * Spawns a process like `Process::new`, but checks the program first so that
+ * the reason why it cannot be run is reported precisely.
+ *
+ * # Return value
+ *
+ * The new process, or an error whose detail is the program or the path it
+ * was resolved to. The kind of the error is FileNotFound if the program does
+ * not exist, PermissionDenied if it exists but is not executable, and
+ * NotExecutable if it is in a format which the system cannot execute.
+ */
+pub fn spawn_checked(prog: &str, args: &[~str],
+                     options: ProcessOptions) -> Result<Process, io::IoError> {
+    let env = match options.child_env() {
+        Some(env) => env,
+        None => os::env(),
+    };
+    let path = match validate::check_program(prog, &options.program_search, env) {
+        Ok(path) => path,
+        Err(ProgramNotExecutable(path)) => {
+            return Err(io::IoError {
+                kind: io::PermissionDenied,
+                desc: "program is not executable",
+                detail: Some(path.display().to_str()),
+            })
+        }
+        Err(..) => {
+            return Err(io::IoError {
+                kind: io::FileNotFound,
+                desc: "program not found",
+                detail: Some(prog.to_owned()),
+            })
+        }
+    };
+    match io::result(|| Process::new(prog, args, options)) {
+        Ok(Some(p)) => Ok(p),
+        Ok(None) => Err(io::IoError {
+            kind: io::OtherIoError,
+            desc: "process could not be spawned",
+            detail: None,
+        }),
+        Err(mut e) => {
+            if e.kind == io::PermissionDenied || e.kind == io::NotExecutable {
+                e.detail = Some(path.display().to_str());
+            }
+            Err(e)
+        }
+    }
+}
+
+/**
+ * Spawns a process, waits for it to terminate and returns its stdout if it
+ * succeeded.
+ *
+ * Whether the process succeeded is decided by `classify_exit`, so exit codes
+ * registered as a success for the program are accepted too.
+ *
+ * # Arguments
+ *
+ * * prog - The path to an executable
+ * * args - Vector of arguments to pass to the child process
+ *
+ * # Return value
+ *
+ * The process's stdout output, or an error if the child process could not be
+ * started or did not succeed. In the latter case the detail of the error
+ * contains the command line, the exit status and the process's stderr output.
+ */
+pub fn check_output(prog: &str, args: &[~str]) -> Result<~[u8], io::IoError> {
+    let output = match io::result(|| process_output(prog, args)) {
+        Ok(Some(output)) => output,
+        Ok(None) => return Err(io::standard_error(io::OtherIoError)),
+        Err(e) => return Err(e),
+    };
+    if classify_exit(prog, &output.status) == ExitSuccess {
+        return Ok(output.output);
+    }
+    let stderr = match str::from_utf8(output.error) {
+        Some(s) => s.trim().to_owned(),
+        None => format!("<{} bytes of binary data>", output.error.len()),
+    };
+    Err(io::IoError {
+        kind: io::OtherIoError,
+        desc: "process did not exit successfully",
+        detail: Some(format!("`{}` failed with {}: {}",
+                             display_command(prog, args), output.status, stderr)),
+    })
+}
+
+/// Expands the variable references in `args` against `env`, or against the
+/// environment of the current process if it is None. Raises the `io_error`
+/// condition and returns None if a reference is invalid.
+fn expand_args(args: &[~str], env: Option<&~[(~str, ~str)]>) -> Option<~[~str]> {
+    let vars = match env {
+        Some(env) => env.clone(),
+        None => os::env(),
+    };
+    let mut ret = ~[];
+    for arg in args.iter() {
+        match env::expand(*arg, vars) {
+            Ok(arg) => ret.push(arg),
+            Err(detail) => {
+                io::io_error::cond.raise(io::IoError {
+                    kind: io::InvalidInput,
+                    desc: "invalid variable reference in argument",
+                    detail: Some(detail),
+                });
+                return None;
+            }
+        }
+    }
+    Some(ret)
+}
+
+/// Formats a command line for display in messages, quoting the arguments
+/// which contain whitespace.
+fn display_command(prog: &str, args: &[~str]) -> ~str {
+    let mut ret = prog.to_owned();
+    for arg in args.iter() {
+        ret.push_char(' ');
+        if arg.is_empty() || arg.chars().any(|c| c.is_whitespace()) {
+            ret.push_str(format!("\"{}\"", *arg));
+        } else {
+            ret.push_str(*arg);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use os;
+    use run;
+    use str;
+    use task::spawn;
+    use unstable::running_on_valgrind;
+    use io::pipe::PipeStream;
+    use io::process::{ExitStatus, ExitSignal};
+    use io::{io_error, FileNotFound, InvalidInput, PermissionDenied, TimedOut};
+    use io::fs::File;
+    use io::fs;
+    use io;
+    use libc::c_int;
+    use libc;
+    use vec;
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_process_status() {
+        let mut status = run::process_status("false", []).expect("failed to exec `false`");
+        assert!(status.matches_exit_status(1));
+
+        status = run::process_status("true", []).expect("failed to exec `true`");
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_process_output_fail_to_start() {
+        // If the executable does not exist, then the io_error condition should be raised with
+        // IoErrorKind FileNotFound.
+
+        let mut trapped_io_error = false;
+        let opt_outp = io_error::cond.trap(|e| {
+            trapped_io_error = true;
+            assert_eq!(e.kind, FileNotFound);
+        }).inside(|| -> Option<run::ProcessOutput> {
+            run::process_output("no-binary-by-this-name-should-exist", [])
+        });
+        assert!(trapped_io_error);
+        assert!(opt_outp.is_none());
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_process_output_output() {
+
+        let run::ProcessOutput {status, output, error, ..}
+             = run::process_output("echo", [~"hello"]).expect("failed to exec `echo`");
+        let output_str = str::from_utf8_owned(output).unwrap();
+
+        assert!(status.success());
+        assert_eq!(output_str.trim().to_owned(), ~"hello");
+        // FIXME #7224
+        if !running_on_valgrind() {
+            assert_eq!(error, ~[]);
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_process_output_error() {
+
+        let run::ProcessOutput {status, output, error, ..}
+             = run::process_output("mkdir", [~"."]).expect("failed to exec `mkdir`");
+
+        assert!(status.matches_exit_status(1));
+        assert_eq!(output, ~[]);
+        assert!(!error.is_empty());
+    }
+
+    #[test]
+    #[ignore] // FIXME(#10016) cat never sees stdin close
+    fn test_pipes() {
+
+        let pipe_in = os::pipe();
+        let pipe_out = os::pipe();
+        let pipe_err = os::pipe();
+
+        let mut process = run::Process::new("cat", [], run::ProcessOptions {
+            in_fd: Some(pipe_in.input),
+            out_fd: Some(pipe_out.out),
+            err_fd: Some(pipe_err.out),
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `cat`");
+
+        os::close(pipe_in.input as int);
+        os::close(pipe_out.out as int);
+        os::close(pipe_err.out as int);
+
+        do spawn {
+            writeclose(pipe_in.out, "test");
+        }
+        let actual = readclose(pipe_out.input);
+        readclose(pipe_err.input);
+        process.finish();
+
+        assert_eq!(~"test", actual);
+    }
+
+    fn writeclose(fd: c_int, s: &str) {
+        let mut writer = PipeStream::open(fd);
+        writer.write(s.as_bytes());
+    }
+
+    fn readclose(fd: c_int) -> ~str {
+        let mut res = ~[];
+        let mut reader = PipeStream::open(fd);
+        let mut buf = [0, ..1024];
+        loop {
+            match reader.read(buf) {
+                Some(n) => { res.push_all(buf.slice_to(n)); }
+                None => break
+            }
+        }
+        str::from_utf8_owned(res).unwrap()
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_finish_once() {
+        let mut prog = run::Process::new("false", [], run::ProcessOptions::new())
+            .expect("failed to exec `false`");
+        assert!(prog.finish().matches_exit_status(1));
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_finish_twice() {
+        let mut prog = run::Process::new("false", [], run::ProcessOptions::new())
+            .expect("failed to exec `false`");
+        assert!(prog.finish().matches_exit_status(1));
+        assert!(prog.finish().matches_exit_status(1));
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_finish_with_output_once() {
+
+        let mut prog = run::Process::new("echo", [~"hello"], run::ProcessOptions::new())
+            .expect("failed to exec `echo`");
+        let run::ProcessOutput {status, output, error, ..}
+            = prog.finish_with_output();
+        let output_str = str::from_utf8_owned(output).unwrap();
+
+        assert!(status.success());
+        assert_eq!(output_str.trim().to_owned(), ~"hello");
+        // FIXME #7224
+        if !running_on_valgrind() {
+            assert_eq!(error, ~[]);
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_finish_with_output_twice() {
+
+        let mut prog = run::Process::new("echo", [~"hello"], run::ProcessOptions::new())
+            .expect("failed to exec `echo`");
+        let run::ProcessOutput {status, output, error, ..}
+            = prog.finish_with_output();
+
+        let output_str = str::from_utf8_owned(output).unwrap();
+
+        assert!(status.success());
+        assert_eq!(output_str.trim().to_owned(), ~"hello");
+        // FIXME #7224
+        if !running_on_valgrind() {
+            assert_eq!(error, ~[]);
+        }
+
+        let run::ProcessOutput {status, output, error, ..}
+            = prog.finish_with_output();
+
+        assert!(status.success());
+        assert_eq!(output, ~[]);
+        // FIXME #7224
+        if !running_on_valgrind() {
+            assert_eq!(error, ~[]);
+        }
+    }
+
+    fn run_pwd(dir: Option<&Path>) -> run::Process {
+        run_pwd_with(run::ProcessOptions {
+            dir: dir,
+            .. run::ProcessOptions::new()
+        })
+    }
+
+    #[test]
+    fn test_keep_current_working_dir() {
+        let mut prog = run_pwd(None);
+
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        let parent_dir = os::getcwd();
+        let child_dir = Path::new(output.trim());
+
+        let parent_stat = parent_dir.stat();
+        let child_stat = child_dir.stat();
+
+        assert_eq!(parent_stat.unstable.device, child_stat.unstable.device);
+        assert_eq!(parent_stat.unstable.inode, child_stat.unstable.inode);
+    }
+
+    #[test]
+    fn test_change_working_directory() {
+        // test changing to the parent of os::getcwd() because we know
+        // the path exists (and os::getcwd() is not expected to be root)
+        let parent_dir = os::getcwd().dir_path();
+        let mut prog = run_pwd(Some(&parent_dir));
+
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        let child_dir = Path::new(output.trim());
+
+        let parent_stat = parent_dir.stat();
+        let child_stat = child_dir.stat();
+
+        assert_eq!(parent_stat.unstable.device, child_stat.unstable.device);
+        assert_eq!(parent_stat.unstable.inode, child_stat.unstable.inode);
+    }
+
+    #[cfg(unix,not(target_os="android"))]
+    fn run_env(env: Option<~[(~str, ~str)]>) -> run::Process {
+        run::Process::new("env", [], run::ProcessOptions {
+            env: env,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `env`")
+    }
+    #[cfg(unix,target_os="android")]
+    fn run_env(env: Option<~[(~str, ~str)]>) -> run::Process {
+        run::Process::new("/system/bin/sh", [~"-c",~"set"], run::ProcessOptions {
+            env: env,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `/system/bin/sh`")
+    }
+
+    #[cfg(windows)]
+    fn run_env(env: Option<~[(~str, ~str)]>) -> run::Process {
+        run::Process::new("cmd", [~"/c", ~"set"], run::ProcessOptions {
+            env: env,
+            .. run::ProcessOptions::new()
+        }).expect("failed to run `cmd`")
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))]
+    fn test_inherit_env() {
+        if running_on_valgrind() { return; }
+
+        let mut prog = run_env(None);
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+
+        let r = os::env();
+        for &(ref k, ref v) in r.iter() {
+            // don't check windows magical empty-named variables
+            assert!(k.is_empty() || output.contains(format!("{}={}", *k, *v)));
+        }
+    }
+    #[test]
+    #[cfg(target_os="android")]
+    fn test_inherit_env() {
+        if running_on_valgrind() { return; }
+
+        let mut prog = run_env(None);
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+
+        let r = os::env();
+        for &(ref k, ref v) in r.iter() {
+            // don't check android RANDOM variables
+            if *k != ~"RANDOM" {
+                assert!(output.contains(format!("{}={}", *k, *v)) ||
+                        output.contains(format!("{}=\'{}\'", *k, *v)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_to_env() {
+
+        let mut new_env = os::env();
+        new_env.push((~"RUN_TEST_NEW_ENV", ~"123"));
+
+        let mut prog = run_env(Some(new_env));
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+
+        assert!(output.contains("RUN_TEST_NEW_ENV=123"));
+    }
+
+    #[cfg(unix,not(target_os="android"))]
+    fn run_env_with(opts: run::ProcessOptions) -> run::Process {
+        run::Process::new("env", [], opts).expect("failed to exec `env`")
+    }
+    #[cfg(unix,target_os="android")]
+    fn run_env_with(opts: run::ProcessOptions) -> run::Process {
+        run::Process::new("/system/bin/sh", [~"-c",~"set"], opts)
+            .expect("failed to exec `/system/bin/sh`")
+    }
+    #[cfg(windows)]
+    fn run_env_with(opts: run::ProcessOptions) -> run::Process {
+        run::Process::new("cmd", [~"/c", ~"set"], opts).expect("failed to run `cmd`")
+    }
+
+    // Runs `f` in a native task, as libuv cannot honor some of the options.
+    fn in_native_task(f: proc()) {
+        use native;
+        let (p, c) = Chan::new();
+        do native::task::spawn {
+            f();
+            c.send(());
+        }
+        p.recv();
+    }
+
+    // Runs `prog` with `args` and `opts`, returning what it printed once it
+    // has exited successfully.
+    fn output_of(prog: &str, args: &[~str], opts: run::ProcessOptions) -> ~str {
+        let mut process = match run::Process::new(prog, args, opts) {
+            Some(process) => process,
+            None => fail!("failed to exec `{}`", prog),
+        };
+        let output = process.finish_with_output();
+        assert!(output.status.success());
+        str::from_utf8_owned(output.output).unwrap()
+    }
+
+    #[cfg(unix,not(target_os="android"))]
+    fn run_pwd_with(opts: run::ProcessOptions) -> run::Process {
+        run::Process::new("pwd", [], opts).expect("failed to exec `pwd`")
+    }
+    #[cfg(unix,target_os="android")]
+    fn run_pwd_with(opts: run::ProcessOptions) -> run::Process {
+        run::Process::new("/system/bin/sh", [~"-c",~"pwd"], opts)
+            .expect("failed to exec `/system/bin/sh`")
+    }
+    #[cfg(windows)]
+    fn run_pwd_with(opts: run::ProcessOptions) -> run::Process {
+        run::Process::new("cmd", [~"/c", ~"cd"], opts).expect("failed to run `cmd`")
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_reap_in_background() {
+        use io::timer;
+
+        let prog = run::Process::new("true", [], run::ProcessOptions::new())
+            .expect("failed to exec `true`");
+        let pid = prog.get_id();
+        run::reap_in_background(prog);
+
+        let mut tries = 0;
+        while run::unreaped_children().contains(&pid) {
+            tries += 1;
+            assert!(tries < 1000, "detached child was never reaped");
+            timer::sleep(10);
+        }
+    }
+
+    #[test]
+    fn test_env_diff() {
+        let mut new_env = os::env();
+        new_env.push((~"RUN_TEST_NEW_ENV", ~"123"));
+
+        let opts = run::ProcessOptions { env: Some(new_env), .. run::ProcessOptions::new() };
+        assert_eq!(opts.env_diff(), ~[run::EnvAdded(~"RUN_TEST_NEW_ENV", ~"123")]);
+        assert_eq!(run::ProcessOptions::new().env_diff(), ~[]);
+    }
+
+    #[test]
+    fn test_env_deny() {
+        let mut new_env = os::env();
+        new_env.push((~"RUN_TEST_SECRET_TOKEN", ~"hunter2"));
+        new_env.push((~"RUN_TEST_KEPT", ~"123"));
+
+        let mut prog = run_env_with(run::ProcessOptions {
+            env: Some(new_env),
+            env_deny: ~[~"*_TOKEN"],
+            .. run::ProcessOptions::new()
+        });
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+
+        assert!(!output.contains("RUN_TEST_SECRET_TOKEN"));
+        assert!(output.contains("RUN_TEST_KEPT=123"));
+    }
+
+    #[test]
+    fn test_color_env() {
+        let mut prog = run_env_with(run::ProcessOptions {
+            color: run::ColorNever,
+            .. run::ProcessOptions::new()
+        });
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        assert!(output.contains("NO_COLOR=1"));
+
+        let mut prog = run_env_with(run::ProcessOptions {
+            color: run::ColorAlways,
+            .. run::ProcessOptions::new()
+        });
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        assert!(output.contains("CLICOLOR_FORCE=1"));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_color_never_strips_output() {
+        let mut prog = run::Process::new("printf", [~"\\033[31mred\\033[0m"],
+                                         run::ProcessOptions {
+            color: run::ColorNever,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `printf`");
+        let output = prog.finish_with_output();
+        assert_eq!(output.output, bytes!("red").to_owned());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_collapse_progress() {
+        let mut prog = run::Process::new("printf", [~"1%%\\r50%%\\r100%%\\ndone\\n"],
+                                         run::ProcessOptions {
+            collapse_progress: true,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `printf`");
+        let output = prog.finish_with_output();
+        assert_eq!(output.output, bytes!("100%\ndone\n").to_owned());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_progress_events() {
+        let (port, watcher) = run::ProgressWatcher::new(~[~"{done}/{total}"]);
+        let mut prog = run::Process::new("printf", [~"1/3\\r2/3\\r3/3\\n"],
+                                         run::ProcessOptions {
+            progress: Some(watcher),
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `printf`");
+        prog.finish_with_output();
+        for i in range(1, 4) {
+            let event = port.recv();
+            let done = i.to_str();
+            assert_eq!(event.get("done"), Some(done.as_slice()));
+            assert_eq!(event.get("total"), Some("3"));
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_finish_classified() {
+        let mut prog = run::Process::new("false", [], run::ProcessOptions::new())
+            .expect("failed to exec `false`");
+        assert_eq!(prog.finish_classified(), run::ExitFatal);
+
+        run::register_exit_codes("false", ~[run::ExitCode(1, run::ExitRetryable)]);
+        let mut prog = run::Process::new("false", [], run::ProcessOptions::new())
+            .expect("failed to exec `false`");
+        assert_eq!(prog.finish_classified(), run::ExitRetryable);
+        run::unregister_exit_codes("false");
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_check_output() {
+        let output = run::check_output("echo", [~"hello"]).unwrap();
+        assert_eq!(str::from_utf8_owned(output).unwrap().trim().to_owned(), ~"hello");
+
+        let err = run::check_output("sh", [~"-c", ~"echo oops >&2; exit 3"]).unwrap_err();
+        let detail = err.detail.unwrap();
+        assert!(detail.contains("sh -c \"echo oops >&2; exit 3\""));
+        assert!(detail.contains("exit code: 3"));
+        assert!(detail.contains("oops"));
+
+        assert!(run::check_output("no-binary-by-this-name-should-exist", []).is_err());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_follow_suspend() {
+        let mut prog = run::Process::new("true", [], run::ProcessOptions {
+            follow_suspend: true,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `true`");
+        assert!(prog.finish().success());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_spawn_attributes() {
+        do in_native_task {
+            let attrs = run::SpawnAttributes {
+                file_actions: ~[run::DupFd(1, 5)],
+                .. run::SpawnAttributes::new()
+            };
+            let output = output_of("sh", [~"-c", ~"echo hello >&5"], run::ProcessOptions {
+                spawn_attributes: Some(attrs),
+                .. run::ProcessOptions::new()
+            });
+            assert_eq!(output, ~"hello\n");
+
+            // A descriptor which is not open cannot be duplicated, which is
+            // reported by spawn rather than by the child.
+            let attrs = run::SpawnAttributes {
+                file_actions: ~[run::DupFd(1000, 5)],
+                .. run::SpawnAttributes::new()
+            };
+            let res = io::result(|| run::Process::new("true", [], run::ProcessOptions {
+                spawn_attributes: Some(attrs),
+                .. run::ProcessOptions::new()
+            }));
+            assert!(res.is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="macos")]
+    fn test_sandbox_profile() {
+        do in_native_task {
+            let profile = ~"(version 1) (allow default) (deny network*)";
+            let mut prog = run::Process::new("true", [], run::ProcessOptions {
+                sandbox_profile: Some(profile),
+                .. run::ProcessOptions::new()
+            }).expect("failed to exec `true`");
+            assert!(prog.finish().success());
+
+            let spawned = io::result(|| {
+                run::Process::new("true", [], run::ProcessOptions {
+                    sandbox_profile: Some(~"(not a profile"),
+                    .. run::ProcessOptions::new()
+                })
+            });
+            assert!(spawned.is_err());
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_expand_env() {
+        let env = ~[(~"RUN_TEST_VAR", ~"expanded")];
+        let mut prog = run::Process::new("echo", [~"${RUN_TEST_VAR}"], run::ProcessOptions {
+            env: Some(env.clone()),
+            expand_env: true,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `echo`");
+        assert_eq!(prog.finish_with_output().output, bytes!("expanded\n").to_owned());
+
+        let mut trapped_io_error = false;
+        let prog = io_error::cond.trap(|e| {
+            trapped_io_error = true;
+            assert_eq!(e.kind, InvalidInput);
+        }).inside(|| {
+            run::Process::new("echo", [~"${RUN_TEST_UNSET}"], run::ProcessOptions {
+                env: Some(env.clone()),
+                expand_env: true,
+                .. run::ProcessOptions::new()
+            })
+        });
+        assert!(trapped_io_error);
+        assert!(prog.is_none());
+    }
+
+    #[test]
+    fn test_create_cwd() {
+        use rand;
+        let dir = os::tmpdir().join(format!("rust-{}", rand::random::<u32>()));
+        let cwd = dir.join_many(["a", "b"]);
+        let mut prog = run_pwd_with(run::ProcessOptions {
+            dir: Some(&cwd),
+            create_cwd: true,
+            .. run::ProcessOptions::new()
+        });
+        assert!(prog.finish().success());
+        assert!(cwd.is_dir());
+        fs::rmdir_recursive(&dir);
+    }
+
+    #[test]
+    fn test_temp_cwd() {
+        let mut prog = run_pwd_with(run::ProcessOptions {
+            temp_cwd: true,
+            .. run::ProcessOptions::new()
+        });
+        let dir = prog.temp_cwd().unwrap().clone();
+        assert!(dir.is_dir());
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        let output_str = str::from_utf8_owned(output.output).unwrap();
+        let child_dir = Path::new(output_str.trim());
+        assert_eq!(child_dir.filename(), dir.filename());
+        assert!(!dir.exists());
+        assert!(prog.temp_cwd().is_none());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_keep_temp_cwd_on_failure() {
+        let mut prog = run::Process::new("false", [], run::ProcessOptions {
+            temp_cwd: true,
+            keep_temp_cwd_on_failure: true,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `false`");
+        let dir = prog.temp_cwd().unwrap().clone();
+        assert!(!prog.finish().success());
+        assert!(dir.is_dir());
+        fs::rmdir(&dir);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_finish_with_output_into() {
+        let mut output = bytes!("first\n").to_owned();
+        let mut error = ~[];
+        let mut prog = run::Process::new("echo", [~"second"], run::ProcessOptions::new())
+            .expect("failed to exec `echo`");
+        assert!(prog.finish_with_output_into(&mut output, &mut error).success());
+        assert_eq!(output, bytes!("first\nsecond\n").to_owned());
+    }
+
+    #[test]
+    fn test_validate() {
+        let dir = Path::new("/no-dir-by-this-name-should-exist");
+        let opts = run::ProcessOptions {
+            env: Some(~[(~"A=B", ~"1")]),
+            dir: Some(&dir),
+            .. run::ProcessOptions::new()
+        };
+        assert_eq!(opts.validate("no-binary-by-this-name-should-exist"),
+                   ~[run::ProgramNotFound(~"no-binary-by-this-name-should-exist"),
+                     run::CwdNotFound(dir.clone()),
+                     run::MalformedEnvVar(~"A=B")]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_spawn_checked() {
+        use rand;
+
+        match run::spawn_checked("no-binary-by-this-name-should-exist", [],
+                                 run::ProcessOptions::new()) {
+            Err(e) => assert_eq!(e.kind, FileNotFound),
+            Ok(..) => fail!("spawned a missing program"),
+        }
+
+        let path = os::tmpdir().join(format!("rust-{}", rand::random::<u32>()));
+        File::create(&path).write(bytes!("#!/bin/sh\n"));
+        match run::spawn_checked(path.as_str().unwrap(), [], run::ProcessOptions::new()) {
+            Err(e) => {
+                assert_eq!(e.kind, PermissionDenied);
+                assert_eq!(e.detail, Some(path.display().to_str()));
+            }
+            Ok(..) => fail!("spawned a program which is not executable"),
+        }
+        fs::unlink(&path);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_timeout_output() {
+        let mut prog = run::Process::new("cat", [], run::ProcessOptions::new())
+            .expect("failed to exec `cat`");
+        let mut output = prog.timeout_output();
+        let mut buf = [0u8, ..6];
+        match io::result(|| output.read_timeout(buf, 10)) {
+            Err(e) => assert_eq!(e.kind, TimedOut),
+            Ok(n) => fail!("read {:?} instead of timing out", n),
+        }
+        prog.input().write(bytes!("hello\n"));
+        prog.close_input();
+        assert_eq!(output.read_timeout(buf, 10000), Some(6));
+        assert_eq!(buf.as_slice(), bytes!("hello\n"));
+        assert!(prog.finish().success());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_buffered_output() {
+        use io::Buffer;
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo one; echo two >&2; echo three"],
+                                         run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        let mut output = prog.buffered_output();
+        let lines: ~[~str] = output.lines().collect();
+        assert_eq!(lines, ~[~"one\n", ~"three\n"]);
+        let mut error = prog.buffered_error();
+        assert_eq!(error.read_until('o' as u8), Some(bytes!("two").to_owned()));
+        assert!(prog.finish().success());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_output_chunks() {
+        let mut prog = run::Process::new("sh", [~"-c", ~"printf ab; sleep 0; printf cde"],
+                                         run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        let chunks: ~[~[u8]] = prog.output_chunks(2).collect();
+        assert_eq!(chunks, ~[bytes!("ab").to_owned(), bytes!("cd").to_owned(),
+                             bytes!("e").to_owned()]);
+        assert!(prog.finish().success());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_wait_for_output() {
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo starting; echo ready >&2; cat"],
+                                         run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        assert_eq!(prog.wait_for_output("ready", 10000), Some(~"ready"));
+        match io::result(|| prog.wait_for_output("never", 10)) {
+            Err(e) => assert_eq!(e.kind, TimedOut),
+            Ok(line) => fail!("got {:?} instead of timing out", line),
+        }
+        prog.close_input();
+        assert!(prog.finish().success());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_multiplexer() {
+        let mut mux = run::Multiplexer::new();
+        for word in ["one", "two"].iter() {
+            let cmd = format!("echo {0}; echo {0} >&2", *word);
+            let prog = run::Process::new("sh", [~"-c", cmd], run::ProcessOptions::new())
+                .expect("failed to exec `sh`");
+            mux.add(prog);
+        }
+        let mut outputs = [~[], ~[]];
+        let mut errors = [~[], ~[]];
+        let mut ends = 0;
+        for event in mux.by_ref() {
+            match event {
+                run::MuxData(i, run::ChildStdout, data) => outputs[i].push_all(data),
+                run::MuxData(i, run::ChildStderr, data) => errors[i].push_all(data),
+                run::MuxEnd(..) => ends += 1,
+            }
+        }
+        assert_eq!(ends, 4);
+        assert_eq!(outputs[0], bytes!("one\n").to_owned());
+        assert_eq!(errors[1], bytes!("two\n").to_owned());
+        for prog in mux.unwrap().mut_iter() {
+            assert!(prog.finish().success());
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_broadcast_writer() {
+        let mut progs = ~[];
+        let mut writer = run::BroadcastWriter::new(run::WaitForSlowest);
+        for _ in range(0, 2) {
+            let mut prog = run::Process::new("cat", [], run::ProcessOptions::new())
+                .expect("failed to exec `cat`");
+            writer.add_input(&mut prog);
+            progs.push(prog);
+        }
+        writer.write(bytes!("hello\n"));
+        assert!(writer.is_fed(0) && writer.is_fed(1));
+        drop(writer);
+        for prog in progs.mut_iter() {
+            let output = prog.finish_with_output();
+            assert!(output.status.success());
+            assert_eq!(output.output, bytes!("hello\n").to_owned());
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_fan_in_reader() {
+        let mut reader = run::FanInReader::lines();
+        let mut progs = ~[];
+        for word in ["one", "two"].iter() {
+            let mut prog = run::Process::new("echo", [word.to_owned()], run::ProcessOptions::new())
+                .expect("failed to exec `echo`");
+            reader.add_output(&mut prog);
+            progs.push(prog);
+        }
+        let output = str::from_utf8_owned(reader.read_to_end()).unwrap();
+        assert!(output == ~"one\ntwo\n" || output == ~"two\none\n");
+        for prog in progs.mut_iter() {
+            assert!(prog.finish().success());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_capabilities() {
+        do in_native_task {
+            use libc;
+            // Only root can bound its capabilities, and only root regains
+            // them when it executes the program unless it does.
+            let root = unsafe { libc::funcs::posix88::unistd::getuid() } == 0;
+            let caps = run::Capabilities { bound: root, .. run::Capabilities::new() };
+            let output = output_of("grep", [~"CapEff", ~"/proc/self/status"],
+                                   run::ProcessOptions {
+                capabilities: Some(caps),
+                .. run::ProcessOptions::new()
+            });
+            assert!(output.ends_with("0000000000000000\n"));
+
+            if root {
+                // Changing the user needs capabilities which are dropped
+                // afterwards.
+                let caps = run::Capabilities { bound: true, .. run::Capabilities::new() };
+                let mut prog = run::Process::new("true", [], run::ProcessOptions {
+                    capabilities: Some(caps),
+                    uid: Some(65534),
+                    gid: Some(65534),
+                    .. run::ProcessOptions::new()
+                }).expect("failed to exec `true`");
+                assert!(prog.finish().success());
+
+                let spawned = io::result(|| {
+                    run::Process::new("true", [], run::ProcessOptions {
+                        capabilities: Some(run::Capabilities::new()),
+                        .. run::ProcessOptions::new()
+                    })
+                });
+                match spawned {
+                    Err(e) => assert_eq!(e.kind, InvalidInput),
+                    Ok(..) => fail!("spawned a root child keeping its bounding set"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_no_new_privs() {
+        do in_native_task {
+            let output = output_of("grep", [~"NoNewPrivs", ~"/proc/self/status"],
+                                   run::ProcessOptions {
+                no_new_privs: true,
+                .. run::ProcessOptions::new()
+            });
+            assert!(output.ends_with("1\n"));
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_fs_isolation() {
+        do in_native_task {
+            use rand;
+            let name = format!("rust-{}", rand::random::<u32>());
+            let isolation = run::FsIsolation {
+                private_tmp: true,
+                read_only: ~[Path::new("/etc")],
+            };
+            let cmd = format!("touch /tmp/{} && ! touch /etc/{} 2>/dev/null", name, name);
+            let mut prog = run::Process::new("sh", [~"-c", cmd], run::ProcessOptions {
+                fs_isolation: Some(isolation),
+                .. run::ProcessOptions::new()
+            }).expect("failed to exec `sh`");
+            assert!(prog.finish().success());
+            assert!(!os::tmpdir().join(name.as_slice()).exists());
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_no_network() {
+        do in_native_task {
+            // Only the loopback interface, which is down, is left.
+            let output = output_of("cat", [~"/proc/net/dev"], run::ProcessOptions {
+                no_network: true,
+                .. run::ProcessOptions::new()
+            });
+            let interfaces: ~[&str] = output.lines().skip(2).collect();
+            assert_eq!(interfaces.len(), 1);
+            assert!(interfaces[0].trim_left().starts_with("lo:"));
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_reproducible() {
+        do in_native_task {
+            let dir = os::tmpdir();
+            let output = output_of("sh", [~"-c", ~"umask; echo $LC_ALL"], run::ProcessOptions {
+                dir: Some(&dir),
+                reproducible: true,
+                .. run::ProcessOptions::new()
+            });
+            assert_eq!(output.lines().collect::<~[&str]>(), ~["0022", "C"]);
+
+            let mut error = None;
+            io_error::cond.trap(|e| error = Some(e.kind)).inside(|| {
+                assert!(run::Process::new("true", [], run::ProcessOptions {
+                    reproducible: true,
+                    .. run::ProcessOptions::new()
+                }).is_none());
+            });
+            assert_eq!(error, Some(InvalidInput));
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_process_trace() {
+        run::enable_tracing(true);
+        let mut prog = run::Process::new("echo", [~"traced"], run::ProcessOptions::new())
+            .expect("failed to exec `echo`");
+        let pid = prog.get_id();
+        prog.finish();
+        run::enable_tracing(false);
+
+        let span = run::process_trace().move_iter().find(|s| s.pid == pid).unwrap();
+        assert_eq!(span.command, ~"echo traced");
+        assert_eq!(span.status, Some(ExitStatus(0)));
+        assert!(span.end_ns.unwrap() >= span.start_ns);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_destroy_signals() {
+        let mut opts = run::ProcessOptions::new();
+        opts.destroy_signal = libc::SIGINT as int;
+        let mut prog = run::Process::new("sleep", [~"10"], opts)
+            .expect("failed to exec `sleep`");
+        prog.destroy();
+        assert_eq!(prog.finish(), ExitSignal(libc::SIGINT as int));
+
+        // The shell only ignores SIGTERM once it has said so.
+        let mut opts = run::ProcessOptions::new();
+        opts.destroy_escalation = ~[(100, libc::SIGKILL as int)];
+        let mut prog = run::Process::new("sh", [~"-c", ~"trap '' TERM; echo ready; sleep 10"],
+                                         opts).expect("failed to exec `sh`");
+        let mut output = prog.buffered_output();
+        assert_eq!(output.read_line(), Some(~"ready\n"));
+        prog.destroy();
+        assert_eq!(prog.finish(), ExitSignal(libc::SIGKILL as int));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_handoff() {
+        do in_native_task {
+            let prog = run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+                .expect("failed to exec `sleep`");
+            let pid = prog.get_id();
+            let handoff = prog.handoff().to_str();
+
+            let handoff = from_str::<run::ProcessHandoff>(handoff).unwrap();
+            let mut prog = run::Process::adopt(&handoff).expect("failed to adopt `sleep`");
+            assert_eq!(prog.get_id(), pid);
+            prog.force_destroy();
+            assert_eq!(prog.finish(), ExitSignal(libc::SIGKILL as int));
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_pidfd() {
+        do in_native_task {
+            let mut prog = run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+                .expect("failed to exec `sleep`");
+            // Kernels older than 5.3 fall back to the id of the child.
+            for &fd in prog.get_pidfd().iter() {
+                assert!(fd >= 0);
+            }
+            prog.force_destroy();
+            assert_eq!(prog.finish(), ExitSignal(libc::SIGKILL as int));
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_env_set_remove() {
+        let mut env = os::env();
+        env.push((~"RUN_TEST_ENV_REMOVED", ~"1"));
+        let mut opts = run::ProcessOptions::new();
+        opts.env = Some(env);
+        opts.env_remove = ~[~"RUN_TEST_ENV_REMOVED"];
+        opts.env_set = ~[(~"RUN_TEST_ENV_SET", ~"2")];
+        let mut prog = run_env_with(opts);
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+
+        assert!(output.contains("RUN_TEST_ENV_SET=2"));
+        assert!(!output.contains("RUN_TEST_ENV_REMOVED"));
+        // Everything else is still passed on.
+        assert!(output.contains("PATH="));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_env_clear() {
+        let mut opts = run::ProcessOptions::new();
+        opts.env_clear = true;
+        opts.env_set = ~[(~"RUN_TEST_ENV_ONLY", ~"1")];
+        let mut prog = run_env_with(opts);
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        assert!(output.contains("RUN_TEST_ENV_ONLY=1"));
+        assert!(!output.contains("PATH="));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_uid_gid() {
+        use libc::funcs::posix88::unistd::{getuid, getgid};
+
+        let (uid, gid) = unsafe { (getuid() as uint, getgid() as uint) };
+        let mut opts = run::ProcessOptions::new();
+        opts.uid = Some(uid);
+        opts.gid = Some(gid);
+        let mut prog = run::Process::new("sh", [~"-c", ~"id -u; id -g"], opts)
+            .expect("failed to exec `sh`");
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        assert_eq!(output, format!("{}\n{}\n", uid, gid));
+
+        if uid != 0 {
+            do in_native_task {
+                let spawned = io::result(|| {
+                    let opts = run::ProcessOptions { uid: Some(0), .. run::ProcessOptions::new() };
+                    run::Process::new("true", [], opts)
+                });
+                match spawned {
+                    Err(e) => assert_eq!(e.kind, PermissionDenied),
+                    Ok(..) => fail!("became root without privileges"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_new_process_group() {
+        use libc::funcs::posix88::unistd::getpgrp;
+
+        fn child_pgid(opts: run::ProcessOptions) -> (libc::pid_t, ~str) {
+            let mut prog = run::Process::new("sh", [~"-c", ~"ps -o pgid= -p $$"], opts)
+                .expect("failed to exec `sh`");
+            let output = prog.finish_with_output().output;
+            (prog.get_id(), str::from_utf8_owned(output).unwrap().trim().to_owned())
+        }
+
+        let parent = unsafe { getpgrp() };
+        let (_, pgid) = child_pgid(run::ProcessOptions::new());
+        assert_eq!(pgid, parent.to_str());
+
+        let (pid, pgid) = child_pgid(run::ProcessOptions { new_session: true,
+                                                           .. run::ProcessOptions::new() });
+        assert_eq!(pgid, pid.to_str());
+
+        do in_native_task {
+            let (pid, pgid) = child_pgid(run::ProcessOptions {
+                new_process_group: true,
+                .. run::ProcessOptions::new()
+            });
+            assert_eq!(pgid, pid.to_str());
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_detached() {
+        let path = os::tmpdir().join(format!("run-test-detached-{}", unsafe { libc::getpid() }));
+        let script = format!("ps -o sid= -p $$ > {}", path.display());
+        let mut prog = run::Process::new("sh", [~"-c", script],
+                                         run::ProcessOptions { detached: true,
+                                                               .. run::ProcessOptions::new() })
+            .expect("failed to exec `sh`");
+        assert!(prog.finish().success());
+        let sid = File::open(&path).read_to_end();
+        fs::unlink(&path);
+        assert_eq!(str::from_utf8_owned(sid).unwrap().trim().to_owned(), prog.get_id().to_str());
+
+        // Dropping a detached process does not wait for it.
+        let prog = run::Process::new("sleep", [~"10"],
+                                     run::ProcessOptions { detached: true,
+                                                           .. run::ProcessOptions::new() })
+            .expect("failed to exec `sleep`");
+        let pid = prog.get_id();
+        drop(prog);
+        assert!(run::unreaped_children().contains(&pid));
+        unsafe { libc::funcs::posix88::signal::kill(pid, libc::SIGKILL); }
+
+        // It is reaped in the background once it has been killed.
+        let mut tries = 0;
+        while run::unreaped_children().contains(&pid) {
+            tries += 1;
+            assert!(tries < 1000, "dropped child was never reaped");
+            io::timer::sleep(10);
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_pty() {
+        do in_native_task {
+            let script = ~"test -t 0 && test -t 1 && read line && echo got $line";
+            let mut prog = run::Process::new("sh", [~"-c", script],
+                                             run::ProcessOptions { pty: true,
+                                                                   .. run::ProcessOptions::new() })
+                .expect("failed to exec `sh`");
+            prog.pty().write(bytes!("hello\n"));
+            let output = prog.finish_with_output();
+            assert!(output.status.success());
+            // The terminal echoes the input back, and ends lines with "\r\n".
+            let output = str::from_utf8_owned(output.output).unwrap();
+            assert!(output.contains("got hello\r\n"));
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_before_exec() {
+        use libc::funcs::posix88::unistd::chdir;
+
+        do in_native_task {
+            let root = "/".to_c_str();
+            let hook = || unsafe { root.with_ref(|p| chdir(p)) == 0 };
+            let output = output_of("pwd", [], run::ProcessOptions { before_exec: Some(hook),
+                                                                   .. run::ProcessOptions::new() });
+            assert_eq!(output, ~"/\n");
+
+            let missing = "/no/such/directory".to_c_str();
+            let spawned = io::result(|| {
+                let hook = || unsafe { missing.with_ref(|p| chdir(p)) == 0 };
+                run::Process::new("pwd", [], run::ProcessOptions { before_exec: Some(hook),
+                                                                   .. run::ProcessOptions::new() })
+            });
+            match spawned {
+                Err(e) => assert_eq!(e.kind, FileNotFound),
+                Ok(..) => fail!("spawned a process whose before_exec hook failed"),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_rlimits() {
+        do in_native_task {
+            let options = run::ProcessOptions { rlimits: ~[(run::OpenFiles, 64, 64),
+                                                            (run::CoreSize, 0, 0)],
+                                                .. run::ProcessOptions::new() };
+            assert_eq!(output_of("sh", [~"-c", ~"ulimit -n; ulimit -c"], options), ~"64\n0\n");
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_priority() {
+        do in_native_task {
+            let options = run::ProcessOptions { priority: Some(10),
+                                                .. run::ProcessOptions::new() };
+            assert_eq!(output_of("nice", [], options), ~"10\n");
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_cpu_affinity() {
+        do in_native_task {
+            let options = run::ProcessOptions { cpu_affinity: Some(~[0]),
+                                                .. run::ProcessOptions::new() };
+            let output = output_of("grep", [~"Cpus_allowed_list", ~"/proc/self/status"], options);
+            assert_eq!(output.words().last(), Some("0"));
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_chroot() {
+        use libc::funcs::posix88::unistd::getuid;
+
+        do in_native_task {
+            // The program does not exist inside of the new root, but only the
+            // superuser gets as far as looking for it.
+            let root = os::tmpdir();
+            let spawned = io::result(|| {
+                let opts = run::ProcessOptions { chroot: Some(root.clone()),
+                                                 .. run::ProcessOptions::new() };
+                run::Process::new("no-binary-by-this-name-should-exist", [], opts)
+            });
+            match spawned {
+                Err(e) if unsafe { getuid() } == 0 => assert_eq!(e.kind, FileNotFound),
+                Err(e) => assert_eq!(e.kind, PermissionDenied),
+                Ok(..) => fail!("spawned a program which does not exist"),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_namespaces() {
+        do in_native_task {
+            let namespaces = run::Namespaces { mount: true, pid: true, uts: true,
+                                               .. run::Namespaces::new() };
+            let script = ~"hostname sandbox && hostname && echo $$ && ls /proc | grep -c '^[0-9]'";
+            let output = output_of("sh", [~"-c", script], run::ProcessOptions {
+                namespaces: namespaces,
+                .. run::ProcessOptions::new()
+            });
+            // The shell is the first process of its namespace, in which only it
+            // and the commands it runs are seen.
+            let lines: ~[&str] = output.lines().collect();
+            assert_eq!(lines.slice_to(2).to_owned(), ~["sandbox", "1"]);
+            assert!(from_str::<uint>(lines[2]).unwrap() <= 3);
+
+            // Signals reach the program, rather than the process waiting for
+            // it, once the program handles them.
+            let script = ~"trap 'exit 3' HUP; echo ready; sleep 10 & wait";
+            let mut prog = run::Process::new("sh", [~"-c", script], run::ProcessOptions {
+                namespaces: run::Namespaces { pid: true, .. run::Namespaces::new() },
+                .. run::ProcessOptions::new()
+            }).expect("failed to exec `sh`");
+            assert_eq!(prog.stdout_lines().next(), Some(~"ready"));
+            prog.signal(libc::SIGHUP as int);
+            assert_eq!(prog.finish(), ExitStatus(3));
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_argv0() {
+        let options = run::ProcessOptions { argv0: Some(~"renamed"),
+                                            .. run::ProcessOptions::new() };
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo $0"], options)
+            .expect("failed to exec `sh`");
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        assert_eq!(str::from_utf8_owned(output.output).unwrap(), ~"renamed\n");
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_program_search() {
+        let spawned = io::result(|| {
+            let opts = run::ProcessOptions { program_search: run::RequirePath,
+                                             .. run::ProcessOptions::new() };
+            run::Process::new("sh", [~"-c", ~"true"], opts)
+        });
+        match spawned {
+            Err(e) => assert_eq!(e.kind, InvalidInput),
+            Ok(..) => fail!("searched the PATH for a program"),
+        }
+
+        // The PATH of the child does not matter, and the program keeps its
+        // name.
+        let options = run::ProcessOptions {
+            env: Some(~[(~"PATH", ~"/nonexistent")]),
+            program_search: run::SearchDirs(~[Path::new("/nonexistent"), Path::new("/bin")]),
+            .. run::ProcessOptions::new()
+        };
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo $0"], options)
+            .expect("failed to exec `sh`");
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        assert_eq!(str::from_utf8_owned(output.output).unwrap(), ~"sh\n");
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_umask() {
+        do in_native_task {
+            let options = run::ProcessOptions { umask: Some(0o077),
+                                                .. run::ProcessOptions::new() };
+            assert_eq!(output_of("sh", [~"-c", ~"umask"], options), ~"0077\n");
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_pass_fds() {
+        let pipe = os::pipe();
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo hello >&4; echo world"],
+                                         run::ProcessOptions {
+            pass_fds: ~[(pipe.out, 4)],
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `sh`");
+        unsafe { libc::close(pipe.out); }
+        let mut reader = PipeStream::open(pipe.input).unwrap();
+        assert_eq!(reader.read_to_end(), bytes!("hello\n").to_owned());
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        assert_eq!(output.output, bytes!("world\n").to_owned());
+
+        let mut error = None;
+        io_error::cond.trap(|e| error = Some(e.kind)).inside(|| {
+            assert!(run::Process::new("true", [], run::ProcessOptions {
+                pass_fds: ~[(0, 1)],
+                .. run::ProcessOptions::new()
+            }).is_none());
+        });
+        assert_eq!(error, Some(InvalidInput));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_inherit_fds() {
+        do in_native_task {
+            let pipe = os::pipe();
+            let test = format!("test -e /dev/fd/{}", pipe.out);
+            let inherited = |inherit_fds: bool| {
+                let mut prog = run::Process::new("sh", [~"-c", test.clone()],
+                                                 run::ProcessOptions {
+                    inherit_fds: inherit_fds,
+                    .. run::ProcessOptions::new()
+                }).expect("failed to exec `sh`");
+                prog.finish().success()
+            };
+            assert!(inherited(true));
+            assert!(!inherited(false));
+            unsafe {
+                libc::close(pipe.input);
+                libc::close(pipe.out);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_redirect_null() {
+        let mut prog = run::Process::new("sh", [~"-c", ~"cat; echo out; echo err >&2"],
+                                         run::ProcessOptions {
+            in_redirect: Some(run::RedirectNull),
+            err_redirect: Some(run::RedirectNull),
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `sh`");
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        assert_eq!(output.output, bytes!("out\n").to_owned());
+        assert_eq!(output.error, ~[]);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_redirect_file() {
+        let path = os::tmpdir().join(format!("run-test-redirect-{}", unsafe { libc::getpid() }));
+        let run_echo = |word: &str, redirect: run::Redirect| {
+            let mut prog = run::Process::new("echo", [word.to_owned()], run::ProcessOptions {
+                out_redirect: Some(redirect),
+                .. run::ProcessOptions::new()
+            }).expect("failed to exec `echo`");
+            assert!(prog.finish().success());
+        };
+        run_echo("one", run::RedirectTruncate(path.clone()));
+        run_echo("two", run::RedirectAppend(path.clone()));
+        assert_eq!(File::open(&path).read_to_end(), bytes!("one\ntwo\n").to_owned());
+        run_echo("three", run::RedirectTruncate(path.clone()));
+        assert_eq!(File::open(&path).read_to_end(), bytes!("three\n").to_owned());
+
+        let mut prog = run::Process::new("cat", [], run::ProcessOptions {
+            in_redirect: Some(run::RedirectTruncate(path.clone())),
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `cat`");
+        let output = prog.finish_with_output();
+        fs::unlink(&path);
+        assert_eq!(output.output, bytes!("three\n").to_owned());
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_stderr_to_stdout() {
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo a; echo b >&2; echo c"],
+                                         run::ProcessOptions {
+            stderr_to_stdout: true,
+            .. run::ProcessOptions::new()
+        }).expect("failed to exec `sh`");
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        assert_eq!(output.output, bytes!("a\nb\nc\n").to_owned());
+        assert_eq!(output.error, ~[]);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_process_output_with_input() {
+        // The input is larger than a pipe holds, and is echoed back while it
+        // is written.
+        let input = vec::from_elem(1024 * 1024, 'x' as u8);
+        let output = run::process_output_with_input("cat", [], input)
+            .expect("failed to exec `cat`");
+        assert!(output.status.success());
+        assert_eq!(output.output, input);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_raw_command_line() {
+        let mut prog = run::Process::new("cmd", [], run::ProcessOptions {
+            raw_command_line: Some(~"cmd /c echo \"a  b\""),
+            .. run::ProcessOptions::new()
+        }).expect("failed to run `cmd`");
+        let output = str::from_utf8_owned(prog.finish_with_output().output).unwrap();
+        assert_eq!(output.trim(), "\"a  b\"");
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_reset_signals() {
+        do in_native_task {
+            let output = run::process_output("grep", [~"^Sig[BI]", ~"/proc/self/status"])
+                .expect("failed to exec `grep`");
+            assert!(output.status.success());
+            let output = str::from_utf8_owned(output.output).unwrap();
+            for line in output.lines() {
+                assert!(line.ends_with("0000000000000000"), "not reset: {}", line);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_vfork() {
+        do in_native_task {
+            let options = run::ProcessOptions {
+                vfork: true,
+                env: Some(~[(~"PATH", ~"/bin:/usr/bin"), (~"GREETING", ~"hello")]),
+                .. run::ProcessOptions::new()
+            };
+            assert_eq!(output_of("sh", [~"-c", ~"echo $GREETING"], options), ~"hello\n");
+
+            let spawned = io::result(|| {
+                let options = run::ProcessOptions { vfork: true,
+                                                    .. run::ProcessOptions::new() };
+                run::Process::new("/nonexistent/program", [], options)
+            });
+            assert!(spawned.is_err());
+
+            let spawned = io::result(|| {
+                let options = run::ProcessOptions { vfork: true, umask: Some(0o077),
+                                                    .. run::ProcessOptions::new() };
+                run::Process::new("true", [], options)
+            });
+            match spawned {
+                Err(e) => assert_eq!(e.kind, InvalidInput),
+                Ok(..) => fail!("set up a vforked child"),
+            }
+
+            // The directory could only be changed by the child.
+            let root = Path::new("/");
+            let spawned = io::result(|| {
+                let options = run::ProcessOptions { vfork: true, dir: Some(&root),
+                                                    .. run::ProcessOptions::new() };
+                run::Process::new("true", [], options)
+            });
+            match spawned {
+                Err(e) => assert_eq!(e.kind, InvalidInput),
+                Ok(..) => fail!("changed the directory of a vforked child"),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_no_ctty() {
+        let options = run::ProcessOptions { no_ctty: true, .. run::ProcessOptions::new() };
+        let script = ~"(: < /dev/tty) 2>/dev/null && echo tty || echo none";
+        let mut prog = run::Process::new("sh", [~"-c", script], options)
+            .expect("failed to exec `sh`");
+        let output = prog.finish_with_output();
+        assert!(output.status.success());
+        assert_eq!(str::from_utf8_owned(output.output).unwrap(), ~"none\n");
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_try_finish() {
+        use io::timer;
+
+        let mut prog = run::Process::new("cat", [], run::ProcessOptions::new())
+            .expect("failed to exec `cat`");
+        assert_eq!(prog.try_finish(), None);
+        prog.close_input();
+        let mut tries = 0;
+        loop {
+            match prog.try_finish() {
+                Some(status) => {
+                    assert!(status.success());
+                    break;
+                }
+                None => {
+                    tries += 1;
+                    assert!(tries < 1000, "child was never finished");
+                    timer::sleep(10);
+                }
+            }
+        }
+        assert!(prog.try_finish().unwrap().success());
+        assert!(prog.finish().success());
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_finish_with_timeout() {
+        let mut prog = run::Process::new("cat", [], run::ProcessOptions::new())
+            .expect("failed to exec `cat`");
+        assert_eq!(prog.finish_with_timeout(10), None);
+        prog.close_input();
+        assert!(prog.finish_with_timeout(10000).unwrap().success());
+
+        do in_native_task {
+            let mut prog = run::Process::new("cat", [], run::ProcessOptions::new())
+                .expect("failed to exec `cat`");
+            assert_eq!(prog.finish_with_timeout(10), None);
+            prog.close_input();
+            assert!(prog.finish_with_timeout(10000).unwrap().success());
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_kill_on_drop() {
+        let prog = run::Process::new("sleep", [~"10"],
+                                     run::ProcessOptions { drop_policy: run::KillOnDrop,
+                                                           .. run::ProcessOptions::new() })
+            .expect("failed to exec `sleep`");
+        let pid = prog.get_id();
+        drop(prog);
+        // The child was killed and reaped.
+        assert_eq!(unsafe { libc::funcs::posix88::signal::kill(pid, 0) }, -1);
+    }
+
+    #[test]
+    #[cfg(not(target_os="android"))] // FIXME(#10380)
+    fn test_detach() {
+        use io::timer;
+
+        // The child reads its input until it is closed.
+        let prog = run::Process::new("cat", [], run::ProcessOptions::new())
+            .expect("failed to exec `cat`");
+        let pid = prog.get_id();
+        prog.detach();
+
+        let mut tries = 0;
+        while run::unreaped_children().contains(&pid) {
+            tries += 1;
+            assert!(tries < 1000, "detached child was never reaped");
+            timer::sleep(10);
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_signal() {
+        let mut prog = run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+            .expect("failed to exec `sleep`");
+        prog.signal(libc::SIGHUP as int);
+        assert_eq!(prog.finish(), ExitSignal(libc::SIGHUP as int));
+
+        do in_native_task {
+            let mut prog = run::Process::new("true", [], run::ProcessOptions::new())
+                .expect("failed to exec `true`");
+            prog.finish();
+            let mut failed = false;
+            io_error::cond.trap(|_| failed = true).inside(|| prog.signal(libc::SIGHUP as int));
+            assert!(failed);
+        }
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_pause_resume() {
+        use io::timer;
+
+        // Waits for the state of the process in /proc to become `state`.
+        fn wait_for_state(pid: libc::pid_t, state: &str) {
+            let path = Path::new(format!("/proc/{}/stat", pid));
+            let mut tries = 0;
+            loop {
+                let stat = str::from_utf8_owned(File::open(&path).read_to_end()).unwrap();
+                let fields = stat.slice_from(stat.rfind(')').unwrap() + 1);
+                if fields.words().next() == Some(state) {
+                    break;
+                }
+                tries += 1;
+                assert!(tries < 1000, "process never reached state {}", state);
+                timer::sleep(10);
+            }
+        }
+
+        let mut prog = run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+            .expect("failed to exec `sleep`");
+        prog.pause();
+        wait_for_state(prog.get_id(), "T");
+        prog.resume();
+        wait_for_state(prog.get_id(), "S");
+        prog.force_destroy();
+    }
+
+    #[test]
+    #[cfg(target_os="linux")]
+    fn test_destroy_tree() {
+        use io::timer;
+
+        let mut prog = run::Process::new("sh", [~"-c", ~"sleep 100 & echo $!; wait"],
+                                         run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        let line = prog.buffered_output().read_line().unwrap();
+        let pid: libc::pid_t = from_str(line.trim()).unwrap();
+        prog.destroy_tree();
+
+        // The grandchild is reaped by init once it is killed.
+        let path = Path::new(format!("/proc/{}/stat", pid));
+        let mut tries = 0;
+        while path.exists() {
+            tries += 1;
+            assert!(tries < 1000, "grandchild {} kept running", pid);
+            timer::sleep(10);
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_finish_with_rusage() {
+        do in_native_task {
+            let script = ~"i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done";
+            let mut prog = run::Process::new("sh", [~"-c", script], run::ProcessOptions::new())
+                .expect("failed to exec `sh`");
+            let (status, rusage) = prog.finish_with_rusage();
+            assert!(status.success());
+            let rusage = rusage.expect("no resource usage for a native child");
+            assert!(rusage.user_time + rusage.system_time > 0);
+            assert!(rusage.max_rss > 0);
+        }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_output_timing() {
+        use super::clock;
+
+        let before = clock::wall_time_ms();
+        let output = run::process_output("sleep", [~"0.2"]).expect("failed to exec `sleep`");
+        assert!(output.status.success());
+        assert!(output.spawn_time_ms >= before);
+        assert!(output.spawn_time_ms <= clock::wall_time_ms());
+        assert!(output.elapsed_ns >= 150000000);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_wait_any() {
+        fn check() {
+            let mut procs = ~[
+                run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+                    .expect("failed to exec `sleep`"),
+                run::Process::new("sh", [~"-c", ~"exit 3"], run::ProcessOptions::new())
+                    .expect("failed to exec `sh`"),
+            ];
+            let (i, status) = run::wait_any(procs.as_mut_slice()).unwrap();
+            assert_eq!(i, 1);
+            assert!(status.matches_exit_status(3));
+            procs[0].force_destroy();
+            let mut none: ~[run::Process] = ~[];
+            assert!(run::wait_any(none.as_mut_slice()).is_none());
+        }
+        check();
+        do in_native_task { check() }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_exit_port() {
+        use comm::Select;
+
+        let prog = run::Process::new("sh", [~"-c", ~"exit 4"], run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        let mut exited = prog.exit_port();
+        let (mut other, _chan) = Chan::<()>::new();
+
+        let select = Select::new();
+        let mut exited_handle = select.add(&mut exited);
+        let _other_handle = select.add(&mut other);
+        let ret = select.wait();
+        assert_eq!(ret, exited_handle.id);
+        assert!(exited_handle.recv().matches_exit_status(4));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_shutdown() {
+        let mut prog = run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+            .expect("failed to exec `sleep`");
+        assert_eq!(prog.shutdown(10000), ExitSignal(libc::SIGTERM as int));
+        assert_eq!(prog.shutdown(10000), ExitSignal(libc::SIGTERM as int));
+
+        let mut prog = run::Process::new("sh", [~"-c", ~"trap '' TERM; echo ready; sleep 10"],
+                                         run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        assert_eq!(prog.buffered_output().read_line(), Some(~"ready\n"));
+        assert_eq!(prog.shutdown(100), ExitSignal(libc::SIGKILL as int));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_on_exit() {
+        fn check() {
+            let (port, chan) = SharedChan::new();
+            for i in range(0, 10) {
+                let prog = run::Process::new("sh", [~"-c", format!("exit {}", i)],
+                                             run::ProcessOptions::new())
+                    .expect("failed to exec `sh`");
+                let chan = chan.clone();
+                prog.on_exit(proc(status) { chan.send((i, status)) });
+            }
+            for _ in range(0, 10) {
+                let (i, status) = port.recv();
+                assert!(status.matches_exit_status(i));
+            }
+        }
+        check();
+        do in_native_task { check() }
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_supervisor() {
+        use run::{Supervisor, ChildSpec, ChildStarted, ChildExited, ChildRetired};
+
+        let command = run::Command::new("sh").arg("-c").arg("exit 3");
+        let mut failing = ChildSpec::new("failing", command);
+        failing.backoff_ms = 1;
+        failing.max_restarts = Some(1);
+        let mut sup = Supervisor::start(~[failing]);
+        for _ in range(0, 2) {
+            match sup.events().recv() {
+                ChildStarted(name, _) => assert_eq!(name, ~"failing"),
+                event => fail!("unexpected event {:?}", event),
+            }
+            assert_eq!(sup.events().recv(), ChildExited(~"failing", ExitStatus(3)));
+        }
+        assert_eq!(sup.events().recv(), ChildRetired(~"failing"));
+        assert!(sup.events().recv_opt().is_none());
+        sup.stop(0);
+
+        let sleeper = ChildSpec::new("sleeper", run::Command::new("sleep").arg("10"));
+        let mut sup = Supervisor::start(~[sleeper]);
+        match sup.events().recv() {
+            ChildStarted(name, _) => assert_eq!(name, ~"sleeper"),
+            event => fail!("unexpected event {:?}", event),
+        }
+        sup.stop(10000);
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_is_alive() {
+        use io::timer;
+
+        let mut prog = run::Process::new("sleep", [~"10"], run::ProcessOptions::new())
+            .expect("failed to exec `sleep`");
+        let pid = prog.get_id();
+        assert!(prog.is_alive());
+        do in_native_task {
+            assert!(run::is_alive(pid));
+        }
+        prog.signal(libc::SIGKILL as int);
+        assert_eq!(prog.finish(), ExitSignal(libc::SIGKILL as int));
+        assert!(!prog.is_alive());
+
+        let mut prog = run::Process::new("true", [], run::ProcessOptions::new())
+            .expect("failed to exec `true`");
+        // The child is reported as gone before it is reaped.
+        let mut tries = 0;
+        while prog.is_alive() {
+            tries += 1;
+            assert!(tries < 1000, "the child never exited");
+            timer::sleep(10);
+        }
+        assert_eq!(prog.finish(), ExitStatus(0));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_exit_status_helpers() {
+        let status = run::process_status("sh", [~"-c", ~"exit 2"]).expect("failed to exec `sh`");
+        assert_eq!(status, run::ExitStatus(2));
+        assert_eq!(status.code(), Some(2));
+        assert_eq!(status.signal(), None);
+
+        let status = run::process_status("sh", [~"-c", ~"kill -9 $$"])
+            .expect("failed to exec `sh`");
+        assert_eq!(status, run::ExitSignal(9));
+        assert_eq!(status.code(), None);
+        assert_eq!(status.signal(), Some(9));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_stdout_lines() {
+        let mut prog = run::Process::new("sh", [~"-c", ~"echo 10%; read x; echo 100%; printf done"],
+                                         run::ProcessOptions::new())
+            .expect("failed to exec `sh`");
+        let mut lines = prog.stdout_lines();
+        // The first line arrives while the child is still waiting for input.
+        assert_eq!(lines.next(), Some(~"10%"));
+        prog.input().write(bytes!("\n"));
+        assert_eq!(lines.collect::<~[~str]>(), ~[~"100%", ~"done"]);
+        assert!(prog.finish().success());
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading the output of many children from a single task
+
+use comm::SharedChan;
+use io::Reader;
+use io;
+use prelude::*;
+use vec;
+
+use super::Process;
+
+/// An output stream of a child.
+#[deriving(Clone, Eq)]
+pub enum ChildStream {
+    ChildStdout,
+    ChildStderr,
+}
+
+/// An event of a `Multiplexer`, about the child with the given index.
+#[deriving(Clone, Eq)]
+pub enum MuxEvent {
+    /// Output was read from a stream of the child.
+    MuxData(uint, ChildStream, ~[u8]),
+
+    /// A stream of the child ended.
+    MuxEnd(uint, ChildStream),
+}
+
+/**
+ * A set of children whose stdout and stderr are all read at once, so that a
+ * single task can consume the output of many children without any of them
+ * blocking on a full pipe.
+ *
+ * The multiplexer is an iterator over the events of all of the streams of
+ * its children, in the order in which they happen. The iteration ends once
+ * every stream has ended.
+ */
+pub struct Multiplexer {
+    priv port: Port<MuxEvent>,
+    priv chan: SharedChan<MuxEvent>,
+    priv children: ~[Process],
+    priv open: uint,
+}
+
+impl Multiplexer {
+    /// Creates a new multiplexer without any children.
+    pub fn new() -> Multiplexer {
+        let (port, chan) = SharedChan::new();
+        Multiplexer { port: port, chan: chan, children: ~[], open: 0 }
+    }
+
+    /// Adds `process` to the children, starting to read its stdout and
+    /// stderr unless they have been taken already. Returns the index by which
+    /// the events refer to the child.
+    pub fn add(&mut self, mut process: Process) -> uint {
+        let index = self.children.len();
+        let (output, error) = process.take_outputs();
+        for stream in output.move_iter() {
+            self.read(stream, index, ChildStdout);
+        }
+        for stream in error.move_iter() {
+            self.read(stream, index, ChildStderr);
+        }
+        self.children.push(process);
+        index
+    }
+
+    /// Returns the child with the given index, for example to finish it once
+    /// its streams have ended.
+    pub fn get_mut<'a>(&'a mut self, index: uint) -> &'a mut Process {
+        &mut self.children[index]
+    }
+
+    /// Returns the children, in the order of their indices.
+    pub fn unwrap(self) -> ~[Process] {
+        let Multiplexer { children, .. } = self;
+        children
+    }
+
+    fn read(&mut self, stream: io::PipeStream, index: uint, which: ChildStream) {
+        self.open += 1;
+        let chan = self.chan.clone();
+        do spawn {
+            let _guard = io::ignore_io_error();
+            let mut stream = stream;
+            let mut buf = vec::from_elem(64 * 1024, 0u8);
+            // Keep reading once the multiplexer is gone, so that the child
+            // never blocks on a full pipe.
+            let mut watched = true;
+            loop {
+                match stream.read(buf) {
+                    Some(n) => {
+                        if watched {
+                            let data = buf.slice_to(n).to_owned();
+                            watched = chan.try_send(MuxData(index, which, data));
+                        }
+                    }
+                    None => break,
+                }
+            }
+            chan.try_send(MuxEnd(index, which));
+        }
+    }
+}
+
+impl Iterator<MuxEvent> for Multiplexer {
+    fn next(&mut self) -> Option<MuxEvent> {
+        if self.open == 0 {
+            return None
+        }
+        let event = self.port.recv();
+        match event {
+            MuxEnd(..) => self.open -= 1,
+            MuxData(..) => {}
+        }
+        Some(event)
+    }
+}
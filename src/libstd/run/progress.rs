@@ -0,0 +1,236 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Extraction of progress reports from the output of a child
+//!
+//! Progress is described with simple line patterns. Literal text in a pattern
+//! has to match exactly, `*` matches any (possibly empty) run of characters
+//! and `{name}` captures a non-empty run of non-whitespace characters under
+//! the given name. A pattern has to match a whole line, so for example
+//!
+//!     *{percent}%*
+//!
+//! extracts the percentage out of a line of `rsync --progress` output, and
+//!
+//!     Compiling {file} *
+//!
+//! extracts the name of the crate being built by a build tool.
+//!
+//! Both `\n` and `\r` terminate a line, so that tools which redraw a single
+//! progress line report every update.
+
+use comm::SharedChan;
+use prelude::*;
+use str;
+
+/// A progress report extracted from one line of output.
+#[deriving(Clone, Eq)]
+pub struct ProgressEvent {
+    /// The index of the pattern which matched the line.
+    pattern: uint,
+
+    /// The values captured by the pattern, by name.
+    fields: ~[(~str, ~str)],
+}
+
+impl ProgressEvent {
+    /// Returns the value captured under `name`, if any.
+    pub fn get<'a>(&'a self, name: &str) -> Option<&'a str> {
+        for &(ref k, ref v) in self.fields.iter() {
+            if k.as_slice() == name {
+                return Some(v.as_slice());
+            }
+        }
+        None
+    }
+}
+
+/// Matches the output of a child against a set of patterns, sending a
+/// `ProgressEvent` for each line which matches one of them.
+#[deriving(Clone)]
+pub struct ProgressWatcher {
+    priv patterns: ~[~str],
+    priv chan: SharedChan<ProgressEvent>,
+}
+
+impl ProgressWatcher {
+    /// Creates a new watcher for the given patterns, along with the port on
+    /// which its events are received.
+    pub fn new(patterns: ~[~str]) -> (Port<ProgressEvent>, ProgressWatcher) {
+        let (port, chan) = SharedChan::new();
+        (port, ProgressWatcher { patterns: patterns, chan: chan })
+    }
+
+    /// Matches a single line of output against the patterns, sending an
+    /// event for the first pattern which matches it.
+    pub fn feed(&self, line: &str) {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            match match_line(*pattern, line) {
+                Some(fields) => {
+                    self.chan.try_send(ProgressEvent {
+                        pattern: i,
+                        fields: fields,
+                    });
+                    return
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// A `Reader` which passes the data of the stream it wraps through unchanged,
+/// feeding every line of it to a `ProgressWatcher` on the way.
+pub struct ProgressReader<R> {
+    priv inner: R,
+    priv watcher: ProgressWatcher,
+    priv line: ~[u8],
+}
+
+impl<R: Reader> ProgressReader<R> {
+    /// Creates a new reader which reports the progress found in `inner`.
+    pub fn new(inner: R, watcher: ProgressWatcher) -> ProgressReader<R> {
+        ProgressReader { inner: inner, watcher: watcher, line: ~[] }
+    }
+
+    fn flush_line(&mut self) {
+        if !self.line.is_empty() {
+            match str::from_utf8(self.line) {
+                Some(line) => self.watcher.feed(line),
+                None => {}
+            }
+            self.line.truncate(0);
+        }
+    }
+}
+
+impl<R: Reader> Reader for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
+        match self.inner.read(buf) {
+            Some(n) => {
+                for &b in buf.slice_to(n).iter() {
+                    if b == '\n' as u8 || b == '\r' as u8 {
+                        self.flush_line();
+                    } else {
+                        self.line.push(b);
+                    }
+                }
+                Some(n)
+            }
+            None => {
+                self.flush_line();
+                None
+            }
+        }
+    }
+}
+
+enum Token<'a> {
+    Literal(&'a str),
+    Capture(&'a str),
+    Any,
+}
+
+fn tokenize<'a>(pattern: &'a str) -> ~[Token<'a>] {
+    let mut tokens = ~[];
+    let mut rest = pattern;
+    while !rest.is_empty() {
+        if rest.starts_with("*") {
+            tokens.push(Any);
+            rest = rest.slice_from(1);
+        } else if rest.starts_with("{") && rest.find('}').is_some() {
+            let end = rest.find('}').unwrap();
+            tokens.push(Capture(rest.slice(1, end)));
+            rest = rest.slice_from(end + 1);
+        } else {
+            let end = rest.find(|c: char| c == '*' || c == '{').unwrap_or(rest.len());
+            let end = if end == 0 { 1 } else { end };
+            tokens.push(Literal(rest.slice_to(end)));
+            rest = rest.slice_from(end);
+        }
+    }
+    tokens
+}
+
+/// Matches `line` against `pattern`, returning the captured fields if it
+/// matches.
+pub fn match_line(pattern: &str, line: &str) -> Option<~[(~str, ~str)]> {
+    let tokens = tokenize(pattern);
+    let mut fields = ~[];
+    if match_tokens(tokens, line, &mut fields) {
+        Some(fields)
+    } else {
+        None
+    }
+}
+
+fn match_tokens(tokens: &[Token], line: &str, fields: &mut ~[(~str, ~str)]) -> bool {
+    if tokens.is_empty() {
+        return line.is_empty();
+    }
+    match tokens[0] {
+        Literal(lit) => {
+            line.starts_with(lit) &&
+                match_tokens(tokens.slice_from(1), line.slice_from(lit.len()), fields)
+        }
+        Any => {
+            range(0, line.len() + 1).any(|i| {
+                line.is_char_boundary(i) &&
+                    match_tokens(tokens.slice_from(1), line.slice_from(i), fields)
+            })
+        }
+        Capture(name) => {
+            let max = line.find(|c: char| c.is_whitespace()).unwrap_or(line.len());
+            for i in range(1, max + 1) {
+                if !line.is_char_boundary(i) { continue }
+                fields.push((name.to_owned(), line.slice_to(i).to_owned()));
+                if match_tokens(tokens.slice_from(1), line.slice_from(i), fields) {
+                    return true;
+                }
+                fields.pop();
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use io::mem::MemReader;
+    use prelude::*;
+    use super::{match_line, ProgressWatcher, ProgressReader};
+
+    #[test]
+    fn test_match_line() {
+        assert_eq!(match_line("*{percent}%*", "  12,345  50%  1.2MB/s"),
+                   Some(~[(~"percent", ~"50")]));
+        assert_eq!(match_line("Compiling {file} *", "Compiling std v0.10"),
+                   Some(~[(~"file", ~"std")]));
+        assert_eq!(match_line("Compiling {file} *", "Linking std"), None);
+        assert_eq!(match_line("{a}/{b}", "3/10"), Some(~[(~"a", ~"3"), (~"b", ~"10")]));
+        assert_eq!(match_line("{a}", ""), None);
+    }
+
+    #[test]
+    fn test_progress_reader() {
+        let (port, watcher) = ProgressWatcher::new(~[~"*{percent}%", ~"file: {file}"]);
+        let input = bytes!("file: a.rs\n10%\r100%\nother\n").to_owned();
+        let mut reader = ProgressReader::new(MemReader::new(input.clone()), watcher);
+        assert_eq!(reader.read_to_end(), input);
+
+        let event = port.recv();
+        assert_eq!(event.pattern, 1);
+        assert_eq!(event.get("file"), Some("a.rs"));
+        assert_eq!(port.recv().get("percent"), Some("10"));
+        assert_eq!(port.recv().get("percent"), Some("100"));
+        drop(reader);
+        assert!(port.recv_opt().is_none());
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Redirections of the standard streams of a child
+//!
+//! A redirected stream is connected to a descriptor which the current process
+//! opens right before the child is spawned and closes right after, so that
+//! the caller neither pumps a pipe nor keeps track of the descriptor.
+
+use io;
+use libc::c_int;
+use libc;
+use os;
+use prelude::*;
+
+/// Where a standard stream of a child is connected to instead of a pipe to
+/// the current process.
+#[deriving(Clone, Eq)]
+pub enum Redirect {
+    /// The null device, `/dev/null` on Unix and `NUL` on Windows, which
+    /// discards what is written to it and is at its end when it is read.
+    RedirectNull,
+
+    /// The file at the given path, which an output stream truncates, or
+    /// creates if it does not exist yet.
+    RedirectTruncate(Path),
+
+    /// The file at the given path, which an output stream appends to, or
+    /// creates if it does not exist yet, so that several children can log to
+    /// it in turn.
+    RedirectAppend(Path),
+}
+
+/// Opens the descriptor a stream redirected to `redirect` is connected to,
+/// for reading if `input` is true, in which case a file is neither created
+/// nor truncated. If it cannot be opened, then the `io_error` condition is
+/// raised and None is returned. The caller closes it once the child has been
+/// spawned.
+pub fn open(redirect: &Redirect, input: bool) -> Option<c_int> {
+    let (path, flags) = match *redirect {
+        RedirectNull => return open_null(),
+        RedirectTruncate(ref path) | RedirectAppend(ref path) if input => {
+            (path, libc::O_RDONLY)
+        }
+        RedirectTruncate(ref path) => (path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC),
+        RedirectAppend(ref path) => (path, libc::O_WRONLY | libc::O_CREAT | libc::O_APPEND),
+    };
+    let fd = path.with_c_str(|p| unsafe { libc::open(p, flags, 0o666) });
+    if fd == -1 {
+        io::io_error::cond.raise(io::IoError {
+            kind: io::OtherIoError,
+            desc: "couldn't open the file a stream is redirected to",
+            detail: Some(format!("{}: {}", path.display(), os::last_os_error())),
+        });
+        None
+    } else {
+        Some(fd)
+    }
+}
+
+fn open_null() -> Option<c_int> {
+    let path = if cfg!(windows) { "NUL" } else { "/dev/null" };
+    let fd = path.with_c_str(|p| unsafe { libc::open(p, libc::O_RDWR, 0) });
+    if fd == -1 {
+        io::io_error::cond.raise(io::IoError {
+            kind: io::OtherIoError,
+            desc: "couldn't open the null device",
+            detail: Some(os::last_os_error()),
+        });
+        None
+    } else {
+        Some(fd)
+    }
+}
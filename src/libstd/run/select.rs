@@ -0,0 +1,90 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for the first of several children to exit
+//!
+//! A task running many children at once, such as a parallel test runner,
+//! wants to hear of whichever of them exits first, without dedicating a task
+//! to each child only to block in `finish`.
+
+use cmp;
+use io::process::ProcessExit;
+use io::timer;
+use prelude::*;
+
+use super::Process;
+
+/**
+ * Waits until any of `processes` terminates, and finishes it as if by
+ * `try_finish`.
+ *
+ * A process which has been finished already is returned right away, so it
+ * should be removed from `processes` before waiting for the others.
+ *
+ * In native tasks on Linux 5.3 and later the task blocks on the pidfds of
+ * the children, and otherwise the children are polled at growing intervals
+ * of up to a tenth of a second.
+ *
+ * # Return value
+ *
+ * The index of the process in `processes` along with its exit code, or None
+ * if `processes` is empty.
+ */
+pub fn wait_any(processes: &mut [Process]) -> Option<(uint, ProcessExit)> {
+    if processes.is_empty() {
+        return None;
+    }
+    let mut delay = 1;
+    loop {
+        for (i, p) in processes.mut_iter().enumerate() {
+            match p.try_finish() {
+                Some(status) => return Some((i, status)),
+                None => {}
+            }
+        }
+        if !block(processes) {
+            timer::sleep(delay);
+            delay = cmp::min(delay * 2, 100);
+        }
+    }
+}
+
+/// Blocks until the pidfd of any of `processes` becomes readable, which it
+/// does once the child exits, returning false if any of them has no pidfd.
+#[cfg(target_os = "linux")]
+fn block(processes: &[Process]) -> bool {
+    use libc::{c_int, c_ulong};
+
+    struct pollfd {
+        fd: c_int,
+        events: i16,
+        revents: i16,
+    }
+
+    extern {
+        fn poll(fds: *mut pollfd, nfds: c_ulong, timeout: c_int) -> c_int;
+    }
+
+    static POLLIN: i16 = 1;
+
+    let mut fds = ~[];
+    for p in processes.iter() {
+        match p.get_pidfd() {
+            Some(fd) => fds.push(pollfd { fd: fd, events: POLLIN, revents: 0 }),
+            None => return false,
+        }
+    }
+    // An interrupted poll only means that the children are checked again.
+    unsafe { poll(fds.as_mut_ptr(), fds.len() as c_ulong, -1); }
+    true
+}
+
+#[cfg(not(target_os = "linux"))]
+fn block(_processes: &[Process]) -> bool { false }
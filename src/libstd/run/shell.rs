@@ -0,0 +1,104 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The shell of the system, which runs command lines given as strings
+
+use io::process::ProcessExit;
+use prelude::*;
+
+use super::{Command, ProcessOutput};
+
+/// A shell along with the argument convention it is given a command line
+/// with.
+#[deriving(Clone, Eq)]
+pub struct Shell {
+    /// The path to the shell.
+    program: &'static str,
+
+    /// The flag which precedes the command line.
+    command_flag: &'static str,
+}
+
+impl Shell {
+    /// Returns the arguments which make the shell run `command_line`.
+    pub fn args(&self, command_line: &str) -> ~[~str] {
+        ~[self.command_flag.to_owned(), command_line.to_owned()]
+    }
+
+    /// Returns a command which runs `command_line` with the shell.
+    pub fn command(&self, command_line: &str) -> Command {
+        Command::new(self.program).args(self.args(command_line))
+    }
+}
+
+/// Returns the shell of the system: `/bin/sh -c` on Unix, `/system/bin/sh -c`
+/// on Android, where there is no `/bin`, and `cmd.exe /C` on Windows.
+pub fn default_shell() -> Shell {
+    Shell { program: PROGRAM, command_flag: COMMAND_FLAG }
+}
+
+/**
+ * Runs `command_line` with the shell of the system and waits for it to
+ * terminate.
+ *
+ * # Return value
+ *
+ * The exit status of the shell, or None if it could not be started.
+ */
+pub fn shell(command_line: &str) -> Option<ProcessExit> {
+    let shell = default_shell();
+    super::process_status(shell.program, shell.args(command_line))
+}
+
+/**
+ * Runs `command_line` with the shell of the system, records all its output,
+ * and waits for it to terminate.
+ *
+ * # Return value
+ *
+ * The stdout/stderr output and exit status of the shell, or None if it could
+ * not be started.
+ */
+pub fn shell_output(command_line: &str) -> Option<ProcessOutput> {
+    let shell = default_shell();
+    super::process_output(shell.program, shell.args(command_line))
+}
+
+#[cfg(unix, not(target_os = "android"))] static PROGRAM: &'static str = "/bin/sh";
+#[cfg(target_os = "android")] static PROGRAM: &'static str = "/system/bin/sh";
+#[cfg(windows)] static PROGRAM: &'static str = "cmd.exe";
+
+#[cfg(unix)] static COMMAND_FLAG: &'static str = "-c";
+#[cfg(windows)] static COMMAND_FLAG: &'static str = "/C";
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use str;
+    use super::{default_shell, shell, shell_output};
+
+    #[test]
+    fn test_default_shell() {
+        let shell = default_shell();
+        let args = shell.args("echo hello");
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1], ~"echo hello");
+        assert!(shell.command("exit 0").status().success());
+        assert!(!shell.command("exit 1").status().success());
+    }
+
+    #[test]
+    fn test_shell() {
+        assert!(shell("exit 3").unwrap().matches_exit_status(3));
+        let output = shell_output("echo hello").unwrap();
+        assert!(output.status.success());
+        assert_eq!(str::from_utf8(output.output).unwrap().trim(), "hello");
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Supervision of long-running children
+//!
+//! A supervisor keeps a set of children running, such as the workers of a
+//! daemon. Each child is declared as a `ChildSpec`, whose restart policy
+//! says whether it is started again when it exits. A child which keeps
+//! exiting is restarted after a delay which doubles each time, and is given
+//! up on once it has been restarted as often as its spec allows. A child
+//! which ran for long enough before it exited was healthy, so its restarts
+//! are counted and delayed from scratch again.
+//!
+//! The children are run and watched by a task of the supervisor, which
+//! reports what happens to them as `SupervisorEvent`s.
+
+use cmp;
+use comm::{Data, Empty, Disconnected};
+use io::process::ProcessExit;
+use io::timer;
+use io;
+use libc::pid_t;
+use prelude::*;
+use task;
+
+use super::{Command, Process};
+use super::clock::precise_time_ns;
+
+/// When a supervised child is started again after it exits.
+#[deriving(Clone, Eq)]
+pub enum RestartPolicy {
+    /// The child is restarted whenever it exits.
+    RestartAlways,
+
+    /// The child is restarted if it exits with a nonzero status, is killed
+    /// by a signal or cannot be started at all.
+    RestartOnFailure,
+
+    /// The child is never restarted.
+    RestartNever,
+}
+
+/// The declaration of a supervised child.
+#[deriving(Clone)]
+pub struct ChildSpec {
+    /// The name the child is reported under in `SupervisorEvent`s.
+    name: ~str,
+
+    /// The command the child runs.
+    command: Command,
+
+    /// When the child is restarted.
+    restart: RestartPolicy,
+
+    /// How long the supervisor waits before the first restart of the child,
+    /// in milliseconds. The delay doubles with each restart.
+    backoff_ms: u64,
+
+    /// The longest the supervisor waits before restarting the child, in
+    /// milliseconds.
+    max_backoff_ms: u64,
+
+    /// How often the child may be restarted before the supervisor gives up
+    /// on it, or None if it is restarted for as long as the supervisor runs.
+    max_restarts: Option<uint>,
+
+    /// How long the child has to run, in milliseconds, for the restarts
+    /// before to be forgotten, so that it starts over with the delay of its
+    /// first restart once it exits.
+    stable_ms: u64,
+}
+
+impl ChildSpec {
+    /// Declares a child named `name` which runs `command`, and which is
+    /// restarted up to five times if it fails, after a delay starting at a
+    /// tenth of a second and growing to at most thirty seconds. Restarts are
+    /// forgotten once the child has run for a minute.
+    pub fn new(name: &str, command: Command) -> ChildSpec {
+        ChildSpec {
+            name: name.to_owned(),
+            command: command,
+            restart: RestartOnFailure,
+            backoff_ms: 100,
+            max_backoff_ms: 30000,
+            max_restarts: Some(5),
+            stable_ms: 60000,
+        }
+    }
+
+    /// Returns whether a child which ran for `ran_ns` nanoseconds ran for
+    /// long enough for its restarts to be forgotten.
+    fn ran_stably(&self, ran_ns: u64) -> bool {
+        ran_ns >= self.stable_ms * 1000000
+    }
+
+    /// Returns whether the child is restarted after exiting with `status`,
+    /// or after failing to start if `status` is None, if it has been
+    /// restarted `restarts` times already.
+    fn restarts_after(&self, status: Option<ProcessExit>, restarts: uint) -> bool {
+        let wanted = match self.restart {
+            RestartAlways => true,
+            RestartOnFailure => !status.map_or(false, |s| s.success()),
+            RestartNever => false,
+        };
+        wanted && self.max_restarts.map_or(true, |max| restarts < max)
+    }
+}
+
+/// What happened to a supervised child, which is named after its spec.
+#[deriving(Clone, Eq)]
+pub enum SupervisorEvent {
+    /// The child was started as the process with the given id.
+    ChildStarted(~str, pid_t),
+
+    /// The child could not be started.
+    ChildSpawnFailed(~str),
+
+    /// The child exited with the given status.
+    ChildExited(~str, ProcessExit),
+
+    /// The child is no longer restarted, as its restart policy does not ask
+    /// for it or it has been restarted as often as it may be.
+    ChildRetired(~str),
+}
+
+/// A running supervisor, whose children are shut down when it is stopped or
+/// dropped.
+pub struct Supervisor {
+    priv control: Chan<u64>,
+    priv events: Port<SupervisorEvent>,
+}
+
+impl Supervisor {
+    /// Starts a supervisor running the children declared by `specs`.
+    pub fn start(specs: ~[ChildSpec]) -> Supervisor {
+        let (control_port, control) = Chan::new();
+        let (events, event_chan) = Chan::new();
+        let mut builder = task::task();
+        builder.name("<process supervisor>");
+        do builder.spawn {
+            supervise(specs, control_port, event_chan)
+        }
+        Supervisor { control: control, events: events }
+    }
+
+    /// Returns the port on which the events of the children are received,
+    /// which is closed once the supervisor has stopped, or once all of its
+    /// children have retired.
+    pub fn events<'a>(&'a mut self) -> &'a mut Port<SupervisorEvent> {
+        &mut self.events
+    }
+
+    /**
+     * Stops the supervisor, shutting the running children down as
+     * `Process::shutdown` does, with a grace period of `grace_ms`
+     * milliseconds which they share: all of them are asked to exit before
+     * any is waited for. Blocks until all of them have exited.
+     *
+     * Dropping a supervisor shuts its children down without a grace period,
+     * in the background.
+     */
+    pub fn stop(self, grace_ms: u64) {
+        let Supervisor { control, events } = self;
+        // The supervisor has returned already if all of its children retired.
+        control.try_send(grace_ms);
+        while events.recv_opt().is_some() {}
+    }
+}
+
+/// The state of a supervised child.
+enum ChildState {
+    /// Running since the given time of `precise_time_ns`.
+    Running(Process, u64),
+    /// Waiting to be restarted, at the given time of `precise_time_ns`.
+    Restarting(u64),
+    Retired,
+}
+
+struct Child {
+    spec: ChildSpec,
+    state: ChildState,
+    restarts: uint,
+    backoff_ms: u64,
+}
+
+/// The body of the task of a supervisor.
+fn supervise(specs: ~[ChildSpec], control: Port<u64>, events: Chan<SupervisorEvent>) {
+    let mut children: ~[Child] = specs.move_iter().map(|spec| {
+        let backoff_ms = spec.backoff_ms;
+        Child { spec: spec, state: Restarting(0), restarts: 0, backoff_ms: backoff_ms }
+    }).collect();
+
+    let mut delay = 1;
+    loop {
+        let grace_ms = match control.try_recv() {
+            Data(grace_ms) => Some(grace_ms),
+            Disconnected => Some(0),
+            Empty => None,
+        };
+        match grace_ms {
+            Some(grace_ms) => {
+                let exited: ~[Option<ProcessExit>] = children.mut_iter().map(|child| {
+                    match child.state {
+                        Running(ref mut p, _) => p.begin_shutdown(),
+                        Restarting(..) | Retired => None,
+                    }
+                }).collect();
+                let deadline = precise_time_ns() + grace_ms * 1000000;
+                for (child, exited) in children.mut_iter().zip(exited.move_iter()) {
+                    match child.state {
+                        Running(ref mut p, _) => {
+                            let status = match exited {
+                                Some(status) => status,
+                                None => {
+                                    let now = precise_time_ns();
+                                    let left = if deadline > now { deadline - now } else { 0 };
+                                    p.end_shutdown(left / 1000000)
+                                }
+                            };
+                            events.try_send(ChildExited(child.spec.name.clone(), status));
+                        }
+                        Restarting(..) | Retired => {}
+                    }
+                }
+                return;
+            }
+            None => {}
+        }
+
+        let mut changed = false;
+        let mut next_restart = None;
+        for child in children.mut_iter() {
+            let now = precise_time_ns();
+            let mut start = false;
+            let mut stable = false;
+            let mut exited = match child.state {
+                Running(ref mut p, started) => match p.try_finish() {
+                    Some(status) => {
+                        stable = child.spec.ran_stably(now - started);
+                        Some(Some(status))
+                    }
+                    None => None,
+                },
+                Restarting(at) if at <= now => {
+                    start = true;
+                    None
+                }
+                Restarting(at) => {
+                    next_restart = Some(next_restart.map_or(at, |next| cmp::min(next, at)));
+                    None
+                }
+                Retired => None,
+            };
+            for status in exited.iter() {
+                for &status in status.iter() {
+                    events.try_send(ChildExited(child.spec.name.clone(), status));
+                }
+            }
+            if start {
+                match io::result(|| child.spec.command.spawn()) {
+                    Ok(Some(p)) => {
+                        events.try_send(ChildStarted(child.spec.name.clone(), p.get_id()));
+                        child.state = Running(p, now);
+                    }
+                    Ok(None) | Err(..) => {
+                        events.try_send(ChildSpawnFailed(child.spec.name.clone()));
+                        exited = Some(None);
+                    }
+                }
+            }
+            match exited {
+                Some(status) => {
+                    changed = true;
+                    if stable {
+                        child.restarts = 0;
+                        child.backoff_ms = child.spec.backoff_ms;
+                    }
+                    if child.spec.restarts_after(status, child.restarts) {
+                        let at = now + child.backoff_ms * 1000000;
+                        child.restarts += 1;
+                        child.backoff_ms = cmp::min(child.backoff_ms * 2,
+                                                    child.spec.max_backoff_ms);
+                        child.state = Restarting(at);
+                        next_restart = Some(next_restart.map_or(at, |next| cmp::min(next, at)));
+                    } else {
+                        events.try_send(ChildRetired(child.spec.name.clone()));
+                        child.state = Retired;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        if children.iter().all(|c| match c.state { Retired => true, _ => false }) {
+            return;
+        }
+
+        // The children are polled at growing intervals, but none is
+        // restarted later than it is due.
+        if changed {
+            delay = 1;
+        }
+        let mut sleep_ms = delay;
+        for &at in next_restart.iter() {
+            let now = precise_time_ns();
+            sleep_ms = cmp::min(sleep_ms, if at > now { (at - now) / 1000000 } else { 0 });
+        }
+        if sleep_ms > 0 {
+            timer::sleep(sleep_ms);
+        }
+        delay = cmp::min(delay * 2, 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use super::{ChildSpec, RestartAlways, RestartNever, RestartOnFailure};
+    use run::Command;
+    use io::process::{ExitStatus, ExitSignal};
+
+    #[test]
+    fn test_restarts_after() {
+        let mut spec = ChildSpec::new("child", Command::new("true"));
+        assert!(spec.restarts_after(Some(ExitStatus(1)), 0));
+        assert!(spec.restarts_after(Some(ExitSignal(9)), 4));
+        assert!(spec.restarts_after(None, 0));
+        assert!(!spec.restarts_after(Some(ExitStatus(0)), 0));
+        assert!(!spec.restarts_after(Some(ExitStatus(1)), 5));
+
+        spec.restart = RestartAlways;
+        spec.max_restarts = None;
+        assert!(spec.restarts_after(Some(ExitStatus(0)), 100));
+
+        spec.restart = RestartNever;
+        assert!(!spec.restarts_after(Some(ExitStatus(1)), 0));
+
+        spec.restart = RestartOnFailure;
+        assert!(spec.restarts_after(Some(ExitStatus(1)), 0));
+    }
+
+    #[test]
+    fn test_ran_stably() {
+        let mut spec = ChildSpec::new("child", Command::new("true"));
+        spec.stable_ms = 1000;
+        assert!(!spec.ran_stably(999999999));
+        assert!(spec.ran_stably(1000000000));
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Suspension of a foreground child along with the current process
+//!
+//! When the user presses Ctrl-Z, the terminal sends SIGTSTP to the foreground
+//! process group. A wrapper whose child runs in another process group (or
+//! ignores the signal) would otherwise leave the child running in the
+//! background while the shell believes the whole job is stopped. Following
+//! the suspension stops the child with SIGSTOP, stops the current process,
+//! and continues the child once the shell continues the current process.
+
+use libc::pid_t;
+
+#[cfg(unix)] use comm::Select;
+#[cfg(unix)] use io::signal::{Listener, StopTemporarily};
+#[cfg(unix)] use io;
+#[cfg(unix)] use libc::c_int;
+#[cfg(unix)] use libc;
+use prelude::*;
+
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+pub mod signals {
+    use libc::c_int;
+    pub static SIGCONT: c_int = 18;
+    pub static SIGSTOP: c_int = 19;
+}
+
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+pub mod signals {
+    use libc::c_int;
+    pub static SIGSTOP: c_int = 17;
+    pub static SIGCONT: c_int = 19;
+}
+
+/**
+ * Starts following the suspensions of the current process with the child
+ * `pid`.
+ *
+ * # Return value
+ *
+ * A channel which stops the following when it is dropped, or None if SIGTSTP
+ * could not be listened for (for example on Windows, where there is no job
+ * control).
+ */
+#[cfg(unix)]
+pub fn follow(pid: pid_t) -> Option<Chan<()>> {
+    let mut listener = Listener::new();
+    match io::result(|| listener.register(StopTemporarily)) {
+        Ok(true) => {}
+        Ok(false) | Err(..) => return None,
+    }
+    let (done, chan) = Chan::new();
+    do spawn {
+        watch(pid, listener, done);
+    }
+    Some(chan)
+}
+
+#[cfg(windows)]
+pub fn follow(_pid: pid_t) -> Option<Chan<()>> { None }
+
+#[cfg(unix)]
+fn watch(pid: pid_t, mut listener: Listener, mut done: Port<()>) {
+    let sel = Select::new();
+    let mut signals = sel.add(&mut listener.port);
+    let done = sel.add(&mut done);
+    loop {
+        // Nothing is ever sent on `done`, it only becomes ready once the
+        // process is finished or dropped.
+        if sel.wait() == done.id { break }
+        signals.recv();
+        stop(pid);
+    }
+}
+
+#[cfg(unix)]
+fn stop(pid: pid_t) {
+    fn kill(pid: pid_t, sig: c_int) {
+        unsafe { libc::funcs::posix88::signal::kill(pid, sig); }
+    }
+    kill(pid, signals::SIGSTOP);
+    // Having a handler for SIGTSTP means it no longer stops us, so stop
+    // ourselves the way the default action would have. SIGSTOP cannot be
+    // caught, so this only returns once the shell continues us.
+    kill(unsafe { libc::getpid() }, signals::SIGSTOP);
+    kill(pid, signals::SIGCONT);
+}
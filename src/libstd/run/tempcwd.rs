@@ -0,0 +1,79 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Temporary working directories which live as long as a child
+
+use io::fs;
+use io::process::ProcessExit;
+use io;
+use os;
+use prelude::*;
+use rand;
+
+/// A freshly created temporary directory, which is deleted along with its
+/// contents when it is dropped.
+pub struct TempCwd {
+    priv path: Option<Path>,
+    priv keep_on_failure: bool,
+}
+
+impl TempCwd {
+    /// Creates a new directory in `os::tmpdir()`. If `keep_on_failure` is
+    /// true then `finish` keeps it for a child which failed, so that what it
+    /// left behind can be inspected.
+    ///
+    /// Raises the `io_error` condition and returns None if no directory can
+    /// be created.
+    pub fn new(keep_on_failure: bool) -> Option<TempCwd> {
+        let tmpdir = os::make_absolute(&os::tmpdir());
+        let mut last_error = None;
+        for _ in range(0u, 1000) {
+            let p = tmpdir.join(format!("rust-{}", rand::random::<u32>()));
+            match io::result(|| fs::mkdir(&p, io::UserRWX)) {
+                Ok(()) => {
+                    return Some(TempCwd { path: Some(p), keep_on_failure: keep_on_failure })
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        io::io_error::cond.raise(last_error.unwrap());
+        None
+    }
+
+    /// Returns the path to the directory.
+    pub fn path<'a>(&'a self) -> &'a Path {
+        self.path.get_ref()
+    }
+
+    /// Deletes the directory now that the child which ran in it exited with
+    /// `status`, unless it failed and the directory is to be kept then.
+    pub fn finish(mut self, status: &ProcessExit) {
+        if self.keep_on_failure && !status.success() {
+            let path = self.path.take_unwrap();
+            debug!("keeping the working directory {} of a failed child", path.display());
+        }
+    }
+
+    /// Keeps the directory instead of deleting it, returning its path.
+    pub fn keep(mut self) -> Path {
+        self.path.take_unwrap()
+    }
+}
+
+impl Drop for TempCwd {
+    fn drop(&mut self) {
+        for path in self.path.iter() {
+            if path.exists() {
+                let _guard = io::ignore_io_error();
+                fs::rmdir_recursive(path);
+            }
+        }
+    }
+}
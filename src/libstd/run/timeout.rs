@@ -0,0 +1,168 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reads with a deadline from the output of a child
+
+use cmp;
+use comm::Select;
+use io::{IoError, Reader, Timer};
+use io;
+use prelude::*;
+use vec;
+
+/// A `Reader` over an output stream of a child which can also wait for a
+/// bounded time only for the next chunk of output.
+///
+/// The stream is read by a task of its own, which sends the chunks it reads
+/// to this reader as they arrive. A read which times out therefore loses
+/// nothing: the chunk it waited for is returned by a later read.
+pub struct TimeoutReader {
+    priv port: Port<Result<~[u8], IoError>>,
+    priv chunk: ~[u8],
+    priv pos: uint,
+    priv timer: Option<Timer>,
+}
+
+impl TimeoutReader {
+    /// Creates a new reader of `inner`, which starts to be read right away.
+    pub fn new<R: Reader + Send>(inner: R) -> TimeoutReader {
+        let (port, chan) = Chan::new();
+        do spawn {
+            let mut inner = inner;
+            let mut error = None;
+            io::io_error::cond.trap(|e| error = Some(e)).inside(|| {
+                let mut buf = vec::from_elem(64 * 1024, 0u8);
+                loop {
+                    match inner.read(buf) {
+                        Some(n) => {
+                            if !chan.try_send(Ok(buf.slice_to(n).to_owned())) {
+                                break
+                            }
+                        }
+                        None => break,
+                    }
+                }
+            });
+            for e in error.move_iter() {
+                chan.try_send(Err(e));
+            }
+        }
+        TimeoutReader { port: port, chunk: ~[], pos: 0, timer: None }
+    }
+
+    /**
+     * Reads like `read`, but waits at most `msecs` milliseconds for output to
+     * arrive if none is available yet.
+     *
+     * Raises the `io_error` condition with the kind TimedOut and returns None
+     * if no output arrived in time. The stream can still be read afterwards.
+     */
+    pub fn read_timeout(&mut self, buf: &mut [u8], msecs: u64) -> Option<uint> {
+        if self.pos < self.chunk.len() {
+            return Some(self.copy_chunk(buf));
+        }
+        if self.timer.is_none() {
+            self.timer = Timer::new();
+        }
+        let mut timeout = match self.timer {
+            Some(ref mut timer) => timer.oneshot(msecs),
+            None => return None,
+        };
+        let received = {
+            let sel = Select::new();
+            let mut data = sel.add(&mut self.port);
+            let timer = sel.add(&mut timeout);
+            if sel.wait() == timer.id {
+                None
+            } else {
+                Some(data.recv_opt())
+            }
+        };
+        match received {
+            Some(received) => self.received(received, buf),
+            None => {
+                io::io_error::cond.raise(io::standard_error(io::TimedOut));
+                None
+            }
+        }
+    }
+
+    /// Handles what the reading task sent, or None if it is done.
+    fn received(&mut self, received: Option<Result<~[u8], IoError>>,
+                buf: &mut [u8]) -> Option<uint> {
+        match received {
+            Some(Ok(chunk)) => {
+                self.chunk = chunk;
+                self.pos = 0;
+                Some(self.copy_chunk(buf))
+            }
+            Some(Err(e)) => {
+                io::io_error::cond.raise(e);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn copy_chunk(&mut self, buf: &mut [u8]) -> uint {
+        let n = cmp::min(buf.len(), self.chunk.len() - self.pos);
+        vec::bytes::copy_memory(buf.mut_slice_to(n), self.chunk.slice(self.pos, self.pos + n));
+        self.pos += n;
+        n
+    }
+}
+
+impl Reader for TimeoutReader {
+    fn read(&mut self, buf: &mut [u8]) -> Option<uint> {
+        if self.pos < self.chunk.len() {
+            return Some(self.copy_chunk(buf));
+        }
+        let received = self.port.recv_opt();
+        self.received(received, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::{io_error, Reader, TimedOut};
+    use io::mem::MemReader;
+    use io::PortReader;
+    use io;
+    use super::TimeoutReader;
+
+    #[test]
+    fn test_timeout_reader() {
+        let mut reader = TimeoutReader::new(MemReader::new(bytes!("hello").to_owned()));
+        let mut buf = [0u8, ..3];
+        assert_eq!(reader.read_timeout(buf, 10000), Some(3));
+        assert_eq!(buf.as_slice(), bytes!("hel"));
+        assert_eq!(reader.read_to_end(), bytes!("lo").to_owned());
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let (port, chan) = Chan::new();
+        let mut reader = TimeoutReader::new(PortReader::new(port));
+        let mut buf = [0u8, ..5];
+        match io::result(|| reader.read_timeout(buf, 10)) {
+            Err(e) => assert_eq!(e.kind, TimedOut),
+            Ok(n) => fail!("read {:?} instead of timing out", n),
+        }
+        chan.send(bytes!("hello").to_owned());
+        drop(chan);
+        let mut timed_out = false;
+        io_error::cond.trap(|_| timed_out = true).inside(|| {
+            assert_eq!(reader.read_timeout(buf, 10000), Some(5));
+            assert_eq!(reader.read_timeout(buf, 10000), None);
+        });
+        assert!(!timed_out);
+    }
+}
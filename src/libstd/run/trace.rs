@@ -0,0 +1,209 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Timeline of the children run by the program
+//!
+//! Once tracing is enabled with `enable_tracing`, every `run::Process` which
+//! is spawned is recorded along with the times at which it was spawned and
+//! finished. The recording can be written out with `write_chrome_trace` in
+//! the trace event format of `chrome://tracing` (and of Perfetto and
+//! Speedscope), which draws each child as a bar on a lane of its own, so
+//! that the parallelism and the critical path of a build or a test run can
+//! be seen at a glance.
+
+use cast;
+use io::Writer;
+use io::process::ProcessExit;
+use libc;
+use prelude::*;
+use unstable::mutex::{Once, ONCE_INIT};
+use unstable::sync::Exclusive;
+
+use super::clock::precise_time_ns;
+
+/// A child as recorded in the trace.
+#[deriving(Clone)]
+pub struct ProcessSpan {
+    /// The id of the child.
+    pid: libc::pid_t,
+
+    /// The command line of the child.
+    command: ~str,
+
+    /// When the child was spawned, in nanoseconds of a monotonic clock.
+    start_ns: u64,
+
+    /// When the child was found to have exited, or None if it has not been
+    /// finished yet.
+    end_ns: Option<u64>,
+
+    /// The exit status of the child, if it has been finished.
+    status: Option<ProcessExit>,
+
+    priv id: uint,
+}
+
+struct Trace {
+    enabled: bool,
+    next_id: uint,
+    spans: ~[ProcessSpan],
+}
+
+type Registry = Exclusive<Trace>;
+
+static mut REGISTRY: *mut Registry = 0 as *mut Registry;
+
+fn with_registry<T>(f: |&mut Trace| -> T) -> T {
+    static mut INIT: Once = ONCE_INIT;
+
+    unsafe {
+        INIT.doit(|| {
+            let registry: ~Registry = ~Exclusive::new(Trace {
+                enabled: false,
+                next_id: 0,
+                spans: ~[],
+            });
+            REGISTRY = cast::transmute(registry);
+        });
+        (*REGISTRY).with(f)
+    }
+}
+
+/// Starts or stops recording the children which are spawned. Children which
+/// were recorded before tracing is stopped are still recorded when they
+/// finish.
+pub fn enable_tracing(enabled: bool) {
+    with_registry(|trace| trace.enabled = enabled)
+}
+
+/// Returns the children recorded so far, in the order they were spawned.
+pub fn process_trace() -> ~[ProcessSpan] {
+    with_registry(|trace| trace.spans.clone())
+}
+
+/// Forgets the children recorded so far.
+pub fn clear_trace() {
+    with_registry(|trace| trace.spans.truncate(0))
+}
+
+/// Records that `command` was spawned as the child `pid`, returning the id
+/// with which its exit is recorded, or None if tracing is disabled.
+pub fn spawned(pid: libc::pid_t, command: ~str) -> Option<uint> {
+    let start_ns = precise_time_ns();
+    with_registry(|trace| {
+        if !trace.enabled {
+            return None
+        }
+        let id = trace.next_id;
+        trace.next_id += 1;
+        trace.spans.push(ProcessSpan {
+            pid: pid,
+            command: command,
+            start_ns: start_ns,
+            end_ns: None,
+            status: None,
+            id: id,
+        });
+        Some(id)
+    })
+}
+
+/// Records that the child recorded as `id` exited with `status`.
+pub fn exited(id: uint, status: ProcessExit) {
+    let end_ns = precise_time_ns();
+    with_registry(|trace| {
+        for span in trace.spans.mut_iter().filter(|s| s.id == id) {
+            span.end_ns = Some(end_ns);
+            span.status = Some(status);
+        }
+    })
+}
+
+/**
+ * Writes the children recorded so far to `w` as a JSON document in the trace
+ * event format of `chrome://tracing`.
+ *
+ * Each child is an event on a lane named after its id, with its exit status
+ * among its arguments. Times are in microseconds since the first child was
+ * spawned, and children which have not been finished yet are drawn as if
+ * they were still running at the end of the trace.
+ */
+pub fn write_chrome_trace(w: &mut Writer) {
+    write_events(w, process_trace())
+}
+
+fn write_events(w: &mut Writer, spans: &[ProcessSpan]) {
+    let pid = unsafe { libc::getpid() };
+    let origin = spans.iter().map(|s| s.start_ns).min().unwrap_or(0);
+    write!(w, "\\{\"traceEvents\":[");
+    for (i, span) in spans.iter().enumerate() {
+        if i > 0 {
+            write!(w, ",");
+        }
+        let ts = (span.start_ns - origin) / 1000;
+        write!(w, "\n\\{\"name\":\"{}\",\"cat\":\"process\",\"pid\":{},\"tid\":{},\"ts\":{}",
+               escape(span.command), pid, span.pid, ts);
+        match (span.end_ns, span.status) {
+            (Some(end_ns), Some(status)) => {
+                write!(w, ",\"ph\":\"X\",\"dur\":{},\"args\":\\{\"status\":\"{}\"\\}\\}",
+                       (end_ns - span.start_ns) / 1000, status);
+            }
+            _ => write!(w, ",\"ph\":\"B\"\\}"),
+        }
+    }
+    write!(w, "\n],\"displayTimeUnit\":\"ms\"\\}\n");
+}
+
+/// Escapes `s` for a JSON string.
+fn escape(s: &str) -> ~str {
+    let mut ret = ~"";
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\t' => ret.push_str("\\t"),
+            c if c < ' ' => ret.push_str(format!("\\\\u{:04x}", c as uint)),
+            c => ret.push_char(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::mem::MemWriter;
+    use io::process::ExitStatus;
+    use str;
+    use super::{ProcessSpan, escape, write_events};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("echo \"a\\b\"\n\x01"), ~"echo \\\"a\\\\b\\\"\\n\\u0001");
+    }
+
+    #[test]
+    fn test_write_events() {
+        let spans = [
+            ProcessSpan { pid: 10, command: ~"cc a.c", start_ns: 5000, end_ns: Some(9000),
+                          status: Some(ExitStatus(0)), id: 0 },
+            ProcessSpan { pid: 11, command: ~"ld", start_ns: 7000, end_ns: None,
+                          status: None, id: 1 },
+        ];
+        let mut w = MemWriter::new();
+        write_events(&mut w, spans);
+        let json = str::from_utf8_owned(w.unwrap()).unwrap();
+        assert!(json.contains("\"name\":\"cc a.c\""));
+        assert!(json.contains("\"tid\":10,\"ts\":0,\"ph\":\"X\",\"dur\":4,\
+                               \"args\":{\"status\":\"exit code: 0\"}}"));
+        assert!(json.contains("\"tid\":11,\"ts\":2,\"ph\":\"B\"}"));
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Descendants of children
+//!
+//! A child which runs helpers of its own, like a shell running a pipeline or
+//! a build tool running compilers, leaves them running when only the child
+//! is killed. Its descendants are found by following the parent ids of all
+//! processes, which are read from `/proc` on Linux, from `ps` on other unix
+//! systems and from a toolhelp snapshot on Windows. A descendant whose
+//! parent exited already has been adopted by another process, and is not
+//! found.
+//!
+//! Windows keeps the id of the parent of a process after the parent exited,
+//! and that id may have been given to a new process since, so there a
+//! process is only taken for the child of one which was created before it.
+
+use libc::pid_t;
+use prelude::*;
+
+#[cfg(unix)] use libc;
+#[cfg(unix)] use super::suspend::signals::{SIGSTOP, SIGCONT};
+#[cfg(target_os = "linux")] #[cfg(target_os = "android")] use io::File;
+#[cfg(target_os = "linux")] #[cfg(target_os = "android")] use io::fs;
+#[cfg(not(windows))] use io;
+#[cfg(not(windows))] use str;
+
+/// Returns the ids of the children of `pid`, of their children and so on,
+/// parents before their children. Processes which are started or exit while
+/// the processes of the system are listed may be missed.
+pub fn descendants(pid: pid_t) -> ~[pid_t] {
+    let links = parent_links();
+    let mut found = ~[];
+    let mut parent = pid;
+    let mut next = 0;
+    loop {
+        for &(child, p) in links.iter() {
+            if p == parent && child != pid && !found.contains(&child) {
+                found.push(child);
+            }
+        }
+        if next == found.len() {
+            return found;
+        }
+        parent = found[next];
+        next += 1;
+    }
+}
+
+/**
+ * Sends `signum` to every descendant of `pid`.
+ *
+ * `pid` and its descendants are stopped while they are looked for, so that
+ * none of them can start new processes unseen, and are continued once the
+ * descendants have been sent the signal. `pid` itself is not sent it.
+ */
+#[cfg(unix)]
+pub fn signal_descendants(pid: pid_t, signum: int) {
+    use libc::funcs::posix88::signal::kill;
+
+    unsafe { kill(pid, SIGSTOP); }
+    let mut stopped: ~[pid_t] = ~[];
+    loop {
+        let found: ~[pid_t] = descendants(pid).move_iter()
+                                              .filter(|p| !stopped.contains(p))
+                                              .collect();
+        if found.is_empty() {
+            break;
+        }
+        for &p in found.iter() {
+            unsafe { kill(p, SIGSTOP); }
+        }
+        stopped.push_all_move(found);
+    }
+    for &p in stopped.iter() {
+        unsafe {
+            kill(p, signum as libc::c_int);
+            kill(p, SIGCONT);
+        }
+    }
+    unsafe { kill(pid, SIGCONT); }
+}
+
+/// Terminates every descendant of `pid`, as Windows has no signals.
+#[cfg(windows)]
+pub fn signal_descendants(pid: pid_t, _signum: int) {
+    use libc::types::os::arch::extra::DWORD;
+    use libc::consts::os::extra::{FALSE, PROCESS_TERMINATE};
+    use libc::funcs::extra::kernel32::{OpenProcess, TerminateProcess, CloseHandle};
+
+    for &p in descendants(pid).iter() {
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE, FALSE, p as DWORD);
+            if !handle.is_null() {
+                TerminateProcess(handle, 1);
+                CloseHandle(handle);
+            }
+        }
+    }
+}
+
+/// Returns the id of every process of the system along with the id of its
+/// parent.
+#[cfg(target_os = "linux")]
+#[cfg(target_os = "android")]
+fn parent_links() -> ~[(pid_t, pid_t)] {
+    let _guard = io::ignore_io_error();
+    let mut links = ~[];
+    for dir in fs::readdir(&Path::new("/proc")).iter() {
+        let pid = match dir.filename_str().and_then(|s| from_str::<pid_t>(s)) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let stat = match File::open(&dir.join("stat")) {
+            Some(mut f) => f.read_to_end(),
+            None => continue,
+        };
+        let stat = match str::from_utf8_owned(stat) {
+            Some(stat) => stat,
+            None => continue,
+        };
+        // The parent id comes right after the state, which follows the
+        // name of the program in parentheses.
+        let ppid = stat.rfind(')').and_then(|i| {
+            stat.slice_from(i + 1).words().nth(1).and_then(|p| from_str::<pid_t>(p))
+        });
+        for &ppid in ppid.iter() {
+            links.push((pid, ppid));
+        }
+    }
+    links
+}
+
+#[cfg(target_os = "macos")]
+#[cfg(target_os = "freebsd")]
+fn parent_links() -> ~[(pid_t, pid_t)] {
+    let _guard = io::ignore_io_error();
+    let args = [~"-A", ~"-o", ~"pid=", ~"-o", ~"ppid="];
+    let output = match super::process_output("ps", args) {
+        Some(output) => output.output,
+        None => return ~[],
+    };
+    let output = match str::from_utf8_owned(output) {
+        Some(output) => output,
+        None => return ~[],
+    };
+    output.lines().filter_map(|line| {
+        let mut words = line.words().map(|w| from_str::<pid_t>(w));
+        match (words.next(), words.next()) {
+            (Some(Some(pid)), Some(Some(ppid))) => Some((pid, ppid)),
+            _ => None,
+        }
+    }).collect()
+}
+
+#[cfg(windows)]
+fn parent_links() -> ~[(pid_t, pid_t)] {
+    use libc::types::os::arch::extra::{BOOL, DWORD, HANDLE};
+    use libc::consts::os::extra::INVALID_HANDLE_VALUE;
+    use libc::funcs::extra::kernel32::CloseHandle;
+    use hashmap::HashMap;
+    use mem;
+    use unstable::intrinsics;
+
+    static TH32CS_SNAPPROCESS: DWORD = 0x2;
+
+    struct PROCESSENTRY32 {
+        dwSize: DWORD,
+        cntUsage: DWORD,
+        th32ProcessID: DWORD,
+        th32DefaultHeapID: uint,
+        th32ModuleID: DWORD,
+        cntThreads: DWORD,
+        th32ParentProcessID: DWORD,
+        pcPriClassBase: i32,
+        dwFlags: DWORD,
+        szExeFile: [u8, ..260],
+    }
+
+    extern "system" {
+        fn CreateToolhelp32Snapshot(dwFlags: DWORD, th32ProcessID: DWORD) -> HANDLE;
+        fn Process32First(hSnapshot: HANDLE, lppe: *mut PROCESSENTRY32) -> BOOL;
+        fn Process32Next(hSnapshot: HANDLE, lppe: *mut PROCESSENTRY32) -> BOOL;
+    }
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE as HANDLE {
+            return ~[];
+        }
+        let mut entry: PROCESSENTRY32 = intrinsics::init();
+        entry.dwSize = mem::size_of::<PROCESSENTRY32>() as DWORD;
+        let mut links = ~[];
+        let mut more = Process32First(snapshot, &mut entry);
+        while more != 0 {
+            links.push((entry.th32ProcessID as pid_t, entry.th32ParentProcessID as pid_t));
+            more = Process32Next(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+
+        // A parent which exited, or whose id was reused by a process created
+        // after the child, is not the parent of the child any more.
+        let mut created = HashMap::new();
+        for &(pid, _) in links.iter() {
+            for &time in creation_time(pid).iter() {
+                created.insert(pid, time);
+            }
+        }
+        links.move_iter().filter(|&(pid, ppid)| {
+            match (created.find(&pid), created.find(&ppid)) {
+                (Some(child), Some(parent)) => parent <= child,
+                _ => false,
+            }
+        }).collect()
+    }
+}
+
+/// Returns when the process `pid` was created, in the units of a FILETIME,
+/// if it can be opened.
+#[cfg(windows)]
+fn creation_time(pid: pid_t) -> Option<u64> {
+    use libc::types::os::arch::extra::{BOOL, DWORD, HANDLE, FILETIME};
+    use libc::consts::os::extra::FALSE;
+    use libc::funcs::extra::kernel32::{OpenProcess, CloseHandle};
+    use unstable::intrinsics;
+
+    static PROCESS_QUERY_LIMITED_INFORMATION: DWORD = 0x1000;
+
+    extern "system" {
+        fn GetProcessTimes(hProcess: HANDLE, lpCreationTime: *mut FILETIME,
+                           lpExitTime: *mut FILETIME, lpKernelTime: *mut FILETIME,
+                           lpUserTime: *mut FILETIME) -> BOOL;
+    }
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid as DWORD);
+        if handle.is_null() {
+            return None;
+        }
+        let mut creation: FILETIME = intrinsics::init();
+        let mut exit: FILETIME = intrinsics::init();
+        let mut kernel: FILETIME = intrinsics::init();
+        let mut user: FILETIME = intrinsics::init();
+        let ok = GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user);
+        CloseHandle(handle);
+        if ok == FALSE {
+            None
+        } else {
+            Some((creation.dwHighDateTime as u64 << 32) | creation.dwLowDateTime as u64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_descendants() {
+        use libc;
+        use super::descendants;
+
+        // The current process is a descendant of init, unlike init itself.
+        let pid = unsafe { libc::getpid() };
+        assert!(descendants(1).contains(&pid));
+        assert!(!descendants(pid).contains(&1));
+    }
+}
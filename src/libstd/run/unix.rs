@@ -0,0 +1,324 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Unix-specific extensions to spawning processes
+//!
+//! The options of `ProcessOptions` which only have an effect on Unix, or
+//! which work differently there, are not part of the portable struct, and
+//! are set with the methods of `ProcessOptionsExt` instead, which code
+//! building for Unix can use:
+//!
+//! ```rust
+//! use std::run::ProcessOptions;
+//! use std::run::unix::ProcessOptionsExt;
+//!
+//! let options = ProcessOptions::new().follow_suspend(true);
+//! ```
+
+use prelude::*;
+
+use libc::{c_int, mode_t};
+use super::{ProcessOptions, SpawnAttributes, ProcessControls, Capabilities, FsIsolation};
+use super::{Namespaces, Resource};
+
+/// The options of a child which are specific to Unix. Those specific
+/// to one flavor of Unix are ignored on the others unless stated otherwise.
+pub trait ProcessOptionsExt<'a> {
+    /// Stops the child whenever the current process is suspended from the
+    /// terminal with Ctrl-Z (SIGTSTP), and continues it when the current
+    /// process is, as an interactive wrapper around a foreground child
+    /// wants. SIGTSTP then stops the current process through a handler
+    /// while the child is running, rather than through the default action.
+    fn follow_suspend(self, follow: bool) -> Self;
+
+    /// Applies `posix_spawn`-style attributes (default signal dispositions,
+    /// signal mask, process group, suspended start and file actions) to the
+    /// child before it executes the program.
+    fn spawn_attributes(self, attrs: SpawnAttributes) -> Self;
+
+    /// Applies a sandbox profile, in the language of the profiles of
+    /// `sandbox-exec`, to the child on OS X before it executes the program.
+    fn sandbox_profile(self, profile: &str) -> Self;
+
+    /// Applies process controls to the child with `procctl` on FreeBSD.
+    fn process_controls(self, controls: ProcessControls) -> Self;
+
+    /// Delivers `signal` to the child when the current process exits, as
+    /// with `PR_SET_PDEATHSIG` on Linux and `PROC_PDEATHSIG_CTL` on FreeBSD.
+    /// On Linux the signal is actually delivered when the thread which
+    /// spawned the child exits. Spawning the child raises the `io_error`
+    /// condition with IoUnavailable on other platforms.
+    fn parent_death_signal(self, signal: int) -> Self;
+
+    /// Reduces the capability sets of the child on Linux to the
+    /// capabilities kept, and its bounding set too if asked.
+    fn capabilities(self, capabilities: Capabilities) -> Self;
+
+    /// Bars the child and everything it runs from gaining privileges on
+    /// Linux, as with `PR_SET_NO_NEW_PRIVS`.
+    fn no_new_privs(self, no_new_privs: bool) -> Self;
+
+    /// Gives the child a mount namespace of its own on Linux, with a private
+    /// `/tmp` and read-only directories as described.
+    fn fs_isolation(self, isolation: FsIsolation) -> Self;
+
+    /// Runs the child as the user `uid`, dropping its supplementary groups,
+    /// so that a server running as root can spawn its workers unprivileged.
+    fn uid(self, uid: uint) -> Self;
+
+    /// Runs the child with the group `gid`.
+    fn gid(self, gid: uint) -> Self;
+
+    /// Limits the resources of the child, as the resource with its soft and
+    /// hard limit, where `u64::MAX` stands for no limit. Only the superuser
+    /// can raise a hard limit.
+    fn rlimits(self, limits: ~[(Resource, u64, u64)]) -> Self;
+
+    /// Sets the file mode creation mask of the child between the fork and
+    /// the exec, overriding the mask of a reproducible child. Spawning the
+    /// child raises the `io_error` condition with IoUnavailable in green
+    /// tasks.
+    fn umask(self, mask: mode_t) -> Self;
+
+    /// Runs the child with `dir` as its root directory, as with `chroot`.
+    /// The program and the working directory are looked up inside of the
+    /// new root, and the child starts at its top if it is given no working
+    /// directory. This needs privileges, and is only supported in native
+    /// tasks.
+    fn chroot(self, dir: &Path) -> Self;
+
+    /// Hands descriptors of the current process to the child, as pairs of
+    /// the descriptor and the number it gets in the child, which has to be
+    /// above those of the standard streams. The numbers in between which are
+    /// not given are closed in the child.
+    fn pass_fds(self, fds: ~[(c_int, c_int)]) -> Self;
+
+    /// Creates the child in the given namespaces of its own on Linux.
+    /// Spawning the child raises the `io_error` condition with IoUnavailable
+    /// on other platforms if any are asked for.
+    fn namespaces(self, namespaces: Namespaces) -> Self;
+
+    /// Cuts the child off from the network, with an empty network namespace
+    /// on Linux and a sandbox profile on OS X. Spawning the child raises the
+    /// `io_error` condition with IoUnavailable on other platforms.
+    fn no_network(self, no_network: bool) -> Self;
+
+    /// Whether the child starts with the default action for every signal and
+    /// no signal blocked, as it does by default, rather than ignoring and
+    /// blocking the signals the current process does. In green tasks the
+    /// child keeps the signal handling of the current process either way.
+    fn reset_signals(self, reset: bool) -> Self;
+
+    /// Spawns the child with `vfork`, which is much faster than forking for a
+    /// process with a large heap. Only the standard streams and the
+    /// environment of the child can then be set up: spawning it raises the
+    /// `io_error` condition with InvalidInput if any other option needing
+    /// setup in it is given. This is only supported in native tasks.
+    fn vfork(self, vfork: bool) -> Self;
+
+    /// Keeps the child from ever getting the controlling terminal of the
+    /// current process: it starts a new session, and gets the null device as
+    /// its standard input if it would otherwise inherit a terminal.
+    fn no_ctty(self, no_ctty: bool) -> Self;
+
+    /// Connects the standard streams of the child which are not redirected to
+    /// a new pseudo-terminal instead of to pipes, which becomes the
+    /// controlling terminal of a session of its own. The master side of the
+    /// terminal is returned by `Process::pty`. This is only supported in
+    /// native tasks.
+    fn pty(self, pty: bool) -> Self;
+
+    /// Calls `hook` in the child between the fork and the exec, once all of
+    /// the other options have been applied. If it returns false, the child
+    /// is not spawned and the `io_error` condition is raised with the error
+    /// of the value the hook left in `errno`.
+    ///
+    /// The hook runs in a copy of the current process in which only the
+    /// spawning thread survived, so it must only call functions which are
+    /// async-signal-safe: it must not allocate, fail, log, or use anything
+    /// of the runtime. This is only supported in native tasks.
+    fn before_exec(self, hook: 'a || -> bool) -> Self;
+
+    /// Runs the child with the scheduling priority `nice`, from -20 for the
+    /// most favorable to 19 for the least. Only the superuser can give a
+    /// process a better priority than its own.
+    fn priority(self, nice: int) -> Self;
+
+    /// Restricts the child and its own children to the CPUs with the given
+    /// numbers, starting at 0, on Linux, ignoring those beyond the 1024th.
+    /// Spawning the child raises the `io_error` condition with IoUnavailable
+    /// on other platforms, and in green tasks.
+    fn cpu_affinity(self, cpus: ~[uint]) -> Self;
+}
+
+impl<'a> ProcessOptionsExt<'a> for ProcessOptions<'a> {
+    fn follow_suspend(mut self, follow: bool) -> ProcessOptions<'a> {
+        self.follow_suspend = follow;
+        self
+    }
+
+    fn spawn_attributes(mut self, attrs: SpawnAttributes) -> ProcessOptions<'a> {
+        self.spawn_attributes = Some(attrs);
+        self
+    }
+
+    fn sandbox_profile(mut self, profile: &str) -> ProcessOptions<'a> {
+        self.sandbox_profile = Some(profile.to_owned());
+        self
+    }
+
+    fn process_controls(mut self, controls: ProcessControls) -> ProcessOptions<'a> {
+        self.process_controls = Some(controls);
+        self
+    }
+
+    fn parent_death_signal(mut self, signal: int) -> ProcessOptions<'a> {
+        self.parent_death_signal = Some(signal);
+        self
+    }
+
+    fn capabilities(mut self, capabilities: Capabilities) -> ProcessOptions<'a> {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    fn no_new_privs(mut self, no_new_privs: bool) -> ProcessOptions<'a> {
+        self.no_new_privs = no_new_privs;
+        self
+    }
+
+    fn fs_isolation(mut self, isolation: FsIsolation) -> ProcessOptions<'a> {
+        self.fs_isolation = Some(isolation);
+        self
+    }
+
+    fn uid(mut self, uid: uint) -> ProcessOptions<'a> {
+        self.uid = Some(uid);
+        self
+    }
+
+    fn gid(mut self, gid: uint) -> ProcessOptions<'a> {
+        self.gid = Some(gid);
+        self
+    }
+
+    fn rlimits(mut self, limits: ~[(Resource, u64, u64)]) -> ProcessOptions<'a> {
+        self.rlimits = limits;
+        self
+    }
+
+    fn umask(mut self, mask: mode_t) -> ProcessOptions<'a> {
+        self.umask = Some(mask);
+        self
+    }
+
+    fn chroot(mut self, dir: &Path) -> ProcessOptions<'a> {
+        self.chroot = Some(dir.clone());
+        self
+    }
+
+    fn pass_fds(mut self, fds: ~[(c_int, c_int)]) -> ProcessOptions<'a> {
+        self.pass_fds = fds;
+        self
+    }
+
+    fn namespaces(mut self, namespaces: Namespaces) -> ProcessOptions<'a> {
+        self.namespaces = namespaces;
+        self
+    }
+
+    fn no_network(mut self, no_network: bool) -> ProcessOptions<'a> {
+        self.no_network = no_network;
+        self
+    }
+
+    fn reset_signals(mut self, reset: bool) -> ProcessOptions<'a> {
+        self.reset_signals = reset;
+        self
+    }
+
+    fn vfork(mut self, vfork: bool) -> ProcessOptions<'a> {
+        self.vfork = vfork;
+        self
+    }
+
+    fn no_ctty(mut self, no_ctty: bool) -> ProcessOptions<'a> {
+        self.no_ctty = no_ctty;
+        self
+    }
+
+    fn pty(mut self, pty: bool) -> ProcessOptions<'a> {
+        self.pty = pty;
+        self
+    }
+
+    fn before_exec(mut self, hook: 'a || -> bool) -> ProcessOptions<'a> {
+        self.before_exec = Some(hook);
+        self
+    }
+
+    fn priority(mut self, nice: int) -> ProcessOptions<'a> {
+        self.priority = Some(nice);
+        self
+    }
+
+    fn cpu_affinity(mut self, cpus: ~[uint]) -> ProcessOptions<'a> {
+        self.cpu_affinity = Some(cpus);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use run;
+    use run::{ProcessOptions, FileSize, Namespaces};
+    use super::ProcessOptionsExt;
+
+    #[test]
+    fn test_process_options_ext() {
+        let options = ProcessOptions::new().follow_suspend(true).sandbox_profile("(version 1)")
+                                           .parent_death_signal(15);
+        assert!(options.follow_suspend);
+        assert_eq!(options.sandbox_profile, Some(~"(version 1)"));
+        assert_eq!(options.parent_death_signal, Some(15));
+
+        let root = Path::new("/");
+        let options = ProcessOptions::new().uid(1000).gid(100).umask(0o077).chroot(&root)
+                                           .rlimits(~[(FileSize, 1024, 1024)])
+                                           .pass_fds(~[(0, 3)]).no_network(true)
+                                           .reset_signals(false).vfork(true).no_ctty(true)
+                                           .pty(true).priority(10).cpu_affinity(~[0]);
+        assert_eq!(options.uid, Some(1000));
+        assert_eq!(options.gid, Some(100));
+        assert_eq!(options.umask, Some(0o077));
+        assert_eq!(options.chroot, Some(root));
+        assert!(options.rlimits == ~[(FileSize, 1024, 1024)]);
+        assert_eq!(options.pass_fds, ~[(0, 3)]);
+        assert!(options.no_network);
+        assert!(!options.reset_signals);
+        assert!(options.vfork && options.no_ctty && options.pty);
+        assert_eq!(options.priority, Some(10));
+        assert_eq!(options.cpu_affinity, Some(~[0u]));
+
+        let options = ProcessOptions::new().before_exec(|| true).namespaces(Namespaces::new());
+        assert!(options.before_exec.is_some());
+        assert!(options.namespaces == Namespaces::new());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[cfg(target_os = "freebsd")]
+    fn test_parent_death_signal() {
+        let options = ProcessOptions::new().parent_death_signal(15);
+        let mut prog = run::Process::new("true", [], options).expect("failed to exec `true`");
+        assert!(prog.finish().success());
+    }
+}
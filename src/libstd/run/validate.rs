@@ -0,0 +1,236 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checks of the configuration of a child, made before it is spawned
+
+use fmt;
+use io;
+use libc::c_int;
+use libc;
+use prelude::*;
+use unstable::intrinsics;
+
+use super::env;
+
+/// A problem with the configuration of a child which would keep it from
+/// being spawned, or from being spawned as intended.
+#[deriving(Clone, Eq)]
+pub enum SpawnProblem {
+    /// The program could not be found, either at the given path or in any
+    /// directory of the `PATH` of the child.
+    ProgramNotFound(~str),
+
+    /// The program was found at the given path, but is not an executable
+    /// file.
+    ProgramNotExecutable(Path),
+
+    /// The working directory does not exist.
+    CwdNotFound(Path),
+
+    /// The working directory exists but is not a directory.
+    CwdNotDirectory(Path),
+
+    /// The name of the given environment variable is empty or contains `=`
+    /// or a NUL, or its value contains a NUL.
+    MalformedEnvVar(~str),
+
+    /// The given file descriptor to hand to the child is not open.
+    BadFd(c_int),
+}
+
+impl fmt::Default for SpawnProblem {
+    /// Format a SpawnProblem as a message for the user.
+    fn fmt(obj: &SpawnProblem, f: &mut fmt::Formatter) {
+        match *obj {
+            ProgramNotFound(ref prog) => write!(f.buf, "program `{}` not found", *prog),
+            ProgramNotExecutable(ref path) => {
+                write!(f.buf, "`{}` is not executable", path.display())
+            }
+            CwdNotFound(ref path) => {
+                write!(f.buf, "working directory `{}` does not exist", path.display())
+            }
+            CwdNotDirectory(ref path) => {
+                write!(f.buf, "working directory `{}` is not a directory", path.display())
+            }
+            MalformedEnvVar(ref key) => {
+                write!(f.buf, "malformed environment variable `{}`", key.escape_default())
+            }
+            BadFd(fd) => write!(f.buf, "file descriptor {} is not open", fd),
+        }
+    }
+}
+
+/// Returns the problems with the environment variables of `env`.
+pub fn check_env(env: &[(~str, ~str)]) -> ~[SpawnProblem] {
+    env.iter().filter(|&&(ref k, ref v)| {
+        k.is_empty() || k.contains_char('=') || k.contains_char('\0') ||
+            v.contains_char('\0')
+    }).map(|&(ref k, _)| MalformedEnvVar(k.clone())).collect()
+}
+
+/// Returns the problem with the working directory `dir`, if any.
+pub fn check_cwd(dir: &Path) -> Option<SpawnProblem> {
+    if dir.is_dir() {
+        None
+    } else if dir.exists() {
+        Some(CwdNotDirectory(dir.clone()))
+    } else {
+        Some(CwdNotFound(dir.clone()))
+    }
+}
+
+/// Returns the problem with the file descriptor `fd`, if any.
+pub fn check_fd(fd: c_int) -> Option<SpawnProblem> {
+    let mut stat: libc::stat = unsafe { intrinsics::init() };
+    match unsafe { libc::fstat(fd, &mut stat) } {
+        -1 => Some(BadFd(fd)),
+        _ => None,
+    }
+}
+
+/// Where the program of a child is looked for if it is not given as a path.
+#[deriving(Clone, Eq)]
+pub enum ProgramSearch {
+    /// The directories of the `PATH` of the child are searched, as with
+    /// `execvp`.
+    SearchEnvPath,
+
+    /// The program is not searched for at all, as with `execv`, and has to
+    /// be given as a path.
+    RequirePath,
+
+    /// The given directories are searched in order, whatever the `PATH` is.
+    SearchDirs(~[Path]),
+}
+
+/// Resolves `prog` to the file which would be executed for it, searching as
+/// told by `search` if it is not a path itself, and checks that the file is
+/// executable.
+pub fn check_program(prog: &str, search: &ProgramSearch,
+                     env: &[(~str, ~str)]) -> Result<Path, SpawnProblem> {
+    let path = match find_program(prog, search, env) {
+        Some(path) => path,
+        None => return Err(ProgramNotFound(prog.to_owned())),
+    };
+    if is_executable(&path) {
+        Ok(path)
+    } else {
+        Err(ProgramNotExecutable(path))
+    }
+}
+
+/// Finds the file which would be executed for `prog` with the environment
+/// `env`.
+pub fn find_program(prog: &str, search: &ProgramSearch,
+                    env: &[(~str, ~str)]) -> Option<Path> {
+    if is_path(prog) {
+        return with_extensions(&Path::new(prog), env);
+    }
+    match *search {
+        SearchEnvPath => {
+            let dirs = match env::lookup(env, "PATH") {
+                Some(dirs) => dirs,
+                None => return None,
+            };
+            let sep = if cfg!(windows) { ';' } else { ':' };
+            for dir in dirs.split(sep) {
+                let dir = if dir.is_empty() { "." } else { dir };
+                match with_extensions(&Path::new(dir).join(prog), env) {
+                    Some(path) => return Some(path),
+                    None => {}
+                }
+            }
+            None
+        }
+        RequirePath => None,
+        SearchDirs(ref dirs) => {
+            for dir in dirs.iter() {
+                match with_extensions(&dir.join(prog), env) {
+                    Some(path) => return Some(path),
+                    None => {}
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Checks whether `prog` is a path rather than a name to search for.
+pub fn is_path(prog: &str) -> bool {
+    prog.contains_char('/') || (cfg!(windows) && prog.contains_char('\\'))
+}
+
+/// Returns `path` if it is a file, or on Windows the first file which is
+/// named like it with one of the extensions of `PATHEXT`.
+fn with_extensions(path: &Path, env: &[(~str, ~str)]) -> Option<Path> {
+    if path.is_file() {
+        return Some(path.clone());
+    }
+    if cfg!(windows) && path.extension().is_none() {
+        let exts = env::lookup(env, "PATHEXT").unwrap_or(".COM;.EXE;.BAT;.CMD");
+        for ext in exts.split(';').filter(|e| !e.is_empty()) {
+            let candidate = path.with_extension(ext.slice_from(1));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    match io::result(|| path.stat()) {
+        Ok(stat) => {
+            let exec = io::UserExecute | io::GroupExecute | io::OtherExecute;
+            stat.kind == io::TypeFile && stat.perm & exec != 0
+        }
+        Err(..) => false,
+    }
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use os;
+    use super::{check_env, check_fd, find_program, BadFd, MalformedEnvVar};
+    use super::{SearchEnvPath, RequirePath, SearchDirs};
+
+    #[test]
+    fn test_check_env() {
+        let env = [(~"GOOD", ~"1"), (~"", ~"2"), (~"A=B", ~"3"), (~"NUL", ~"a\0b")];
+        assert_eq!(check_env(env),
+                   ~[MalformedEnvVar(~""), MalformedEnvVar(~"A=B"), MalformedEnvVar(~"NUL")]);
+    }
+
+    #[test]
+    fn test_check_fd() {
+        assert!(check_fd(1).is_none());
+        assert_eq!(check_fd(-1), Some(BadFd(-1)));
+    }
+
+    #[test]
+    #[cfg(unix,not(target_os="android"))]
+    fn test_find_program() {
+        assert!(find_program("sh", &SearchEnvPath, os::env()).is_some());
+        assert!(find_program("no-binary-by-this-name-should-exist", &SearchEnvPath,
+                             os::env()).is_none());
+        assert_eq!(find_program("/bin/sh", &SearchEnvPath, []), Some(Path::new("/bin/sh")));
+        assert!(find_program("sh", &RequirePath, os::env()).is_none());
+        assert_eq!(find_program("/bin/sh", &RequirePath, []), Some(Path::new("/bin/sh")));
+        assert_eq!(find_program("sh", &SearchDirs(~[Path::new("/bin")]), []),
+                   Some(Path::new("/bin/sh")));
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for a line to appear in the output of a child
+
+use comm::{Select, SharedChan};
+use io::{Buffer, BufferedReader, Reader, Timer};
+use io;
+use prelude::*;
+use str;
+
+/// Starts reading `streams` by lines in tasks of their own, returning the
+/// port on which all of their lines are received. The port is closed once
+/// every stream has ended.
+///
+/// The streams keep being read after the port is dropped, so that a child
+/// never blocks on the output nobody waits for anymore.
+pub fn watch_lines<R: Reader + Send>(streams: ~[R]) -> Port<~[u8]> {
+    let (port, chan) = SharedChan::new();
+    for stream in streams.move_iter() {
+        let chan = chan.clone();
+        do spawn {
+            let _guard = io::ignore_io_error();
+            let mut reader = BufferedReader::new(stream);
+            let mut watched = true;
+            loop {
+                match reader.read_until('\n' as u8) {
+                    Some(line) => {
+                        if watched {
+                            watched = chan.try_send(line);
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+    port
+}
+
+/**
+ * Receives lines from `lines` until one containing `pattern` arrives,
+ * returning it without its trailing whitespace. The other lines are dropped.
+ *
+ * Raises the `io_error` condition with the kind TimedOut and returns None if
+ * no such line arrived within `msecs` milliseconds. Returns None if the port
+ * was closed before.
+ */
+pub fn wait_for_line(lines: &mut Port<~[u8]>, pattern: &str, msecs: u64) -> Option<~str> {
+    let mut timer = match Timer::new() {
+        Some(timer) => timer,
+        None => return None,
+    };
+    let mut timeout = timer.oneshot(msecs);
+    loop {
+        let received = {
+            let sel = Select::new();
+            let mut line = sel.add(&mut *lines);
+            let timer = sel.add(&mut timeout);
+            if sel.wait() == timer.id {
+                None
+            } else {
+                Some(line.recv_opt())
+            }
+        };
+        match received {
+            Some(Some(line)) => {
+                match str::from_utf8(line) {
+                    Some(line) if line.contains(pattern) => {
+                        return Some(line.trim_right().to_owned())
+                    }
+                    _ => {}
+                }
+            }
+            Some(None) => return None,
+            None => {
+                io::io_error::cond.raise(io::standard_error(io::TimedOut));
+                return None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use io::TimedOut;
+    use io::mem::MemReader;
+    use io;
+    use super::{watch_lines, wait_for_line};
+
+    #[test]
+    fn test_wait_for_line() {
+        let streams = ~[MemReader::new(bytes!("starting\nListening on port 80\n").to_owned()),
+                        MemReader::new(bytes!("warning\n").to_owned())];
+        let mut lines = watch_lines(streams);
+        assert_eq!(wait_for_line(&mut lines, "Listening", 10000),
+                   Some(~"Listening on port 80"));
+        assert_eq!(wait_for_line(&mut lines, "Listening", 10000), None);
+    }
+
+    #[test]
+    fn test_wait_for_line_timeout() {
+        let (mut lines, _chan) = Chan::<~[u8]>::new();
+        match io::result(|| wait_for_line(&mut lines, "ready", 10)) {
+            Err(e) => assert_eq!(e.kind, TimedOut),
+            Ok(line) => fail!("got {:?} instead of timing out", line),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Windows-specific extensions to spawning processes
+//!
+//! The options of `ProcessOptions` which only have an effect on Windows, or
+//! which work differently there, are not part of the portable struct, and
+//! are set with the methods of `ProcessOptionsExt` instead, which code
+//! building for Windows can use:
+//!
+//! ```rust
+//! use std::run::{ProcessOptions, NoConsole};
+//! use std::run::windows::ProcessOptionsExt;
+//!
+//! let options = ProcessOptions::new().console(NoConsole).breakaway_from_job(true);
+//! ```
+
+use prelude::*;
+
+use super::{ProcessOptions, ConsoleMode, JobLimits, Mitigations, OutputEncoding, ArgQuoting};
+
+/// The options of a child which only have an effect on Windows.
+pub trait ProcessOptionsExt {
+    /// Attaches the child to the given console.
+    fn console(self, console: ConsoleMode) -> Self;
+
+    /// Adds the given flags to those the child is created with.
+    fn creation_flags(self, flags: u32) -> Self;
+
+    /// Places the child in a Job Object enforcing the given limits.
+    fn job_limits(self, limits: JobLimits) -> Self;
+
+    /// Makes the child break away from the Job Object of the current
+    /// process, if any.
+    fn breakaway_from_job(self, breakaway: bool) -> Self;
+
+    /// Transcodes the captured output of the child from the given encoding.
+    fn output_encoding(self, encoding: OutputEncoding) -> Self;
+
+    /// Starts the child on the given window station and desktop.
+    fn desktop(self, desktop: &str) -> Self;
+
+    /// Sets the security descriptors, in SDDL, of the handles of the child
+    /// and of its main thread.
+    fn security(self, process: Option<~str>, thread: Option<~str>) -> Self;
+
+    /// Starts the child with the given mitigation policies.
+    fn mitigations(self, mitigations: Mitigations) -> Self;
+
+    /// Gives the child the given command line verbatim instead of one
+    /// joined from its arguments.
+    fn raw_command_line(self, line: &str) -> Self;
+
+    /// Quotes the arguments of the child in its command line as given.
+    fn quoting(self, quoting: ArgQuoting) -> Self;
+
+    /// Runs the child in the priority class closest to the niceness `nice`:
+    /// 15 and above is idle, 5 and above below normal, -5 and below above
+    /// normal, and -15 and below high.
+    fn priority(self, nice: int) -> Self;
+
+    /// Restricts the child and its own children to the CPUs with the given
+    /// numbers, starting at 0, ignoring those beyond the number of bits of a
+    /// `uint`. This is only supported in native tasks.
+    fn cpu_affinity(self, cpus: ~[uint]) -> Self;
+}
+
+impl<'a> ProcessOptionsExt for ProcessOptions<'a> {
+    fn console(mut self, console: ConsoleMode) -> ProcessOptions<'a> {
+        self.console = console;
+        self
+    }
+
+    fn creation_flags(mut self, flags: u32) -> ProcessOptions<'a> {
+        self.creation_flags |= flags;
+        self
+    }
+
+    fn job_limits(mut self, limits: JobLimits) -> ProcessOptions<'a> {
+        self.job_limits = Some(limits);
+        self
+    }
+
+    fn breakaway_from_job(mut self, breakaway: bool) -> ProcessOptions<'a> {
+        self.breakaway_from_job = breakaway;
+        self
+    }
+
+    fn output_encoding(mut self, encoding: OutputEncoding) -> ProcessOptions<'a> {
+        self.output_encoding = encoding;
+        self
+    }
+
+    fn desktop(mut self, desktop: &str) -> ProcessOptions<'a> {
+        self.desktop = Some(desktop.to_owned());
+        self
+    }
+
+    fn security(mut self, process: Option<~str>, thread: Option<~str>) -> ProcessOptions<'a> {
+        self.process_security = process;
+        self.thread_security = thread;
+        self
+    }
+
+    fn mitigations(mut self, mitigations: Mitigations) -> ProcessOptions<'a> {
+        self.mitigations = mitigations;
+        self
+    }
+
+    fn raw_command_line(mut self, line: &str) -> ProcessOptions<'a> {
+        self.raw_command_line = Some(line.to_owned());
+        self
+    }
+
+    fn quoting(mut self, quoting: ArgQuoting) -> ProcessOptions<'a> {
+        self.quoting = quoting;
+        self
+    }
+
+    fn priority(mut self, nice: int) -> ProcessOptions<'a> {
+        self.priority = Some(nice);
+        self
+    }
+
+    fn cpu_affinity(mut self, cpus: ~[uint]) -> ProcessOptions<'a> {
+        self.cpu_affinity = Some(cpus);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prelude::*;
+    use run::{ProcessOptions, NoConsole, CrtQuoting};
+    use super::ProcessOptionsExt;
+
+    #[test]
+    fn test_process_options_ext() {
+        let options = ProcessOptions::new().console(NoConsole).creation_flags(0x10)
+                                           .creation_flags(0x200).breakaway_from_job(true)
+                                           .desktop("winsta0\\default").quoting(CrtQuoting)
+                                           .raw_command_line("cmd /c ver").priority(10)
+                                           .cpu_affinity(~[0]);
+        assert!(options.console == NoConsole);
+        assert_eq!(options.creation_flags, 0x210);
+        assert!(options.breakaway_from_job);
+        assert_eq!(options.desktop, Some(~"winsta0\\default"));
+        assert!(options.quoting == CrtQuoting);
+        assert_eq!(options.raw_command_line, Some(~"cmd /c ver"));
+        assert_eq!(options.priority, Some(10));
+        assert_eq!(options.cpu_affinity, Some(~[0u]));
+    }
+}
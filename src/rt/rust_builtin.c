@@ -365,6 +365,26 @@ rust_unset_sigprocmask() {
     // empty stub for windows to keep linker happy
 }
 
+void
+rust_set_sigprocmask(const int *signals, size_t count) {
+    // empty stub for windows to keep linker happy
+}
+
+void
+rust_reset_signal(int signum) {
+    // empty stub for windows to keep linker happy
+}
+
+void
+rust_reset_all_signals() {
+    // empty stub for windows to keep linker happy
+}
+
+void
+rust_reset_caught_signals() {
+    // empty stub for windows to keep linker happy
+}
+
 #else
 
 void
@@ -376,6 +396,49 @@ rust_unset_sigprocmask() {
     sigprocmask(SIG_SETMASK, &sset, NULL);
 }
 
+void
+rust_set_sigprocmask(const int *signals, size_t count) {
+    sigset_t sset;
+    size_t i;
+    sigemptyset(&sset);
+    for (i = 0; i < count; i++) {
+        sigaddset(&sset, signals[i]);
+    }
+    sigprocmask(SIG_SETMASK, &sset, NULL);
+}
+
+void
+rust_reset_signal(int signum) {
+    signal(signum, SIG_DFL);
+}
+
+void
+rust_reset_all_signals() {
+    int i;
+    for (i = 1; i < NSIG; i++) {
+        if (i != SIGKILL && i != SIGSTOP) {
+            signal(i, SIG_DFL);
+        }
+    }
+}
+
+void
+rust_reset_caught_signals() {
+    // Only the signals which have a handler are reset, as the ones which are
+    // ignored stay ignored across exec. This is async-signal-safe, so that a
+    // child spawned with vfork can call it.
+    int i;
+    struct sigaction sa;
+    for (i = 1; i < NSIG; i++) {
+        if (i != SIGKILL && i != SIGSTOP && sigaction(i, NULL, &sa) == 0 &&
+            sa.sa_handler != SIG_IGN && sa.sa_handler != SIG_DFL) {
+            sa.sa_handler = SIG_DFL;
+            sa.sa_flags = 0;
+            sigaction(i, &sa, NULL);
+        }
+    }
+}
+
 #endif
 
 #if defined(__WIN32__)
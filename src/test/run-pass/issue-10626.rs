@@ -31,9 +31,7 @@ pub fn main () {
     let config = process::ProcessConfig {
         program : args[0].as_slice(),
         args : &[~"child"],
-        env : None,
-        cwd : None,
-        io : &[]
+        .. process::ProcessConfig::new()
     };
 
     let mut p = process::Process::new(config).unwrap();